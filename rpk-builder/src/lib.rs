@@ -10,9 +10,9 @@ pub mod usb;
 
 pub use embassy_sync::blocking_mutex::raw::NoopRawMutex;
 pub use rpk_firmware::{
-    config, debug, firmware_functions, fixme, info, key_reporter, key_scanner, mapper,
-    norflash_ring_fs, ring_fs, usb::Configurator as UsbConfigurator, usb::State as UsbState,
-    usb::UsbBuffers,
+    config, debug, encoder, firmware_functions, fixme, indicator, info, key_reporter, key_scanner,
+    mapper, norflash_ring_fs, ring_fs, split, usb::Configurator as UsbConfigurator,
+    usb::State as UsbState, usb::UsbBuffers,
 };
 pub use rpk_macros::configure_keyboard;
 pub use static_cell::StaticCell;
@@ -1,5 +1,6 @@
 pub use core::sync::atomic::AtomicU16;
-pub use embassy_rp::{bind_interrupts, flash, gpio, init, peripherals, rom_data, usb};
+pub use embassy_futures::select;
+pub use embassy_rp::{bind_interrupts, flash, gpio, init, peripherals, rom_data, uart, usb};
 
 /// Configure and run a keyboard using the config file [`default-layout.rpk.conf`][c].
 ///
@@ -32,10 +33,10 @@ macro_rules! rp_run_keyboard {
         use rp::peripherals::{FLASH, USB};
         use rpk_builder::norflash_ring_fs::NorflashRingFs;
         use rpk_builder::StaticCell;
-        use rpk_builder::{mapper, key_scanner, ring_fs::RingFs,
+        use rpk_builder::{mapper, key_scanner, indicator, encoder, ring_fs::RingFs,
             UsbState, UsbConfigurator, UsbBuffers, config, usb};
         use rpk_builder::NoopRawMutex;
-        use core::sync::atomic::AtomicU16;
+        use core::sync::atomic::{AtomicU8, AtomicU16};
 
         type ScanChannel = key_scanner::KeyScannerChannel<NoopRawMutex, SCANNER_BUFFER_SIZE>;
         type MapperChannel = mapper::MapperChannel<NoopRawMutex, REPORT_BUFFER_SIZE>;
@@ -53,8 +54,12 @@ macro_rules! rp_run_keyboard {
         static USB_BUFFERS: StaticCell<UsbBuffers> = StaticCell::new();
         static USB_CONFIG: StaticCell<UsbConfigurator> = StaticCell::new();
         static SHARED_HID_STATE: StaticCell<UsbState> = StaticCell::new();
+        static BUS_STATE: StaticCell<usb::BusState> = StaticCell::new();
+        static BUS_HANDLER: StaticCell<usb::BusHandler> = StaticCell::new();
 
         static DEBOUNCE_TUNE: AtomicU16 = AtomicU16::new(524);
+        static DEBOUNCE_MODE: AtomicU8 = AtomicU8::new(0);
+        static STATS: mapper::stats::Stats = mapper::stats::Stats::new();
 
         bind_interrupts!(struct Irqs {
             USBCTRL_IRQ => InterruptHandler<USB>;
@@ -72,19 +77,40 @@ macro_rules! rp_run_keyboard {
             }
         }
 
+        async fn run_scanner(
+            input_pins: [Input<'static>; INPUT_N],
+            output_pins: [Output<'static>; OUTPUT_N],
+            key_scan_channel: &'static ScanChannel,
+            bus_state: &'static usb::BusState,
+        ) {
+            if DIRECT_PINS {
+                key_scanner::DirectPinScanner::new(
+                    input_pins,
+                    key_scan_channel,
+                    &DEBOUNCE_TUNE,
+                    &DEBOUNCE_MODE,
+                    bus_state,
+                ).run().await;
+            } else {
+                key_scanner::KeyScanner::new(
+                    input_pins,
+                    output_pins,
+                    key_scan_channel,
+                    &DEBOUNCE_TUNE,
+                    &DEBOUNCE_MODE,
+                    bus_state,
+                ).run::<ROW_IS_OUTPUT>().await;
+            }
+        }
+
         #[embassy_executor::task]
         async fn scanner(
             input_pins: [Input<'static>; INPUT_N],
             output_pins: [Output<'static>; OUTPUT_N],
-            key_scan_channel: &'static ScanChannel
+            key_scan_channel: &'static ScanChannel,
+            bus_state: &'static usb::BusState,
         ) {
-            let mut scanner = key_scanner::KeyScanner::new(
-                input_pins,
-                output_pins,
-                key_scan_channel,
-                &DEBOUNCE_TUNE,
-            );
-            scanner.run::<ROW_IS_OUTPUT>().await;
+            run_scanner(input_pins, output_pins, key_scan_channel, bus_state).await;
         }
 
         #[embassy_executor::task]
@@ -97,7 +123,7 @@ macro_rules! rp_run_keyboard {
             mapper::config_loader::run::<
             ROW_COUNT, COL_COUNT, LAYOUT_MAX,
             SCANNER_BUFFER_SIZE, REPORT_BUFFER_SIZE,
-            >(layout_mapping, key_scan_channel, mapper_channel, fs, &DEBOUNCE_TUNE).await;
+            >(layout_mapping, key_scan_channel, mapper_channel, fs, &DEBOUNCE_TUNE, &DEBOUNCE_MODE, &STATS).await;
         }
 
         #[embassy_executor::task]
@@ -114,9 +140,33 @@ macro_rules! rp_run_keyboard {
 
         #[embassy_executor::task]
         async fn hid_reader(
+            mapper_channel: &'static MapperChannel,
             shared_hid_reader: usb::SharedHidReader<'static, Driver<'static, USB>>,
         ) {
-            rpk_builder::usb::HidEpHandler.run(shared_hid_reader).await;
+            rpk_builder::usb::HidEpHandler::new(mapper_channel.host_leds())
+                .run(shared_hid_reader)
+                .await;
+        }
+
+        #[embassy_executor::task]
+        async fn indicators(
+            pins: [Output<'static>; INDICATOR_N],
+            mapper_channel: &'static MapperChannel,
+        ) {
+            indicator::Indicators::new(pins, &INDICATORS)
+                .run(mapper_channel.indicators())
+                .await;
+        }
+
+        #[embassy_executor::task]
+        async fn encoders(
+            a_pins: [Input<'static>; ENCODER_N],
+            b_pins: [Input<'static>; ENCODER_N],
+            key_scan_channel: &'static ScanChannel,
+        ) {
+            encoder::Encoders::new(a_pins, b_pins, &ENCODER_KEYS, key_scan_channel)
+                .run()
+                .await;
         }
 
         #[embassy_executor::task]
@@ -135,9 +185,14 @@ macro_rules! rp_run_keyboard {
         async fn main(spawner: embassy_executor::Spawner) -> ! {
             let p = rpk_builder::rp::init(Default::default());
             let (input_pins, output_pins) = config_pins!(peripherals: p);
+            let indicator_pins = config_indicator_pins!(peripherals: p);
+            let (encoder_a_pins, encoder_b_pins) = config_encoder_pins!(peripherals: p);
 
             let key_scan_channel: &'static ScanChannel = KEY_SCAN_CHANNEL.init(ScanChannel::default());
             let mapper_channel: &'static MapperChannel = MAPPER_CHANNEL.init(MapperChannel::default());
+            let bus_state: &'static usb::BusState = BUS_STATE.init(usb::BusState::new());
+
+            config_split_secondary!(peripherals: p, input_pins, output_pins, key_scan_channel, bus_state);
 
             let flash: &'static mut Flash = FLASH.init(Flash::new(p.FLASH, p.DMA_CH0));
             let fs: &'static Rfs = RFS.init(Rfs::new(flash).unwrap());
@@ -148,7 +203,8 @@ macro_rules! rp_run_keyboard {
             let usb_buffers: &'static mut UsbBuffers = USB_BUFFERS.init(UsbBuffers::default());
             let usb_config: &'static mut UsbConfigurator = USB_CONFIG.init(CONFIG_BUILDER.usb_configurator());
 
-            let usb_builder = usb_config.usb_builder(driver, usb_buffers).unwrap();
+            let mut usb_builder = usb_config.usb_builder(driver, usb_buffers).unwrap();
+            usb_builder.handler(BUS_HANDLER.init(usb::BusHandler::new(bus_state)));
 
             let (shared_hid_writer, shared_hid_reader, usb_builder) =
             CONFIG_BUILDER.shared_hid_iface(
@@ -156,7 +212,7 @@ macro_rules! rp_run_keyboard {
 
             let host_channel: &'static HostChannel = HOST_CHANNEL.init(Default::default());
 
-            let config_interface = ConfigInterface::new(fs, mapper_channel.control(), &host_channel);
+            let config_interface = ConfigInterface::new(fs, mapper_channel.control(), &host_channel, &STATS);
 
             let (config_ep, usb_builder) = CONFIG_BUILDER.cfg_ep(config_interface, usb_builder);
 
@@ -166,13 +222,19 @@ macro_rules! rp_run_keyboard {
             rpk_builder::firmware_functions::handle_reset_to_usb_boot(Some(&reset_to_usb_boot));
 
             spawner.spawn(timer(mapper_channel.timer())).unwrap();
-            spawner.spawn(scanner(input_pins, output_pins, key_scan_channel)).unwrap();
+            spawner.spawn(scanner(input_pins, output_pins, key_scan_channel, bus_state)).unwrap();
             spawner.spawn(mapper(&LAYOUT_MAPPING, key_scan_channel, mapper_channel, fs)).unwrap();
             spawner.spawn(hid_reporter(mapper_channel, shared_hid_writer)).unwrap();
-            spawner.spawn(hid_reader(shared_hid_reader)).unwrap();
+            spawner.spawn(hid_reader(mapper_channel, shared_hid_reader)).unwrap();
+            if INDICATOR_N > 0 {
+                spawner.spawn(indicators(indicator_pins, mapper_channel)).unwrap();
+            }
+            if ENCODER_N > 0 {
+                spawner.spawn(encoders(encoder_a_pins, encoder_b_pins, key_scan_channel)).unwrap();
+            }
             spawner.spawn(vendor_interface(config_ep)).unwrap();
 
-            usb.run().await;
+            run_split_primary!(peripherals: p, key_scan_channel, usb::run_device(&mut usb, bus_state))
         }
 
     };
@@ -12,6 +12,8 @@ use rpk_firmware::{
     usb::{Configurator, SHARED_REPORT_DESC, State},
 };
 
+pub use rpk_firmware::usb::{BusHandler, BusState, run_device};
+
 pub type SharedHidWriter<'d, D> = hid::HidWriter<'d, D, 34>;
 pub type SharedHidReader<'d, D> = hid::HidReader<'d, D, 10>;
 
@@ -30,6 +32,7 @@ impl ConfigBuilder {
         conf.product = Some(self.product);
         conf.serial_number = Some(self.serial_number);
         conf.max_power = self.max_power;
+        conf.supports_remote_wakeup = true;
         Configurator::new(conf)
     }
 
@@ -109,7 +112,7 @@ impl<'d, D: Driver<'d>> ConfigEndPoint<'d, D> {
                         let _ = self.write_ep.write(msg.as_slice()).await;
                     }
                     embassy_futures::select::Either::Second(key) => {
-                        key_msg.set_key(key.as_memo_bytes());
+                        key_msg.set_key(&key);
                         let _ = self.write_ep.write(key_msg.as_slice()).await;
                     }
                 }
@@ -119,21 +122,37 @@ impl<'d, D: Driver<'d>> ConfigEndPoint<'d, D> {
     }
 }
 
-pub struct HidEpHandler;
-impl HidEpHandler {
+/// The report id of the keyboard collection in [`SHARED_REPORT_DESC`]; which holds the LED
+/// output report.
+const KEYBOARD_REPORT_ID: u8 = 6;
+
+pub struct HidEpHandler<'c> {
+    host_leds: &'c mapper::HostLeds,
+}
+impl<'c> HidEpHandler<'c> {
+    pub fn new(host_leds: &'c mapper::HostLeds) -> Self {
+        Self { host_leds }
+    }
+
     pub async fn run<'d, D: Driver<'d>>(mut self, ep_reader: SharedHidReader<'d, D>) {
-        ep_reader.run(false, &mut self).await;
+        ep_reader.run(true, &mut self).await;
     }
 }
 
-impl RequestHandler for HidEpHandler {
+impl RequestHandler for HidEpHandler<'_> {
     fn get_report(&mut self, id: ReportId, _buf: &mut [u8]) -> Option<usize> {
         crate::info!("Get report for {:?}", id);
         None
     }
 
     fn set_report(&mut self, id: ReportId, data: &[u8]) -> OutResponse {
-        crate::info!("Set report for {:?}: {:?}", id, data);
+        match (id, data) {
+            (ReportId::Out(KEYBOARD_REPORT_ID), [KEYBOARD_REPORT_ID, leds]) => {
+                self.host_leds.set(*leds);
+            }
+            (ReportId::Out(0), [leds]) => self.host_leds.set(*leds),
+            _ => crate::info!("Set report for {:?}: {:?}", id, data),
+        }
         OutResponse::Accepted
     }
 
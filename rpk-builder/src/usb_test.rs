@@ -1,14 +1,16 @@
 extern crate std;
 use super::*;
+use core::sync::atomic::{AtomicBool, AtomicUsize};
+
 use embassy_futures::{
     block_on,
-    select::{select3, Either3},
+    select::{select, select3, Either, Either3},
 };
 use embassy_time::Timer;
 use rpk_common::usb_vendor_message as msg;
 use rpk_firmware::{
     flash_test_stub::NorFlashStub,
-    mapper::ControlSignal,
+    mapper::{ControlSignal, HostLeds},
     norflash_ring_fs::NorflashRingFs,
     ring_fs::RingFs,
     usb_test_stub::{MyDriver, MyEndpointIn, MyEndpointOut},
@@ -35,10 +37,11 @@ macro_rules! setup {
 
             let $messages = ep_in.messages.clone();
             let host_channel = Default::default();
+            let stats = Default::default();
             let mut $cfg_ep = ConfigEndPoint::<'_, MyDriver> {
                 write_ep: ep_in,
                 read_ep: ep_out,
-                config_interface: ConfigInterface::new(&$fs, &ctl_sig, &host_channel),
+                config_interface: ConfigInterface::new(&$fs, &ctl_sig, &host_channel, &stats),
             };
             $x
         });
@@ -82,3 +85,57 @@ fn config_end_point() {
     }
     );
 }
+
+#[test]
+fn hid_ep_handler_host_leds() {
+    block_on(async {
+        let host_leds = HostLeds::default();
+        let ep_out = MyEndpointOut::default();
+        ep_out.messages.try_send(std::vec![6, 2]).unwrap();
+        let offset = AtomicUsize::new(0);
+        let boot_protocol = AtomicBool::new(false);
+        let reader = SharedHidReader::<MyDriver>::new(ep_out, &offset, &boot_protocol);
+
+        match select3(
+            HidEpHandler::new(&host_leds).run(reader),
+            host_leds.wait(),
+            Timer::after_millis(200),
+        )
+        .await
+        {
+            Either3::First(_) => panic!("Unexpected run end"),
+            Either3::Second(leds) => assert_eq!(leds, 2),
+            Either3::Third(_) => panic!("Timed out"),
+        }
+    });
+}
+
+#[test]
+fn hid_ep_handler_set_report() {
+    let host_leds = HostLeds::default();
+    let mut handler = HidEpHandler::new(&host_leds);
+
+    handler.set_report(ReportId::Out(5), &[5, 1]);
+    handler.set_report(ReportId::Out(6), &[6]);
+
+    block_on(async {
+        if let Either::First(leds) = select(host_leds.wait(), Timer::after_millis(10)).await {
+            panic!("Unexpected leds {leds}");
+        }
+
+        handler.set_report(ReportId::Out(6), &[6, 3]);
+
+        match select(host_leds.wait(), Timer::after_millis(10)).await {
+            Either::First(leds) => assert_eq!(leds, 3),
+            Either::Second(_) => panic!("Timed out"),
+        }
+
+        // boot protocol
+        handler.set_report(ReportId::Out(0), &[1]);
+
+        match select(host_leds.wait(), Timer::after_millis(10)).await {
+            Either::First(leds) => assert_eq!(leds, 1),
+            Either::Second(_) => panic!("Timed out"),
+        }
+    });
+}
@@ -13,12 +13,65 @@ pub const DUAL_ACTION_TIMEOUT: u16 = 3;
 pub const DUAL_ACTION_TIMEOUT2: u16 = 4;
 pub const DEBOUNCE_SETTLE_TIME: u16 = 5;
 pub const TAPDANCE_TAP_TIMEOUT: u16 = 6;
-pub const LAST_TIMEOUT: u16 = 6;
+pub const COMBO_TIMEOUT: u16 = 7;
+pub const LEADER_TIMEOUT: u16 = 8;
+pub const CAPS_WORD_TIMEOUT: u16 = 9;
+pub const DUAL_ACTION_MODE: u16 = 10;
+pub const DUAL_ACTION_REQUIRE_PRIOR_IDLE: u16 = 11;
+pub const DEBOUNCE_MODE: u16 = 12;
+pub const LAST_TIMEOUT: u16 = 12;
 
 pub const DUAL_ACTION_TIMEOUT_DEFAULT: u16 = 180; // 180ms
 pub const DUAL_ACTION_TIMEOUT2_DEFAULT: u16 = 20; // 20ms
 pub const DEBOUNCE_SETTLE_TIME_DEFAULT: u16 = (20.0 * 65535.0 / 2500.0) as u16; // 20.0 ms
 pub const TAPDANCE_TAP_TIMEOUT_DEFAULT: u16 = 180; // 180ms
+pub const COMBO_TIMEOUT_DEFAULT: u16 = 50; // 50ms
+pub const LEADER_TIMEOUT_DEFAULT: u16 = 1000; // 1s
+pub const CAPS_WORD_TIMEOUT_DEFAULT: u16 = 5000; // 5s
+pub const DUAL_ACTION_MODE_DEFAULT: u16 = dual_action_modes::TIMEOUT;
+pub const DUAL_ACTION_REQUIRE_PRIOR_IDLE_DEFAULT: u16 = 0; // off
+pub const DEBOUNCE_MODE_DEFAULT: u16 = debounce_modes::EAGER;
+
+/// How a dual action decides between tap and hold when other keys are pressed while it is down.
+pub mod dual_action_modes {
+    /// Decide using `dual_action_timeout` and `dual_action_timeout2`.
+    pub const TIMEOUT: u16 = 0;
+    /// Hold as soon as another key is pressed.
+    pub const HOLD_ON_OTHER_KEY_PRESS: u16 = 1;
+    /// Hold when another key is pressed and released.
+    pub const PERMISSIVE_HOLD: u16 = 2;
+    /// Like `PERMISSIVE_HOLD` but hold when another key is pressed after `dual_action_timeout2`.
+    pub const BALANCED: u16 = 3;
+    /// Hold when a key on the other hand is pressed; tap when a key on the same hand is pressed.
+    pub const BILATERAL: u16 = 4;
+
+    pub const NAMES: [&str; 5] = [
+        "timeout",
+        "hold_on_other_key_press",
+        "permissive_hold",
+        "balanced",
+        "bilateral",
+    ];
+
+    /// The hand of a key position. Positions not assigned to a hand are `NONE`.
+    pub mod hands {
+        pub const NONE: u8 = 0;
+        pub const LEFT: u8 = 1;
+        pub const RIGHT: u8 = 2;
+    }
+}
+
+/// How the key scanner reports a key that changed state while its switch bounces.
+pub mod debounce_modes {
+    /// Report the change straight away then ignore the switch until it settles.
+    pub const EAGER: u16 = 0;
+    /// Report the change once the switch has settled.
+    pub const DEFER: u16 = 1;
+    /// Report a press straight away and a release once the switch has settled.
+    pub const ASYMMETRIC: u16 = 2;
+
+    pub const NAMES: [&str; 3] = ["eager", "defer", "asymmetric"];
+}
 
 pub const COMPOSITE_BIT: u16 = 0x0100;
 pub const COMPOSITE_PART_BIT: u16 = 0x0200;
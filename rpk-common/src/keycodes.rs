@@ -10,8 +10,12 @@ pub mod key_range {
 
     pub const BASIC_MIN: u16 = 0x4;
     pub const BASIC_A: u16 = 0x4;
+    pub const BASIC_Z: u16 = 0x1d;
     pub const BASIC_1: u16 = 0x1e;
     pub const BASIC_0: u16 = 0x27;
+    pub const BASIC_BACKSPACE: u16 = 0x2a;
+    pub const BASIC_MINUS: u16 = 0x2d;
+    pub const BASIC_DELETE: u16 = 0x4c;
     pub const BASIC_MAX: u16 = 0xfe;
     pub const MODIFIER_MIN: u16 = 0xe0;
     pub const MODIFIER_MAX: u16 = 0xe7;
@@ -44,6 +48,12 @@ pub mod key_range {
     pub const FW_CLEAR_ALL: u16 = FIRMWARE_MIN + 2;
     pub const FW_CLEAR_LAYERS: u16 = FIRMWARE_MIN + 3;
     pub const FW_STOP_ACTIVE: u16 = FIRMWARE_MIN + 4;
+    pub const FW_CAPS_WORD: u16 = FIRMWARE_MIN + 5;
+    pub const FW_DYN_MACRO_RECORD: u16 = FIRMWARE_MIN + 0x10;
+    pub const FW_DYN_MACRO_RECORD_END: u16 =
+        FW_DYN_MACRO_RECORD + super::dyn_macro::COUNT as u16 - 1;
+    pub const FW_DYN_MACRO_PLAY: u16 = FIRMWARE_MIN + 0x20;
+    pub const FW_DYN_MACRO_PLAY_END: u16 = FW_DYN_MACRO_PLAY + super::dyn_macro::COUNT as u16 - 1;
 
     pub const MOUSE_BUTTON: u16 = 0;
     pub const MOUSE_BUTTON_END: u16 = 7;
@@ -68,4 +78,36 @@ pub mod macro_types {
     pub const RELEASE: u16 = 5;
     pub const DELAY: u16 = 6;
     pub const TAPDANCE: u16 = 7;
+    pub const COMBO: u16 = 8;
+    pub const LEADER: u16 = 9;
+    pub const LEADER_SEQUENCE: u16 = 10;
+    pub const CAPS_WORD_KEYS: u16 = 11;
+    pub const HANDS: u16 = 12;
+    pub const DYN_MACRO: u16 = 13;
+    pub const LED_LAYERS: u16 = 14;
+    pub const IF_LED: u16 = 15;
+
+    pub const COMBO_MAX_KEYS: usize = 4;
+    pub const LEADER_MAX_KEYS: usize = 5;
+}
+
+/// The keyboard LEDs set by the host in the HID LED output report.
+pub mod leds {
+    pub const NUM_LOCK: u8 = 1;
+    pub const CAPS_LOCK: u8 = 2;
+    pub const SCROLL_LOCK: u8 = 4;
+    pub const COMPOSE: u8 = 8;
+    pub const KANA: u8 = 16;
+
+    /// The config names of the LEDs in bit order.
+    pub const NAMES: [&str; 5] = ["numlock", "capslock", "scrolllock", "compose", "kana"];
+}
+
+/// Dynamic macros are recorded on the keyboard. Each recorded item is a keycode press, a keycode
+/// release (`RELEASE | keycode`) or a delay (`DELAY | milliseconds`).
+pub mod dyn_macro {
+    pub const COUNT: usize = 4;
+    pub const RELEASE: u16 = 0x4000;
+    pub const DELAY: u16 = 0x8000;
+    pub const MAX_DELAY: u16 = DELAY - 1;
 }
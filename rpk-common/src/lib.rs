@@ -13,7 +13,7 @@ pub mod usb_vendor_message;
 /// This constant defines the protocol version used by keyboard configuration objects generated by the
 /// [`rpk-config`](https://docs.rs/rpk-config) tool.
 /// It's essential for compatibility between different versions of the tool and its consumers.
-pub const PROTOCOL_VERSION: u16 = 1;
+pub const PROTOCOL_VERSION: u16 = 2;
 
 /// This function takes two 16-bit unsigned integers, `n1` and `n2`, and combines them into a 32-bit
 /// floating-point number.
@@ -5,6 +5,9 @@ pub const RESET_TO_USB_BOOT: u8 = 4;
 pub const READ_FILE_BY_INDEX: u8 = 5;
 pub const FETCH_STATS: u8 = 6;
 pub const SCAN_KEYS: u8 = 7;
+pub const READ_FILE_DATA: u8 = 8;
+pub const ACTIVATE_FILE_BY_INDEX: u8 = 9;
+pub const ACTIVATE_FILE_BY_LOCATION: u8 = 10;
 
 /// the maximum allowed size of a usb bulk message.
 pub const MAX_BULK_LEN: u16 = 64;
@@ -13,4 +16,13 @@ pub mod host_recv {
     pub const FILE_INFO: u8 = 0;
     pub const STATS: u8 = 1;
     pub const KEY_SCAN: u8 = 2;
+    pub const FILE_DATA: u8 = 3;
+    pub const ACTIVATE: u8 = 4;
+}
+
+pub mod file_type {
+    pub const CONFIG: u8 = 0;
+    pub const DYN_MACRO: u8 = 1;
+    pub const CONFIG_SOURCE: u8 = 2;
+    pub const ACTIVE: u8 = 3;
 }
@@ -2,14 +2,17 @@ use clap::{Args, Parser, Subcommand, ValueEnum};
 use rpk_common::keycodes::key_range;
 use rpk_config::{
     ConfigError,
-    compiler::KeyboardConfig,
-    keycodes, pretty_compile,
-    vendor_coms::{self, FileInfo, KeyboardCtl},
+    compiler::{self, KeyboardConfig},
+    decompiler,
+    dyn_macro::DynMacro,
+    heatmap::Heatmap,
+    keycodes, pretty_compile, pretty_warnings,
+    vendor_coms::{self, FileInfo, FileType, KeyboardCtl, KeyboardStats},
 };
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fs,
-    io::{self, Write},
+    io::{self, IsTerminal, Write},
     path::{Path, PathBuf},
     process,
     sync::Arc,
@@ -17,8 +20,10 @@ use std::{
 };
 
 use anyhow::{Result, anyhow};
+use chrono::Local;
 
 mod init_builder;
+mod simulate;
 
 fn parse_hex(v: &Option<&str>) -> Result<Option<u16>> {
     if let Some(v) = v {
@@ -75,14 +80,32 @@ enum Commands {
     USBList,
     /// List files
     Ls(LsArgs),
+    /// Download recorded dynamic macros as config text
+    DynMacros(DynMacrosArgs),
     /// Show keyboard statistics
     Stats(StatsArgs),
+    /// Show key presses and releases as they are scanned
+    Monitor(MonitorArgs),
+    /// Show how often each key has been pressed
+    Heatmap(HeatmapArgs),
     /// Reset (restart) the keyboard
     Reset(ResetArgs),
     /// Validate a keyboard configuation file
     Validate(ValidateArgs),
+    /// Simulate a keyboard running a configuation
+    Simulate(SimulateArgs),
+    /// Run the test cases in a keyboard configuation test file
+    Test(TestArgs),
     /// Upload keyboard configuation
     Upload(UploadArgs),
+    /// Download the keyboard configuation source saved by upload --with-source
+    Pull(PullArgs),
+    /// Convert a keyboard configuation binary back into config text
+    Decompile(DecompileArgs),
+    /// Switch to a previously uploaded keyboard configuation
+    Activate(ActivateArgs),
+    /// Switch back to the keyboard configuation uploaded before the active one
+    Rollback(RollbackArgs),
     /// Initialize a new keyboard project
     Init(InitArgs),
 }
@@ -156,6 +179,48 @@ struct StatsArgs {
     config_file: Option<PathBuf>,
 }
 
+#[derive(Args)]
+struct MonitorArgs {
+    /// Include extra information
+    #[clap(long, short)]
+    verbose: bool,
+
+    /// Draw the key matrix showing which keys are down and which have been pressed
+    #[clap(long, short)]
+    grid: bool,
+
+    /// Use config file to select keyboard and name keys
+    config_file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct HeatmapArgs {
+    /// Include extra information
+    #[clap(long, short)]
+    verbose: bool,
+
+    /// Also write the counts to a CSV file
+    #[clap(long)]
+    csv: Option<PathBuf>,
+
+    /// Also write the counts to a JSON file
+    #[clap(long)]
+    json: Option<PathBuf>,
+
+    /// Use config file to select keyboard and name keys
+    config_file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct DynMacrosArgs {
+    /// Include extra information
+    #[clap(long, short)]
+    verbose: bool,
+
+    /// Use config file to select keyboard
+    config_file: Option<PathBuf>,
+}
+
 #[derive(Args)]
 struct ResetArgs {
     /// Reset keyboard in to usb boot mode
@@ -192,11 +257,7 @@ impl DeviceFinder {
         Ok(Self {
             vendor_id,
             product_id,
-            serial_number: if cli.serial_number.is_none() {
-                String::new()
-            } else {
-                cli.serial_number.as_ref().unwrap().to_owned()
-            },
+            serial_number: cli.serial_number.clone().unwrap_or_default(),
         })
     }
 
@@ -265,10 +326,14 @@ impl DeviceFinder {
         let err = match fs::read_to_string(file) {
             Ok(src) => {
                 let config = compile_file(file, src.as_str())?;
+                if !args.no_warn {
+                    pretty_warnings(file, &src, config.warnings());
+                }
                 let bin = config.serialize();
                 let finder = DeviceFinder::from_config(&config, self)?;
                 let ctl = finder.get_keyboard()?;
-                return ctl.save_config(bin.as_slice(), file.file_name());
+                let source = args.with_source.then_some(src.as_str());
+                return ctl.save_config(bin.as_slice(), file.file_name(), source);
             }
 
             Err(err) => err.to_string(),
@@ -325,10 +390,11 @@ impl DeviceFinder {
 
         let iter = ctl.list_files().filter(|i| {
             if let Some(dups) = &mut dups {
-                if dups.contains(&i.filename) {
+                let key = (i.filename.to_owned(), i.file_type);
+                if dups.contains(&key) {
                     false
                 } else {
-                    dups.insert(i.filename.to_owned());
+                    dups.insert(key);
                     true
                 }
             } else {
@@ -347,6 +413,152 @@ impl DeviceFinder {
         Ok(())
     }
 
+    fn pull(&self, args: &PullArgs) -> Result<()> {
+        let (dev, ctl) = self.get_keyboard_controller(&args.config_file)?;
+
+        if args.verbose {
+            print_dev_info(&dev);
+        }
+
+        let ctl2 = ctl.clone();
+
+        spawn(move || {
+            ctl2.listen();
+        });
+
+        let files: Vec<FileInfo> = ctl.list_files().collect();
+        let config = find_config(&ctl, &files, args.index)?;
+
+        // the source is saved straight after its config
+        let source = files
+            .iter()
+            .find(|i| {
+                i.file_type == FileType::ConfigSource
+                    && i.index + 1 == config.index
+                    && i.filename == config.filename
+            })
+            .ok_or_else(|| {
+                anyhow!(
+                    "No source saved for {} ({}); upload it using --with-source",
+                    config.filename,
+                    config.index
+                )
+            })?;
+
+        let src = vendor_coms::config_source_from_file(
+            ctl.fetch_file_by_location(source.location)?.as_slice(),
+        )?;
+
+        match &args.output {
+            Some(file) => fs::write(file, src)?,
+            None => print!("{src}"),
+        }
+
+        Ok(())
+    }
+
+    fn decompile(&self, args: &DecompileArgs) -> Result<()> {
+        let data: Vec<u16> = match &args.binary {
+            Some(file) => fs::read(file)
+                .map_err(|err| anyhow!("Failed to read \"{}\": {err}", file.display()))?
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .collect(),
+            None => {
+                let (dev, ctl) = self.get_keyboard_controller(&args.config_file)?;
+
+                if args.verbose {
+                    print_dev_info(&dev);
+                }
+
+                let ctl2 = ctl.clone();
+
+                spawn(move || {
+                    ctl2.listen();
+                });
+
+                let files: Vec<FileInfo> = ctl.list_files().collect();
+                let config = find_config(&ctl, &files, args.index)?;
+
+                vendor_coms::config_from_file(
+                    ctl.fetch_file_by_location(config.location)?.as_slice(),
+                )?
+            }
+        };
+
+        let src = decompiler::decompile(&data)?;
+
+        // files on the keyboard may be padded after the layout
+        match compiler::compile(PathBuf::from("<decompiled>"), &src) {
+            Ok(config) if data.starts_with(&config.serialize()) => {}
+            _ => eprintln!("warning: the decompiled config does not compile to the same binary"),
+        }
+
+        match &args.output {
+            Some(file) => fs::write(file, src)?,
+            None => print!("{src}"),
+        }
+
+        Ok(())
+    }
+
+    fn activate(&self, args: &ActivateArgs) -> Result<()> {
+        let (dev, ctl) = self.get_keyboard_controller(&args.config_file)?;
+
+        if args.verbose {
+            print_dev_info(&dev);
+        }
+
+        let ctl2 = ctl.clone();
+
+        spawn(move || {
+            ctl2.listen();
+        });
+
+        let files: Vec<FileInfo> = ctl.list_files().collect();
+        let Some(info) = files.iter().find(|i| i.index == args.index) else {
+            return Err(anyhow!("File {} not found", args.index));
+        };
+        if info.file_type != FileType::Config {
+            return Err(anyhow!("File {} is not a config file", info.index));
+        }
+
+        ctl.activate_file_by_location(info.location)?;
+        println!("Activated {} ({})", info.filename, info.index);
+
+        Ok(())
+    }
+
+    fn rollback(&self, args: &RollbackArgs) -> Result<()> {
+        let (dev, ctl) = self.get_keyboard_controller(&args.config_file)?;
+
+        if args.verbose {
+            print_dev_info(&dev);
+        }
+
+        let ctl2 = ctl.clone();
+
+        spawn(move || {
+            ctl2.listen();
+        });
+
+        let files: Vec<FileInfo> = ctl.list_files().collect();
+        let active = ctl
+            .active_config(&files)?
+            .ok_or_else(|| anyhow!("No config file found"))?;
+        let Some(info) = files
+            .iter()
+            .find(|i| i.file_type == FileType::Config && i.index > active.index)
+        else {
+            return Err(anyhow!("No older config file to roll back to"));
+        };
+
+        ctl.activate_file_by_location(info.location)?;
+        println!("Activated {} ({})", info.filename, info.index);
+
+        Ok(())
+    }
+
     fn stats(&self, args: &StatsArgs) -> Result<()> {
         let (dev, ctl) = self.get_keyboard_controller(&args.config_file)?;
 
@@ -362,7 +574,157 @@ impl DeviceFinder {
 
         let stats = ctl.fetch_stats()?;
 
-        println!("Up since FIXME ({:?})", &stats.uptime);
+        let since = Local::now() - chrono::Duration::from_std(stats.uptime)?;
+        println!(
+            "Up since {} ({}s)",
+            since.format("%Y-%m-%d %H:%M:%S"),
+            stats.uptime.as_secs()
+        );
+
+        if args.verbose {
+            let src = match &args.config_file {
+                Some(file) => fs::read_to_string(file)?,
+                None => String::new(),
+            };
+            let config = match &args.config_file {
+                Some(file) => Some(compile_file(file, src.as_str())?),
+                None => None,
+            };
+            print_stats(&stats, config.as_ref());
+        }
+
+        Ok(())
+    }
+
+    fn monitor(&self, args: &MonitorArgs) -> Result<()> {
+        let (dev, ctl) = self.get_keyboard_controller(&args.config_file)?;
+
+        if args.verbose {
+            print_dev_info(&dev);
+        }
+
+        let src = match &args.config_file {
+            Some(file) => fs::read_to_string(file)?,
+            None => String::new(),
+        };
+        let config = match &args.config_file {
+            Some(file) => Some(compile_file(file, src.as_str())?),
+            None => None,
+        };
+        let mut grid = MatrixGrid::new(config.as_ref().map_or((0, 0), |c| c.matrix_size()));
+
+        let keys = ctl.key_scans()?;
+
+        let ctl2 = ctl.clone();
+
+        spawn(move || {
+            ctl2.listen();
+        });
+
+        let ctl2 = ctl.clone();
+        let stop = move || {
+            let _ = ctl2.log_keys(false);
+            process::exit(0);
+        };
+        ctrlc::set_handler(stop.clone())?;
+        spawn(move || {
+            let _ = io::stdin().read_line(&mut String::new());
+            stop();
+        });
+
+        let _logging = KeyLogging::start(ctl.clone())?;
+        eprintln!("Monitoring keys; press Enter or Ctrl-C to stop");
+
+        for key in keys {
+            let names = config.as_ref().map_or_else(Vec::new, |c| {
+                c.position_names((key.row as u16) << 8 | key.col as u16)
+            });
+            let line = format!(
+                "{:>10.3} {:<4} row {:>2} col {:>2} {}",
+                key.time.as_secs_f64(),
+                if key.is_down { "down" } else { "up" },
+                key.row,
+                key.col,
+                names.join(" ")
+            );
+            if args.grid {
+                grid.set(key.row, key.col, key.is_down);
+                print!("\x1b[H\x1b[2J{}\n{}\n", grid.render(), line.trim_end());
+            } else {
+                println!("{}", line.trim_end());
+            }
+            io::stdout().flush()?;
+        }
+
+        Ok(())
+    }
+
+    fn heatmap(&self, args: &HeatmapArgs) -> Result<()> {
+        let (dev, ctl) = self.get_keyboard_controller(&args.config_file)?;
+
+        if args.verbose {
+            print_dev_info(&dev);
+        }
+
+        let ctl2 = ctl.clone();
+
+        spawn(move || {
+            ctl2.listen();
+        });
+
+        let stats = ctl.fetch_stats()?;
+        if stats.key_presses.is_empty() {
+            return Err(anyhow!("The keyboard firmware does not count key presses"));
+        }
+
+        let src = match &args.config_file {
+            Some(file) => fs::read_to_string(file)?,
+            None => String::new(),
+        };
+        let config = match &args.config_file {
+            Some(file) => Some(compile_file(file, src.as_str())?),
+            None => None,
+        };
+        let heatmap = Heatmap::new(&stats, config.as_ref());
+
+        println!("{}", heatmap.to_text());
+
+        if let Some(file) = &args.csv {
+            fs::write(file, heatmap.to_csv())?;
+        }
+        if let Some(file) = &args.json {
+            fs::write(file, heatmap.to_json())?;
+        }
+
+        Ok(())
+    }
+
+    fn dyn_macros(&self, args: &DynMacrosArgs) -> Result<()> {
+        let (dev, ctl) = self.get_keyboard_controller(&args.config_file)?;
+
+        if args.verbose {
+            print_dev_info(&dev);
+        }
+
+        let ctl2 = ctl.clone();
+
+        spawn(move || {
+            ctl2.listen();
+        });
+
+        let mut dups = HashSet::new();
+        let mut files: Vec<FileInfo> = ctl
+            .list_files()
+            .filter(|i| i.file_type == FileType::DynMacro && dups.insert(i.filename.to_owned()))
+            .collect();
+        files.sort_by(|a, b| a.filename.as_str().cmp(b.filename.as_str()));
+
+        for info in files {
+            let data = ctl.read_file(info.location, 0, info.length)?;
+            if let Some(mac) = DynMacro::from_file(&data) {
+                println!("{} = {}", mac.name(), mac.to_config_text());
+            }
+        }
 
         Ok(())
     }
@@ -401,10 +763,81 @@ fn list_files(iter: impl IntoIterator<Item = FileInfo>, verbose: bool) {
 
 #[derive(Args)]
 struct UploadArgs {
+    /// Also save the config file, compressed, on the keyboard so it can be recovered using pull
+    #[clap(long, short)]
+    with_source: bool,
+
+    /// Do not report warnings about the config
+    #[clap(long)]
+    no_warn: bool,
+
     /// keyboard config description file
     file: PathBuf,
 }
 
+#[derive(Args)]
+struct ActivateArgs {
+    /// Include extra information
+    #[clap(long, short)]
+    verbose: bool,
+
+    /// The index of the config file to activate (see ls --old)
+    index: u32,
+
+    /// Use config file to select keyboard
+    config_file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct RollbackArgs {
+    /// Include extra information
+    #[clap(long, short)]
+    verbose: bool,
+
+    /// Use config file to select keyboard
+    config_file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct PullArgs {
+    /// Include extra information
+    #[clap(long, short)]
+    verbose: bool,
+
+    /// The index of the config file to pull (see ls); defaults to the active config
+    #[clap(long, short)]
+    index: Option<u32>,
+
+    /// Write the config to this file instead of stdout
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+
+    /// Use config file to select keyboard
+    config_file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct DecompileArgs {
+    /// Include extra information
+    #[clap(long, short)]
+    verbose: bool,
+
+    /// Decompile this file of little-endian layout words instead of a config on the keyboard
+    #[clap(long, short)]
+    binary: Option<PathBuf>,
+
+    /// The index of the config file to decompile (see ls); defaults to the active config
+    #[clap(long, short)]
+    index: Option<u32>,
+
+    /// Write the config to this file instead of stdout
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+
+    /// Use config file to select keyboard
+    config_file: Option<PathBuf>,
+}
+
 #[derive(Args)]
 struct InitArgs {
     /// keyboard config description file
@@ -421,8 +854,118 @@ struct ValidateArgs {
     #[clap(long, short)]
     verbose: bool,
 
+    /// Do not report warnings about the config
+    #[clap(long)]
+    no_warn: bool,
+
+    /// keyboard config description file
+    file: PathBuf,
+}
+
+#[derive(Args)]
+struct SimulateArgs {
+    /// keyboard config description file
+    file: PathBuf,
+
+    /// File of key presses and releases to run; defaults to reading from stdin
+    script: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct TestArgs {
     /// keyboard config description file
     file: PathBuf,
+
+    /// File of test cases; defaults to the config file with a `.test` extension
+    tests: Option<PathBuf>,
+}
+
+fn print_stats(stats: &KeyboardStats, config: Option<&KeyboardConfig>) {
+    println!(
+        "Dual actions: {} tap, {} hold",
+        stats.dual_action_taps, stats.dual_action_holds
+    );
+    println!(
+        "High-water marks: memo stack {}, macro stack {} (words)",
+        stats.memo_high_water, stats.macro_stack_high_water
+    );
+    println!("Report overflows: {}", stats.report_overflows);
+
+    println!("Layer active time:");
+    for (i, t) in stats.layer_active.iter().enumerate() {
+        if t.is_zero() {
+            continue;
+        }
+        let name = config
+            .and_then(|c| c.layer_name(i as u16))
+            .map_or_else(|| i.to_string(), |n| n.to_string());
+        println!("  {:<12} {:.1}s", name, t.as_secs_f64());
+    }
+
+    println!("Key presses:");
+    for (row, counts) in stats
+        .key_presses
+        .chunks(stats.cols.max(1) as usize)
+        .enumerate()
+    {
+        let counts: Vec<String> = counts.iter().map(|n| format!("{n:>6}")).collect();
+        println!("{row:>3} {}", counts.join(""));
+    }
+}
+
+/// Turns key logging off when dropped; such as when monitoring stops on an error.
+struct KeyLogging(Arc<KeyboardCtl<nusb::Interface>>);
+impl KeyLogging {
+    fn start(ctl: Arc<KeyboardCtl<nusb::Interface>>) -> Result<Self> {
+        ctl.log_keys(true)?;
+        Ok(Self(ctl))
+    }
+}
+impl Drop for KeyLogging {
+    fn drop(&mut self) {
+        let _ = self.0.log_keys(false);
+    }
+}
+
+/// The state of each key in the matrix while monitoring.
+struct MatrixGrid {
+    rows: u8,
+    cols: u8,
+    keys: HashMap<(u8, u8), bool>,
+}
+impl MatrixGrid {
+    fn new((rows, cols): (u8, u8)) -> Self {
+        Self {
+            rows,
+            cols,
+            keys: Default::default(),
+        }
+    }
+
+    fn set(&mut self, row: u8, col: u8, is_down: bool) {
+        self.rows = self.rows.max(row + 1);
+        self.cols = self.cols.max(col + 1);
+        self.keys.insert((row, col), is_down);
+    }
+
+    /// Draw the matrix; `#` is down, `o` has been pressed and `.` has not been pressed yet.
+    fn render(&self) -> String {
+        let mut out = String::from("   ");
+        for col in 0..self.cols {
+            out.push_str(&format!("{col:>3}"));
+        }
+        for row in 0..self.rows {
+            out.push_str(&format!("\n{row:>3}"));
+            for col in 0..self.cols {
+                out.push_str(match self.keys.get(&(row, col)) {
+                    Some(true) => "  #",
+                    Some(false) => "  o",
+                    None => "  .",
+                });
+            }
+        }
+        out
+    }
 }
 
 fn print_dev_info(dev: &nusb::DeviceInfo) {
@@ -438,6 +981,23 @@ fn print_dev_info(dev: &nusb::DeviceInfo) {
     );
 }
 
+/// Find the config file numbered `index` or the active config.
+fn find_config<'a>(
+    ctl: &KeyboardCtl<nusb::Interface>,
+    files: &'a [FileInfo],
+    index: Option<u32>,
+) -> Result<&'a FileInfo> {
+    let config = match index {
+        Some(index) => files.iter().find(|i| i.index == index),
+        None => ctl.active_config(files)?,
+    }
+    .ok_or_else(|| anyhow!("Config file not found"))?;
+    if config.file_type != FileType::Config {
+        return Err(anyhow!("File {} is not a config file", config.index));
+    }
+    Ok(config)
+}
+
 fn compile_error(file: &Path, err: &str) -> Result<()> {
     Err(anyhow!(
         "Failed to compile \"{}\"!\n    {}",
@@ -462,11 +1022,16 @@ fn validate(args: &ValidateArgs) -> Result<()> {
     match fs::read_to_string(file) {
         Ok(src) => {
             let conf = compile_file(file, &src)?;
+            if !args.no_warn {
+                pretty_warnings(file, &src, conf.warnings());
+            }
             if args.verbose {
                 let len = vendor_coms::file_name_iter(file.file_name()).1;
                 println!("binary size: {}", conf.serialize().len() * 2 + 18 + len);
                 println!("layers:      {}", conf.layer_count());
                 println!("macros:      {}", conf.macro_count());
+                println!("combos:      {}", conf.combo_count());
+                println!("leaders:     {}", conf.leader_sequence_count());
             }
             Ok(())
         }
@@ -475,6 +1040,100 @@ fn validate(args: &ValidateArgs) -> Result<()> {
     }
 }
 
+fn simulate(args: &SimulateArgs) -> Result<()> {
+    let file = &args.file;
+    let src = match fs::read_to_string(file) {
+        Ok(src) => src,
+        Err(err) => return compile_error(file, err.to_string().as_str()),
+    };
+    let mut config = compile_file(file, &src)?;
+    let resources = simulate::Resources::default();
+    let mut sim = simulate::Simulator::new(&resources, &mut config)?;
+
+    let run_line = |sim: &mut simulate::Simulator, line: &str| -> Result<()> {
+        for step in simulate::parse_script(&config, line)? {
+            for out in sim.run(step)? {
+                println!("{:>8}ms {:?}", out.time, out.event);
+                for report in out.reports {
+                    println!("            {}", simulate::describe_report(&report));
+                }
+            }
+        }
+        Ok(())
+    };
+
+    if let Some(script) = &args.script {
+        let text = fs::read_to_string(script)
+            .map_err(|err| anyhow!("Failed to read \"{}\": {err}", script.display()))?;
+        for (i, line) in text.lines().enumerate() {
+            run_line(&mut sim, line)
+                .map_err(|err| anyhow!("{}:{}: {err}", script.display(), i + 1))?;
+        }
+        return Ok(());
+    }
+
+    let interactive = io::stdin().is_terminal();
+    if interactive {
+        println!("Enter press <key>, release <key>, tap <key> or wait <ms>; Ctrl-D to exit");
+    }
+    let mut line = String::new();
+    loop {
+        if interactive {
+            print!("{}ms> ", sim.now());
+            io::stdout().flush()?;
+        }
+        line.clear();
+        if io::stdin().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        if let Err(err) = run_line(&mut sim, &line) {
+            if !interactive {
+                return Err(err);
+            }
+            eprintln!("{err}");
+        }
+    }
+}
+
+fn test_config(args: &TestArgs) -> Result<()> {
+    let file = &args.file;
+    let src = match fs::read_to_string(file) {
+        Ok(src) => src,
+        Err(err) => return compile_error(file, err.to_string().as_str()),
+    };
+    let mut config = compile_file(file, &src)?;
+
+    let tests = args
+        .tests
+        .clone()
+        .unwrap_or_else(|| file.with_extension("test"));
+    let text = fs::read_to_string(&tests)
+        .map_err(|err| anyhow!("Failed to read \"{}\": {err}", tests.display()))?;
+    let cases = simulate::parse_tests(&text).map_err(|err| anyhow!("{}:{err}", tests.display()))?;
+
+    let mut failed = 0;
+    for case in &cases {
+        let result = simulate::run_test(&mut config, case);
+        if matches!(&result, Ok(typed) if *typed == case.expected) {
+            continue;
+        }
+        failed += 1;
+        println!("{}:{}: {}", tests.display(), case.line, case.script);
+        println!("    expected: {}", case.expected.join(" "));
+        match result {
+            Ok(typed) => println!("         got: {}", typed.join(" ")),
+            Err(err) => println!("       error: {err}"),
+        }
+    }
+
+    println!("{} passed; {failed} failed", cases.len() - failed);
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(anyhow!("{failed} of {} tests failed", cases.len()))
+    }
+}
+
 fn prompt_text(prompt: &str) -> Result<String> {
     println!("Enter the {prompt}: ");
     io::stdout().flush().unwrap();
@@ -596,9 +1255,18 @@ fn run(cli: &Cli) -> Result<()> {
 
     match &cli.command {
         Commands::Upload(args) => finder.upload(args),
+        Commands::Pull(args) => finder.pull(args),
+        Commands::Decompile(args) => finder.decompile(args),
+        Commands::Activate(args) => finder.activate(args),
+        Commands::Rollback(args) => finder.rollback(args),
         Commands::Validate(args) => validate(args),
+        Commands::Simulate(args) => simulate(args),
+        Commands::Test(args) => test_config(args),
         Commands::Ls(args) => finder.ls(args),
         Commands::Stats(args) => finder.stats(args),
+        Commands::Monitor(args) => finder.monitor(args),
+        Commands::Heatmap(args) => finder.heatmap(args),
+        Commands::DynMacros(args) => finder.dyn_macros(args),
         Commands::USBList => finder.list_usb(),
         Commands::Reset(args) => finder.reset_keyboard(args),
         Commands::KeycodesList(args) => list_keycodes(args),
@@ -614,9 +1282,69 @@ mod test {
     fn validate_cmd() {
         let args = ValidateArgs {
             verbose: false,
+            no_warn: false,
             file: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/default.conf"),
         };
 
         validate(&args).expect("to be valid");
     }
+
+    #[test]
+    fn test_cmd() {
+        let args = TestArgs {
+            file: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/default.conf"),
+            tests: None,
+        };
+
+        test_config(&args).expect("to pass");
+    }
+
+    #[test]
+    fn decompile_cmd() {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/default.conf");
+        let src = fs::read_to_string(&file).unwrap();
+        let data = compile_file(&file, &src).unwrap().serialize();
+
+        let dir = std::env::temp_dir().join(format!("rpk-decompile-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let binary = dir.join("default.bin");
+        let output = dir.join("default.rpk.conf");
+        fs::write(
+            &binary,
+            data.iter()
+                .flat_map(|w| w.to_le_bytes())
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+
+        let finder = DeviceFinder {
+            vendor_id: None,
+            product_id: None,
+            serial_number: String::new(),
+        };
+        finder
+            .decompile(&DecompileArgs {
+                verbose: false,
+                binary: Some(binary),
+                index: None,
+                output: Some(output.clone()),
+                config_file: None,
+            })
+            .unwrap();
+
+        let text = fs::read_to_string(&output).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(compile_file(&output, &text).unwrap().serialize(), data);
+    }
+
+    #[test]
+    fn matrix_grid() {
+        let mut grid = MatrixGrid::new((2, 2));
+        grid.set(0, 1, true);
+        grid.set(1, 0, true);
+        grid.set(1, 0, false);
+        grid.set(2, 0, true);
+
+        assert_eq!(grid.render(), "     0  1\n  0  .  #\n  1  o  .\n  2  #  .");
+    }
 }
@@ -0,0 +1,424 @@
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU16};
+
+use anyhow::{Result, anyhow};
+use embassy_futures::block_on;
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use rpk_common::keycodes::key_range;
+use rpk_config::{compiler::KeyboardConfig, keycodes};
+use rpk_firmware::{
+    hid::HidWriter,
+    host_sim::{self, HostDriver, HostEndpointIn, HostReports},
+    key_reporter::Reporter,
+    key_scanner::ScanKey,
+    mapper::{KeyEvent, Mapper, MapperChannel, stats::Stats},
+};
+
+/// The largest matrix that can be simulated.
+const ROW_COUNT: usize = 32;
+const COL_COUNT: usize = 32;
+
+const LAYOUT_MAX: usize = 32 * 1024;
+const REPORT_BUFFER_SIZE: usize = 32;
+
+/// The most steps the mapper may take to settle after one input.
+const MAX_STEPS: usize = 100_000;
+
+/// Longer than any timeout a config can set.
+const TIMEOUT_MAX: u64 = u16::MAX as u64 + 1;
+
+type SimMapper<'c> = Mapper<'c, ROW_COUNT, COL_COUNT, LAYOUT_MAX, NoopRawMutex, REPORT_BUFFER_SIZE>;
+type SimChannel = MapperChannel<NoopRawMutex, REPORT_BUFFER_SIZE>;
+
+/// One input to the simulated keyboard.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    /// Press or release the key at a matrix position.
+    Key(ScanKey),
+    /// Let time pass; in milliseconds.
+    Wait(u64),
+    /// Move the clock forward to a time in milliseconds.
+    At(u64),
+}
+
+/// A key event from the mapper and the HID reports it produced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Output {
+    pub time: u64,
+    pub event: KeyEvent,
+    pub reports: Vec<Vec<u8>>,
+}
+
+/// Parse script `text` into steps. Commands are separated by a newline or `;`:
+///
+/// - `press <key>`, `release <key>` or `tap <key>` where `<key>` is a name from the `matrix` or
+///   `aliases` sections, or a hex position such as `0x0102`.
+/// - `wait <ms>` lets time pass.
+/// - `<ms>` on its own, or before a command, moves the clock forward to that time.
+///
+/// Anything after a `#` is a comment.
+pub fn parse_script(config: &KeyboardConfig, text: &str) -> Result<Vec<Step>> {
+    let mut steps = Vec::new();
+    for line in text.lines() {
+        let line = line.split_once('#').map_or(line, |(code, _)| code);
+        for command in line.split(';') {
+            parse_command(config, command, &mut steps)?;
+        }
+    }
+    Ok(steps)
+}
+
+fn parse_command(config: &KeyboardConfig, command: &str, steps: &mut Vec<Step>) -> Result<()> {
+    let mut words = command.split_whitespace().peekable();
+    if let Some(time) = words.peek().and_then(|w| parse_duration(w).ok()) {
+        steps.push(Step::At(time));
+        words.next();
+    }
+    let Some(verb) = words.next() else {
+        return Ok(());
+    };
+    let arg = words
+        .next()
+        .ok_or_else(|| anyhow!("Missing argument for {verb}"))?;
+    if let Some(extra) = words.next() {
+        return Err(anyhow!("Unexpected '{extra}'"));
+    }
+
+    match verb {
+        "press" => steps.push(Step::Key(key(config, arg, true)?)),
+        "release" => steps.push(Step::Key(key(config, arg, false)?)),
+        "tap" => {
+            steps.push(Step::Key(key(config, arg, true)?));
+            steps.push(Step::Key(key(config, arg, false)?));
+        }
+        "wait" => steps.push(Step::Wait(parse_duration(arg)?)),
+        _ => {
+            return Err(anyhow!(
+                "Unknown command '{verb}'; expected press, release, tap or wait"
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn parse_duration(text: &str) -> Result<u64> {
+    text.strip_suffix("ms")
+        .unwrap_or(text)
+        .parse()
+        .map_err(|_| anyhow!("Invalid time '{text}'; expected milliseconds"))
+}
+
+fn key(config: &KeyboardConfig, name: &str, is_down: bool) -> Result<ScanKey> {
+    let pos = config
+        .matrix_position(name)
+        .ok_or_else(|| anyhow!("Unknown key '{name}'"))?;
+    Ok(ScanKey::new((pos >> 8) as u8, pos as u8, is_down))
+}
+
+/// The parts of a [`Simulator`] that its mapper and reporter refer to.
+#[derive(Default)]
+pub struct Resources {
+    channel: SimChannel,
+    debounce_ms: AtomicU16,
+    debounce_mode: AtomicU8,
+    stats: Stats,
+    boot_protocol: AtomicBool,
+}
+
+/// Runs a config through the keyboard firmware's mapper and reporter on the host using simulated
+/// time.
+pub struct Simulator<'c> {
+    mapper: Box<SimMapper<'c>>,
+    channel: &'c SimChannel,
+    reporter: Reporter<'c, HostDriver, 34>,
+    reports: HostReports,
+    matrix_size: (u8, u8),
+    now: u64,
+}
+impl<'c> Simulator<'c> {
+    pub fn new(resources: &'c Resources, config: &mut KeyboardConfig) -> Result<Self> {
+        let matrix_size = config.matrix_size();
+        let (row_count, col_count) = matrix_size;
+        if row_count as usize > ROW_COUNT || col_count as usize > COL_COUNT {
+            return Err(anyhow!(
+                "Only matrices up to {ROW_COUNT}x{COL_COUNT} can be simulated"
+            ));
+        }
+        config.resize_matrix(ROW_COUNT as u8, COL_COUNT as u8);
+        let layout = config.serialize();
+
+        // timers, such as for macro delays, expire straight away
+        host_sim::set_time(1);
+
+        let mut mapper = Box::new(SimMapper::new(
+            &resources.channel,
+            &resources.debounce_ms,
+            &resources.debounce_mode,
+            &resources.stats,
+        ));
+        mapper
+            .load_layout(layout)
+            .map_err(|e| anyhow!("Failed to load layout: {e:?}"))?;
+
+        let reports = HostReports::default();
+        let ep_in = HostEndpointIn::new(reports.clone());
+        let reporter = Reporter::new(HidWriter::new(ep_in, &resources.boot_protocol));
+
+        let mut sim = Self {
+            mapper,
+            channel: &resources.channel,
+            reporter,
+            reports,
+            matrix_size,
+            now: 0,
+        };
+        // loading the layout reports a clear
+        sim.receive(&mut Vec::new());
+        Ok(sim)
+    }
+
+    /// The simulated time in milliseconds.
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Run `step` returning the key events it caused.
+    pub fn run(&mut self, step: Step) -> Result<Vec<Output>> {
+        let mut out = Vec::new();
+        match step {
+            Step::Key(scan_key) => {
+                let (row_count, col_count) = self.matrix_size;
+                if scan_key.row() >= row_count as usize || scan_key.column() >= col_count as usize {
+                    return Err(anyhow!(
+                        "Key {:#04x}{:02x} is outside the matrix",
+                        scan_key.row(),
+                        scan_key.column()
+                    ));
+                }
+                self.mapper.simulate_key(scan_key, self.now);
+                self.advance(self.now, &mut out)?;
+            }
+            Step::Wait(ms) => self.advance(self.now + ms, &mut out)?,
+            Step::At(time) => {
+                if time < self.now {
+                    return Err(anyhow!("Time {time}ms is before now ({}ms)", self.now));
+                }
+                self.advance(time, &mut out)?;
+            }
+        }
+        Ok(out)
+    }
+
+    fn advance(&mut self, now: u64, out: &mut Vec<Output>) -> Result<()> {
+        for _ in 0..MAX_STEPS {
+            let busy = self.mapper.simulate_step(now);
+            self.receive(out);
+            if !busy {
+                self.now = now;
+                return Ok(());
+            }
+        }
+        Err(anyhow!("Keyboard still busy after {MAX_STEPS} steps"))
+    }
+
+    fn receive(&mut self, out: &mut Vec<Output>) {
+        while let Some(event) = self.channel.try_receive() {
+            block_on(self.reporter.report(event.clone()));
+            let reports = std::iter::from_fn(|| self.reports.take())
+                .filter(|r| !r.is_empty())
+                .collect();
+            out.push(Output {
+                time: self.mapper.now(),
+                event,
+                reports,
+            });
+        }
+    }
+}
+
+/// Tracks what the host sees typed from a sequence of HID reports.
+#[derive(Default)]
+pub struct Typed {
+    modifiers: u8,
+    keys: Vec<u16>,
+    lone_modifiers: u8,
+    /// Each key pressed joined to the modifiers held at the time by `+`; such as `leftshift+j`.
+    /// Modifiers released without a key being pressed are included on their own.
+    pub typed: Vec<String>,
+}
+impl Typed {
+    pub fn report(&mut self, report: &[u8]) {
+        match report {
+            [6, modifiers, keys @ ..] => {
+                let pressed = modifiers & !self.modifiers;
+                let released = self.modifiers & !modifiers;
+                self.modifiers = *modifiers;
+                self.lone_modifiers |= pressed;
+
+                let lone = self.lone_modifiers & released;
+                self.lone_modifiers &= !released;
+                if lone != 0 {
+                    self.typed.push(chord_name(&modifier_codes(lone)));
+                }
+
+                let keys: Vec<u16> = report_keys(keys).collect();
+                for &key in keys.iter().filter(|k| !self.keys.contains(k)) {
+                    self.lone_modifiers = 0;
+                    let mut chord = modifier_codes(*modifiers);
+                    chord.push(key);
+                    self.typed.push(chord_name(&chord));
+                }
+                self.keys = keys;
+            }
+            [4, lo, hi] => self.usage(*lo, *hi, key_range::CONSUMER_MIN),
+            [3, lo, hi] => self.usage(*lo, *hi, key_range::SYS_CTL_MIN - key_range::SYS_CTL_BASE),
+            _ => {}
+        }
+    }
+
+    fn usage(&mut self, lo: u8, hi: u8, base: u16) {
+        let usage = u16::from_le_bytes([lo, hi]);
+        if usage != 0 {
+            self.typed.push(code_name(usage + base));
+        }
+    }
+}
+
+fn modifier_codes(modifiers: u8) -> Vec<u16> {
+    (0..8)
+        .filter(|b| modifiers & (1 << b) != 0)
+        .map(|b| key_range::MODIFIER_MIN + b)
+        .collect()
+}
+
+/// The keycodes of the bits set in the key bitmap of a keyboard report.
+fn report_keys(keys: &[u8]) -> impl Iterator<Item = u16> + '_ {
+    keys.iter().enumerate().flat_map(|(i, bits)| {
+        (0..8)
+            .filter(move |b| bits & (1 << b) != 0)
+            .map(move |b| (i * 8 + b) as u16)
+    })
+}
+
+fn chord_name(codes: &[u16]) -> String {
+    codes
+        .iter()
+        .map(|c| code_name(*c))
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+/// A case from a config test file.
+#[derive(Debug, PartialEq)]
+pub struct TestCase<'a> {
+    /// The line number of the case in the test file.
+    pub line: usize,
+    /// The script to run; see [`parse_script`].
+    pub script: &'a str,
+    /// What the host should see typed; see [`Typed::typed`].
+    pub expected: Vec<String>,
+}
+
+/// Parse a config test file. Each case is a line of the form `<script> => <typed>` where `<typed>`
+/// lists what the host should see separated by spaces; such as `leftshift+j`. Anything after a `#`
+/// is a comment.
+pub fn parse_tests(text: &str) -> Result<Vec<TestCase<'_>>> {
+    let mut cases = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.split_once('#').map_or(line, |(code, _)| code);
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (script, expected) = line
+            .split_once("=>")
+            .ok_or_else(|| anyhow!("{line_no}: Missing '=>'"))?;
+        let expected = expected
+            .split_whitespace()
+            .map(|chord| {
+                let mut codes = chord
+                    .split('+')
+                    .map(|name| {
+                        keycodes::key_code(name)
+                            .or_else(|| keycodes::key_code(&name.to_lowercase()))
+                            .ok_or_else(|| anyhow!("{line_no}: Unknown key name '{name}'"))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                codes.sort_by_key(|c| {
+                    (
+                        !(key_range::MODIFIER_MIN..=key_range::MODIFIER_MAX).contains(c),
+                        *c,
+                    )
+                });
+                Ok(chord_name(&codes))
+            })
+            .collect::<Result<_>>()?;
+        cases.push(TestCase {
+            line: line_no,
+            script: script.trim(),
+            expected,
+        });
+    }
+    Ok(cases)
+}
+
+/// Run `case` on a freshly started keyboard returning what the host saw typed. Time is left to run
+/// on after the script so that pending timeouts expire.
+pub fn run_test(config: &mut KeyboardConfig, case: &TestCase) -> Result<Vec<String>> {
+    let mut steps = parse_script(config, case.script)?;
+    steps.push(Step::Wait(TIMEOUT_MAX));
+    let resources = Resources::default();
+    let mut sim = Simulator::new(&resources, config)?;
+    let mut typed = Typed::default();
+    for step in steps {
+        for out in sim.run(step)? {
+            for report in out.reports {
+                typed.report(&report);
+            }
+        }
+    }
+    Ok(typed.typed)
+}
+
+/// Describe a HID report sent to the host.
+pub fn describe_report(report: &[u8]) -> String {
+    let text = match report {
+        [6, modifiers, keys @ ..] => {
+            let names: Vec<_> = modifier_codes(*modifiers)
+                .into_iter()
+                .chain(report_keys(keys))
+                .map(code_name)
+                .collect();
+            format!("keyboard: {}", names.join(" "))
+        }
+        [4, lo, hi] => format!(
+            "consumer: {}",
+            usage_name(*lo, *hi, key_range::CONSUMER_MIN)
+        ),
+        [3, lo, hi] => format!(
+            "system: {}",
+            usage_name(*lo, *hi, key_range::SYS_CTL_MIN - key_range::SYS_CTL_BASE)
+        ),
+        [2, buttons] => format!("mouse: buttons {buttons:#010b}"),
+        [2, buttons, x, y, wheel, pan] => format!(
+            "mouse: buttons {buttons:#010b} x {} y {} wheel {} pan {}",
+            *x as i8, *y as i8, *wheel as i8, *pan as i8
+        ),
+        _ => format!("{report:?}"),
+    };
+    text.trim_end().to_string()
+}
+
+fn usage_name(lo: u8, hi: u8, base: u16) -> String {
+    match u16::from_le_bytes([lo, hi]) {
+        0 => String::new(),
+        usage => code_name(usage + base),
+    }
+}
+
+fn code_name(code: u16) -> String {
+    keycodes::key_name(code).map_or_else(|| format!("{code:#06x}"), |n| n.to_string())
+}
+
+#[cfg(test)]
+#[path = "simulate_test.rs"]
+mod test;
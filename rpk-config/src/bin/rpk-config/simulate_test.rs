@@ -0,0 +1,257 @@
+use super::*;
+
+use rpk_config::compiler::compile;
+use std::path::PathBuf;
+
+const CONFIG: &str = r#"
+[global]
+dual_action_timeout = 180
+
+[matrix:2x3]
+0x00 = a b c
+0x10 = d e f
+
+[aliases]
+e = space
+
+[main]
+a = dualaction(leftshift, a)
+b = layer(nav)
+c = macro(x y)
+space = space
+
+[nav]
+d = left
+e = audio_vol_up
+"#;
+
+fn config() -> KeyboardConfig<'static> {
+    compile(PathBuf::from("test.rpk.conf"), CONFIG).unwrap()
+}
+
+fn run(sim: &mut Simulator, config: &KeyboardConfig, script: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    for step in parse_script(config, script).unwrap() {
+        for out in sim.run(step).unwrap() {
+            lines.push(format!("{} {:?}", out.time, out.event));
+            lines.extend(out.reports.iter().map(|r| describe_report(r)));
+        }
+    }
+    lines
+}
+
+#[test]
+fn parse_steps() {
+    let config = config();
+
+    assert_eq!(
+        parse_script(
+            &config,
+            "press a; 20ms release 0x11\n  wait 5 # comment\ntap f\n300"
+        )
+        .unwrap(),
+        vec![
+            Step::Key(ScanKey::new(0, 0, true)),
+            Step::At(20),
+            Step::Key(ScanKey::new(1, 1, false)),
+            Step::Wait(5),
+            Step::Key(ScanKey::new(1, 2, true)),
+            Step::Key(ScanKey::new(1, 2, false)),
+            Step::At(300),
+        ]
+    );
+
+    assert_eq!(
+        parse_script(&config, "press space").unwrap(),
+        vec![Step::Key(ScanKey::new(1, 1, true))]
+    );
+
+    assert_eq!(
+        parse_script(&config, "press z").unwrap_err().to_string(),
+        "Unknown key 'z'"
+    );
+    assert_eq!(
+        parse_script(&config, "wait").unwrap_err().to_string(),
+        "Missing argument for wait"
+    );
+    assert_eq!(
+        parse_script(&config, "hold a").unwrap_err().to_string(),
+        "Unknown command 'hold'; expected press, release, tap or wait"
+    );
+    assert_eq!(
+        parse_script(&config, "wait soon").unwrap_err().to_string(),
+        "Invalid time 'soon'; expected milliseconds"
+    );
+}
+
+#[test]
+fn describe_reports() {
+    assert_eq!(
+        describe_report(&[6, 0x22, 0, 0x02]),
+        "keyboard: leftshift rightshift f"
+    );
+    assert_eq!(describe_report(&[6, 0]), "keyboard:");
+    assert_eq!(describe_report(&[4, 0xe9, 0]), "consumer: audio_vol_up");
+    assert_eq!(describe_report(&[4, 0, 0]), "consumer:");
+    assert_eq!(
+        describe_report(&[2, 1, 0xff, 2, 0, 0]),
+        "mouse: buttons 0b00000001 x -1 y 2 wheel 0 pan 0"
+    );
+    assert_eq!(describe_report(&[9, 1]), "[9, 1]");
+}
+
+#[test]
+fn simulate() {
+    let mut config = config();
+    let resources = Resources::default();
+    let mut sim = Simulator::new(&resources, &mut config).unwrap();
+
+    assert_eq!(
+        run(&mut sim, &config, "tap space"),
+        [
+            "0 Basic(44, true)",
+            "keyboard: spacebar",
+            "0 Basic(44, false)",
+            "keyboard:"
+        ]
+    );
+
+    assert_eq!(
+        run(&mut sim, &config, "10 press a; wait 200; release a"),
+        [
+            "190 Basic(225, true)",
+            "keyboard: leftshift",
+            "210 Basic(225, false)",
+            "keyboard:"
+        ]
+    );
+    assert_eq!(sim.now(), 210);
+
+    assert_eq!(
+        run(&mut sim, &config, "press b; tap e; release b"),
+        [
+            "210 Consumer(233)",
+            "consumer: audio_vol_up",
+            "210 Consumer(0)",
+            "consumer:"
+        ]
+    );
+
+    assert_eq!(
+        run(&mut sim, &config, "tap c"),
+        [
+            "210 Basic(27, true)",
+            "keyboard: x",
+            "210 Basic(27, false)",
+            "keyboard:",
+            "210 Basic(28, true)",
+            "keyboard: y",
+            "210 Basic(28, false)",
+            "keyboard:"
+        ]
+    );
+
+    assert_eq!(
+        sim.run(Step::At(100)).unwrap_err().to_string(),
+        "Time 100ms is before now (210ms)"
+    );
+    assert_eq!(
+        sim.run(Step::Key(ScanKey::new(5, 0, true)))
+            .unwrap_err()
+            .to_string(),
+        "Key 0x0500 is outside the matrix"
+    );
+}
+
+#[test]
+fn typed() {
+    let mut typed = Typed::default();
+    for report in [
+        &[6, 0, 0, 0x02][..],
+        &[6, 0],
+        &[6, 2],
+        &[6, 2, 0, 0x20],
+        &[6, 2],
+        &[6, 0],
+        &[6, 1],
+        &[6, 0, 0, 0x20],
+        &[6, 0],
+        &[4, 0xe9, 0],
+        &[4, 0, 0],
+        &[2, 1, 0, 0, 0, 0],
+    ] {
+        typed.report(report);
+    }
+
+    assert_eq!(
+        typed.typed,
+        ["f", "leftshift+j", "leftcontrol", "j", "audio_vol_up"]
+    );
+}
+
+#[test]
+fn parse_test_file() {
+    let cases = parse_tests(
+        "# comment\n\npress a; wait 250ms; tap f; release a => LeftShift+f\ntap c =>\n \
+         tap a => a LeftShift+LeftControl+Space # x",
+    )
+    .unwrap();
+
+    assert_eq!(
+        cases,
+        [
+            TestCase {
+                line: 3,
+                script: "press a; wait 250ms; tap f; release a",
+                expected: vec!["leftshift+f".to_string()],
+            },
+            TestCase {
+                line: 4,
+                script: "tap c",
+                expected: vec![],
+            },
+            TestCase {
+                line: 5,
+                script: "tap a",
+                expected: vec![
+                    "a".to_string(),
+                    "leftcontrol+leftshift+spacebar".to_string()
+                ],
+            },
+        ]
+    );
+
+    assert_eq!(
+        parse_tests("tap a").unwrap_err().to_string(),
+        "1: Missing '=>'"
+    );
+    assert_eq!(
+        parse_tests("\ntap a => leftshift+nokey")
+            .unwrap_err()
+            .to_string(),
+        "2: Unknown key name 'nokey'"
+    );
+}
+
+#[test]
+fn run_tests() {
+    let mut config = config();
+    let cases = parse_tests(
+        "press a; wait 200; tap f; release a => leftshift+f
+         tap a; tap f => a f
+         press a; wait 200; release a => leftshift
+         press b; tap e; release b => audio_vol_up
+         tap c => x y",
+    )
+    .unwrap();
+
+    for case in &cases {
+        assert_eq!(run_test(&mut config, case).unwrap(), case.expected);
+    }
+
+    let case = &parse_tests("tap z => z").unwrap()[0];
+    assert_eq!(
+        run_test(&mut config, case).unwrap_err().to_string(),
+        "Unknown key 'z'"
+    );
+}
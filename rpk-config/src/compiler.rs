@@ -1,11 +1,16 @@
-use std::{collections::HashMap, ops::Range, path::PathBuf, str::CharIndices};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+    path::PathBuf,
+    str::CharIndices,
+};
 
 use rpk_common::{
     PROTOCOL_VERSION,
-    globals::{COMPOSITE_BIT, COMPOSITE_PART_BIT},
+    globals::{COMPOSITE_BIT, COMPOSITE_PART_BIT, dual_action_modes::hands},
     keycodes::{
         key_range::{self, BASIC_0, BASIC_1, BASIC_A},
-        macro_types,
+        leds, macro_types,
     },
 };
 
@@ -28,6 +33,8 @@ const TOO_MANY_MULTI_ALIAS_RHS: &str =
 const TOO_MANY_ROWS: &str = "Too many rows";
 const TOO_MANY_COLS: &str = "Too many keys in row";
 const UNKNOWN_ACTION: &str = "Unknown action/keycode";
+const INVALID_COMBO: &str = "A combo must have 2 to 4 different keys";
+const INVALID_LEADER: &str = "A leader sequence must have 1 to 5 keys";
 const EOF: &str = "Unexpected end of file";
 
 struct SourceIter<'source> {
@@ -46,6 +53,16 @@ impl<'source> SourceIter<'source> {
         }
     }
 
+    /// Continue from the line after the newline at `newline`.
+    fn seek_line(&mut self, source: &'source str, newline: usize) {
+        let mut iter = source.char_indices();
+        let skip = source[..newline].chars().count();
+        iter.nth(skip);
+        self.iter = iter;
+        self.current = (newline, '\n');
+        self.next = None;
+    }
+
     fn put_back(&mut self, item: IndexChar) {
         assert!(self.next.is_none() && item.0 == self.current.0);
         self.next = Some(item);
@@ -104,6 +121,10 @@ struct Parser<'source> {
     config: KeyboardConfig<'source>,
     mark_idx: usize,
     macro_sequence: bool,
+    errors: Vec<ConfigError>,
+    assigned: HashSet<(u16, u32, u16)>,
+    aliases: Vec<(String, SourceRange)>,
+    used_names: HashSet<String>,
 }
 
 fn non_ws_char(c: char) -> bool {
@@ -125,29 +146,36 @@ fn invalid_section_char(c: char) -> bool {
 pub struct KeyboardConfig<'source> {
     pub path: PathBuf,
     pub source: &'source str,
-    global_map: HashMap<&'source str, GlobalProp>,
+    pub(crate) global_map: HashMap<&'source str, GlobalProp>,
     temp_map: HashMap<&'source str, u16>,
     firmware_map: HashMap<&'source str, SourceRange>,
     matrix_map: HashMap<String, Vec<u16>>,
-    layers: HashMap<String, ConfigLayer>,
-    composites: HashMap<u32, ConfigLayer>,
+    pub(crate) layers: HashMap<String, ConfigLayer>,
+    pub(crate) composites: HashMap<u32, ConfigLayer>,
     macros_names: HashMap<Vec<u16>, u16>,
-    macros: Vec<Macro>,
+    pub(crate) macros: Vec<Macro>,
+    pub(crate) combos: Vec<Macro>,
+    pub(crate) leader_sequences: Vec<Macro>,
+    pub(crate) caps_word_keys: Vec<u16>,
+    pub(crate) hands: Vec<(u16, u8)>,
+    pub(crate) led_layers: Vec<(u8, u16)>,
+    pub(crate) section_spans: HashMap<String, SourceRange>,
+    pub(crate) warnings: Vec<ConfigError>,
     next_layer: u16,
     row_count: u8,
     col_count: u8,
 }
 
 #[derive(Debug)]
-struct ConfigLayer {
-    codes: HashMap<u16, u16>,
-    index: u16,
-    suffix: u8,
+pub(crate) struct ConfigLayer {
+    pub(crate) codes: HashMap<u16, u16>,
+    pub(crate) index: u16,
+    pub(crate) suffix: u8,
     composite_part: bool,
 }
 
 #[derive(Debug, PartialEq)]
-enum Macro {
+pub(crate) enum Macro {
     Modifier { keycode: u16, modifiers: u8 },
     Tap(Vec<u16>),
     HoldRelease { hold: u16, release: u16 },
@@ -155,8 +183,16 @@ enum Macro {
     Release(Vec<u16>),
     DualAction(u16, u16),
     TimedDualAction(u16, u16, u16, u16),
+    ModalDualAction(u16, u16, u16, u16, u16, u16),
     Delay(u16),
     TapDance(u16, Vec<u16>),
+    Combo(u16, Vec<u16>),
+    Leader,
+    LeaderSequence(u16, Vec<u16>),
+    CapsWordKeys(Vec<u16>),
+    Hands(Vec<u16>),
+    LedLayers(Vec<u16>),
+    IfLed(u8, u16, u16),
 }
 impl Macro {
     fn serialize(&self) -> Vec<u16> {
@@ -185,11 +221,100 @@ impl Macro {
                     vec![macro_types::DUAL_ACTION, tap, hold, time1, time2]
                 }
             }
+            Macro::ModalDualAction(tap, hold, time1, time2, mode, prior_idle) => {
+                if prior_idle == u16::MAX {
+                    vec![macro_types::DUAL_ACTION, tap, hold, time1, time2, mode]
+                } else {
+                    vec![
+                        macro_types::DUAL_ACTION,
+                        tap,
+                        hold,
+                        time1,
+                        time2,
+                        mode,
+                        prior_idle,
+                    ]
+                }
+            }
             Macro::Delay(n) => {
                 vec![macro_types::DELAY, n]
             }
+            Macro::Combo(action, ref keys) => {
+                let mut v = vec![macro_types::COMBO, action];
+                v.extend_from_slice(keys);
+                v
+            }
+            Macro::Leader => vec![macro_types::LEADER],
+            Macro::CapsWordKeys(ref keys) => binary_seq(macro_types::CAPS_WORD_KEYS, keys),
+            Macro::Hands(ref bitmap) => binary_seq(macro_types::HANDS, bitmap),
+            Macro::LedLayers(ref pairs) => binary_seq(macro_types::LED_LAYERS, pairs),
+            Macro::IfLed(led, on, off) => {
+                vec![macro_types::IF_LED | (led as u16) << 8, on, off]
+            }
+            Macro::LeaderSequence(action, ref keys) => {
+                let mut v = vec![macro_types::LEADER_SEQUENCE, action];
+                v.extend_from_slice(keys);
+                v
+            }
+        }
+    }
+
+    /// The actions this macro uses in the order the compiler reads them.
+    pub(crate) fn children(&self) -> Vec<u16> {
+        match self {
+            Macro::Tap(seq) | Macro::Hold(seq) | Macro::Release(seq) => seq.clone(),
+            Macro::HoldRelease { hold, release } => vec![*hold, *release],
+            Macro::DualAction(tap, hold)
+            | Macro::TimedDualAction(tap, hold, ..)
+            | Macro::ModalDualAction(tap, hold, ..) => vec![*hold, *tap],
+            Macro::TapDance(_, actions) => actions.clone(),
+            Macro::IfLed(_, on, off) => vec![*on, *off],
+            _ => vec![],
         }
     }
+
+    fn deserialize(data: &[u16]) -> Option<Self> {
+        let (&head, rest) = data.split_first()?;
+        let id = head & 0xff;
+        Some(match (id, rest) {
+            (macro_types::MODIFIER, &[keycode]) => Macro::Modifier {
+                keycode,
+                modifiers: (head >> 8) as u8,
+            },
+            (macro_types::IF_LED, &[on, off]) => Macro::IfLed((head >> 8) as u8, on, off),
+            _ if head != id => return None,
+            (macro_types::TAP, seq) => Macro::Tap(seq.to_vec()),
+            (macro_types::HOLD_RELEASE, &[hold, release]) => Macro::HoldRelease { hold, release },
+            (macro_types::HOLD, seq) => Macro::Hold(seq.to_vec()),
+            (macro_types::RELEASE, seq) => Macro::Release(seq.to_vec()),
+            (macro_types::DUAL_ACTION, &[tap, hold]) => Macro::DualAction(tap, hold),
+            (macro_types::DUAL_ACTION, &[tap, hold, time1]) => {
+                Macro::TimedDualAction(tap, hold, time1, u16::MAX)
+            }
+            (macro_types::DUAL_ACTION, &[tap, hold, time1, time2]) => {
+                Macro::TimedDualAction(tap, hold, time1, time2)
+            }
+            (macro_types::DUAL_ACTION, &[tap, hold, time1, time2, mode]) => {
+                Macro::ModalDualAction(tap, hold, time1, time2, mode, u16::MAX)
+            }
+            (macro_types::DUAL_ACTION, &[tap, hold, time1, time2, mode, prior_idle]) => {
+                Macro::ModalDualAction(tap, hold, time1, time2, mode, prior_idle)
+            }
+            (macro_types::DELAY, &[n]) => Macro::Delay(n),
+            (macro_types::TAPDANCE, [timeout, actions @ ..]) => {
+                Macro::TapDance(*timeout, actions.to_vec())
+            }
+            (macro_types::COMBO, [action, keys @ ..]) => Macro::Combo(*action, keys.to_vec()),
+            (macro_types::LEADER, []) => Macro::Leader,
+            (macro_types::LEADER_SEQUENCE, [action, keys @ ..]) => {
+                Macro::LeaderSequence(*action, keys.to_vec())
+            }
+            (macro_types::CAPS_WORD_KEYS, keys) => Macro::CapsWordKeys(keys.to_vec()),
+            (macro_types::HANDS, bitmap) => Macro::Hands(bitmap.to_vec()),
+            (macro_types::LED_LAYERS, pairs) => Macro::LedLayers(pairs.to_vec()),
+            _ => return None,
+        })
+    }
 }
 
 fn binary_seq(id: u16, seq: &[u16]) -> Vec<u16> {
@@ -206,53 +331,132 @@ impl<'source> Parser<'source> {
             config: KeyboardConfig::new(path, source),
             mark_idx: 0,
             macro_sequence: false,
+            errors: vec![],
+            assigned: Default::default(),
+            aliases: vec![],
+            used_names: Default::default(),
         }
     }
 
-    fn parse_sections(&mut self) -> Result<()> {
-        self.config.scan_layer_names()?;
-        loop {
-            match self.next_non_ws() {
-                None => return Ok(()),
-                Some(start) => {
-                    if start.1 == '[' {
-                        self.mark_start();
-                        let tag_end = match self.iter.find(|c| matches!(c, ']' | '\n')) {
-                            Some(tag_end) if tag_end.1 == ']' => tag_end,
-                            v => {
-                                return Err(error_span(
-                                    "Missing section end delimiter ']'",
-                                    start.0..v.map(|v| v.0).unwrap_or(self.iter.len),
-                                ));
-                            }
-                        };
-                        let full_tag_name = &self.config.source[start.0 + 1..tag_end.0];
-                        let tag_name = full_tag_name
-                            .split_once(invalid_section_char)
-                            .map(|n| n.0)
-                            .unwrap_or(full_tag_name);
-
-                        let rem = start.0 + 1 + tag_name.len()..tag_end.0;
-                        match tag_name {
-                            "global" => self.parse_global(rem)?,
-                            "matrix" => self.parse_matrix()?,
-                            "firmware" => {
-                                self.assert_no_suffix(rem)?;
-                                self.parse_firmware()?
-                            }
-                            "aliases" => {
-                                self.assert_no_suffix(rem)?;
-                                self.parse_aliases()?
-                            }
-                            _ => self.parse_layer(start.0 + 1..rem.start)?,
-                        }
-                    } else {
-                        return Err(ConfigError::new(
-                            "expected '['".into(),
-                            start.0..start.0 + 1,
-                        ));
+    /// Parse every section; an error skips the rest of its line, or the rest of its section if
+    /// the error is in the section header, and is added to `errors`.
+    fn parse_sections(&mut self) {
+        if let Err(err) = self.config.scan_layer_names() {
+            self.errors.push(err);
+            return;
+        }
+        while let Some(start) = self.next_non_ws() {
+            if start.1 != '[' {
+                self.errors.push(ConfigError::new(
+                    "expected '['".into(),
+                    start.0..start.0 + 1,
+                ));
+                self.skip_section();
+                continue;
+            }
+            self.mark_start();
+            let tag_end = match self.iter.find(|c| matches!(c, ']' | '\n')) {
+                Some(tag_end) if tag_end.1 == ']' => tag_end,
+                v => {
+                    self.errors.push(error_span(
+                        "Missing section end delimiter ']'",
+                        start.0..v.map(|v| v.0).unwrap_or(self.iter.len),
+                    ));
+                    self.skip_section();
+                    continue;
+                }
+            };
+            let full_tag_name = &self.config.source[start.0 + 1..tag_end.0];
+            let tag_name = full_tag_name
+                .split_once(invalid_section_char)
+                .map(|n| n.0)
+                .unwrap_or(full_tag_name);
+
+            let rem = start.0 + 1 + tag_name.len()..tag_end.0;
+            let mut resume = tag_end.0;
+            loop {
+                let result = match tag_name {
+                    "global" => self.parse_global(rem.clone()),
+                    "matrix" => self.parse_matrix(),
+                    "firmware" => self
+                        .assert_no_suffix(rem.clone())
+                        .and_then(|_| self.parse_firmware()),
+                    "aliases" => self
+                        .assert_no_suffix(rem.clone())
+                        .and_then(|_| self.parse_aliases()),
+                    "combos" => self
+                        .assert_no_suffix(rem.clone())
+                        .and_then(|_| self.parse_combos()),
+                    "leader" => self
+                        .assert_no_suffix(rem.clone())
+                        .and_then(|_| self.parse_leader()),
+                    "hands" => self
+                        .assert_no_suffix(rem.clone())
+                        .and_then(|_| self.parse_hands()),
+                    "leds" => self
+                        .assert_no_suffix(rem.clone())
+                        .and_then(|_| self.parse_leds()),
+                    _ => self.parse_layer(start.0 + 1..rem.start),
+                };
+                let Err(err) = result else {
+                    break;
+                };
+                let in_header = err.span.as_ref().is_some_and(|s| s.start <= tag_end.0);
+                let pos = match &err.span {
+                    Some(span) if span.start < span.end => {
+                        let text = &self.config.source[span.start..span.end.min(self.iter.len)];
+                        span.start + text.trim_end().len()
                     }
+                    Some(span) => span.start,
+                    None => self.iter.current.0,
+                };
+                self.errors.push(err);
+                self.macro_sequence = false;
+                if in_header {
+                    self.skip_line(tag_end.0);
+                    self.skip_section();
+                    break;
+                }
+                if !self.skip_line(pos) {
+                    break;
+                }
+                if self.iter.current.0 <= resume {
+                    self.skip_section();
+                    break;
+                }
+                resume = self.iter.current.0;
+            }
+        }
+    }
+
+    /// Continue from the line after the one holding `pos`; returns false if it is the last line.
+    fn skip_line(&mut self, pos: usize) -> bool {
+        let source = self.config.source;
+        let pos = pos.min(source.len());
+        match source[pos..].find('\n') {
+            Some(i) => {
+                self.iter.seek_line(source, pos + i);
+                true
+            }
+            None => {
+                while self.iter.next().is_some() {}
+                false
+            }
+        }
+    }
+
+    /// Move to the next line starting with `[`.
+    fn skip_section(&mut self) {
+        let mut line_start = self.iter.current.1 == '\n';
+        while let Some(item) = self.iter.next() {
+            match item.1 {
+                '[' if line_start => {
+                    self.iter.put_back(item);
+                    return;
                 }
+                '\n' => line_start = true,
+                c if c.is_whitespace() => {}
+                _ => line_start = false,
             }
         }
     }
@@ -303,8 +507,12 @@ impl<'source> Parser<'source> {
             match self.parse_assignment()? {
                 None => return Ok(()),
                 Some((left, right)) => {
-                    self.assign_global(&left, &right)?;
-                    self.assert_no_more_values(TOO_MANY_RHS)?;
+                    if self.name(&left) == "caps_word_keys" {
+                        self.assign_caps_word_keys(right)?;
+                    } else {
+                        self.assign_global(&left, &right)?;
+                        self.assert_no_more_values(TOO_MANY_RHS)?;
+                    }
                 }
             }
         }
@@ -340,6 +548,19 @@ impl<'source> Parser<'source> {
         Ok(())
     }
 
+    fn assign_caps_word_keys(&mut self, value_range: SourceRange) -> Result<()> {
+        let mut keys = Vec::new();
+        let mut value = Some(value_range);
+        while let Some(range) = value {
+            let code = keycodes::key_code(self.name(&range))
+                .ok_or_else(|| error_span("Unknown keycode", range))?;
+            keys.push(code);
+            value = self.next_assignment_value();
+        }
+        self.config.caps_word_keys = keys;
+        Ok(())
+    }
+
     fn parse_matrix(&mut self) -> Result<()> {
         while let Some(pos) = self.skip_whitespace() {
             if pos.1 == '[' {
@@ -391,6 +612,8 @@ impl<'source> Parser<'source> {
                     if !self.config.assign_aliases(self.name(&key), value) {
                         return Err(error_span("Unknown key name", key));
                     }
+                    self.used_names.insert(matrix_key(self.name(&key)));
+                    self.aliases.push((matrix_key(value), right));
                     self.assert_no_more_values(TOO_MANY_RHS)?;
                 }
             }
@@ -398,6 +621,187 @@ impl<'source> Parser<'source> {
         Ok(())
     }
 
+    fn parse_combos(&mut self) -> Result<()> {
+        while let Some(pos) = self.skip_whitespace() {
+            if pos.1 == '[' {
+                return Ok(());
+            }
+            self.skip_whitespace();
+            match self.parse_assignment()? {
+                None => return Ok(()),
+                Some((left, right)) => {
+                    let keys = self.combo_keys(left)?;
+                    let action = self.read_action(right)?;
+                    self.config.combos.push(Macro::Combo(action, keys));
+                    self.assert_no_more_values(TOO_MANY_RHS)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn combo_keys(&mut self, keys_range: SourceRange) -> Result<Vec<u16>> {
+        let text = self.name(&keys_range);
+        let mut start = keys_range.end - text.len();
+        let mut keys = Vec::new();
+        for name in text.split('+') {
+            let range = start..start + name.len();
+            start = range.end + 1;
+            let keypos = match self.aliases(name) {
+                Some(list) if list.len() == 1 => list[0],
+                Some(_) => {
+                    return Err(error_span("A combo key must be a single position", range));
+                }
+                None => self
+                    .config
+                    .key_position(name)
+                    .ok_or_else(|| error_span(format!("key not found! {name}"), range.clone()))?,
+            };
+            if (keypos >> 8) as u8 >= self.config.row_count {
+                return Err(error_span(TOO_MANY_ROWS, range));
+            }
+            if (keypos & 0xff) as u8 >= self.config.col_count {
+                return Err(error_span(TOO_MANY_COLS, range));
+            }
+            if keys.contains(&keypos) {
+                return Err(error_span(INVALID_COMBO, range));
+            }
+            keys.push(keypos);
+        }
+        if keys.len() < 2 || keys.len() > macro_types::COMBO_MAX_KEYS {
+            return Err(error_span(INVALID_COMBO, keys_range));
+        }
+        Ok(keys)
+    }
+
+    fn parse_hands(&mut self) -> Result<()> {
+        while let Some(pos) = self.skip_whitespace() {
+            if pos.1 == '[' {
+                return Ok(());
+            }
+            self.skip_whitespace();
+            match self.parse_assignment()? {
+                None => return Ok(()),
+                Some((left, right)) => {
+                    let hand = match self.name(&left) {
+                        "left" => hands::LEFT,
+                        "right" => hands::RIGHT,
+                        _ => return Err(error_span("Expected left or right", left)),
+                    };
+                    let mut value = Some(right);
+                    while let Some(range) = value {
+                        for keypos in self.hand_keys(range)? {
+                            self.config.hands.retain(|(p, _)| *p != keypos);
+                            self.config.hands.push((keypos, hand));
+                        }
+                        value = self.next_assignment_value();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_leds(&mut self) -> Result<()> {
+        while let Some(pos) = self.skip_whitespace() {
+            if pos.1 == '[' {
+                return Ok(());
+            }
+            self.skip_whitespace();
+            match self.parse_assignment()? {
+                None => return Ok(()),
+                Some((left, right)) => {
+                    let led = self.led(left)?;
+                    let layer = self.get_layer_index(right)?;
+                    self.config.led_layers.retain(|(l, _)| *l != led);
+                    self.config.led_layers.push((led, layer));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn led(&self, range: SourceRange) -> Result<u8> {
+        keycodes::led_bit(self.name(&range)).ok_or_else(|| {
+            error_span(
+                format!("Invalid LED; only {} are valid", leds::NAMES.join(", ")),
+                range,
+            )
+        })
+    }
+
+    fn hand_keys(&mut self, range: SourceRange) -> Result<Vec<u16>> {
+        let name = self.name(&range);
+        let keys = match self.aliases(name) {
+            Some(list) => list,
+            None => vec![
+                self.config
+                    .key_position(name)
+                    .ok_or_else(|| error_span(format!("key not found! {name}"), range.clone()))?,
+            ],
+        };
+        for keypos in keys.iter() {
+            if (keypos >> 8) as u8 >= self.config.row_count {
+                return Err(error_span(TOO_MANY_ROWS, range));
+            }
+            if (keypos & 0xff) as u8 >= self.config.col_count {
+                return Err(error_span(TOO_MANY_COLS, range));
+            }
+        }
+        Ok(keys)
+    }
+
+    fn parse_leader(&mut self) -> Result<()> {
+        while let Some(pos) = self.skip_whitespace() {
+            if pos.1 == '[' {
+                return Ok(());
+            }
+            let Some(start) = self.next_non_ws() else {
+                return Ok(());
+            };
+            self.mark_start();
+            let end = match self.iter.find(|c| matches!(c, '=' | '\n')) {
+                Some(end) if end.1 == '=' => end,
+                _ => return Err(self.error("Missing =")),
+            };
+            let Some(right) = self.next_assignment_value() else {
+                return Err(self.error("Missing RHS"));
+            };
+            let keys_range = start.0..start.0 + self.config.source[start.0..end.0].trim_end().len();
+            let keys = self.leader_keys(keys_range.clone())?;
+            if self
+                .config
+                .leader_sequences
+                .iter()
+                .any(|m| matches!(m, Macro::LeaderSequence(_, k) if *k == keys))
+            {
+                return Err(error_span("Duplicate leader sequence", keys_range));
+            }
+            let action = self.read_action(right)?;
+            self.config
+                .leader_sequences
+                .push(Macro::LeaderSequence(action, keys));
+            self.assert_no_more_values(TOO_MANY_RHS)?;
+        }
+        Ok(())
+    }
+
+    fn leader_keys(&self, keys_range: SourceRange) -> Result<Vec<u16>> {
+        let text = &self.config.source[keys_range.start..keys_range.end];
+        let mut keys = Vec::new();
+        for name in text.split_whitespace() {
+            let start = keys_range.start + (name.as_ptr() as usize - text.as_ptr() as usize);
+            let range = start..start + name.len();
+            let code = keycodes::key_code(self.name(&range))
+                .ok_or_else(|| error_span("Unknown keycode", range.clone()))?;
+            keys.push(code);
+        }
+        if keys.is_empty() || keys.len() > macro_types::LEADER_MAX_KEYS {
+            return Err(error_span(INVALID_LEADER, keys_range));
+        }
+        Ok(keys)
+    }
+
     fn parse_firmware(&mut self) -> Result<()> {
         while let Some(pos) = self.skip_whitespace() {
             if pos.1 == '[' {
@@ -446,9 +850,9 @@ impl<'source> Parser<'source> {
                     self.mark_idx = mark;
                     let left = self.name(&left_range);
 
-                    let alias_value = self.config.get_aliases(left);
+                    let alias_value = self.aliases(left);
 
-                    let keypos = if let Some(list) = alias_value {
+                    let keypos = if let Some(list) = &alias_value {
                         if list.len() == 1 {
                             list.first().copied()
                         } else {
@@ -462,13 +866,20 @@ impl<'source> Parser<'source> {
                         if (keypos >> 8) as u8 >= self.config.row_count {
                             return Err(self.error(TOO_MANY_ROWS));
                         }
+                        let first = keypos;
                         let mut right = Some(right);
                         while let Some(value) = &right {
                             if (keypos & 0xff) as u8 >= self.config.col_count {
                                 return Err(self.error(TOO_MANY_COLS));
                             }
 
+                            let span = if keypos == first {
+                                left_range.clone()
+                            } else {
+                                value.clone()
+                            };
                             let code = self.read_action(value.to_owned())?;
+                            self.note_assignment(name, composite, keypos, span);
                             if composite == 0 {
                                 self.config.assign_layer_code(name, keypos, code);
                             } else {
@@ -479,9 +890,9 @@ impl<'source> Parser<'source> {
                             keypos += 1;
                         }
                     } else if let Some(positions) = alias_value {
-                        let positions = positions.clone();
                         let code = self.read_action(right)?;
                         for keypos in positions {
+                            self.note_assignment(name, composite, keypos, left_range.clone());
                             if composite == 0 {
                                 self.config.assign_layer_code(name, keypos, code);
                             } else {
@@ -601,24 +1012,45 @@ impl<'source> Parser<'source> {
         let tap_name = self.read_arg();
         let tap = self.read_action(tap_name)?;
 
-        let Some(c) = self.next_non_ws() else {
-            return Err(self.error(EOF));
-        };
-
-        Ok(if c.1 == ')' {
-            self.add_macro(Macro::DualAction(tap, hold))
-        } else {
-            let t1 = self.read_timeout()?;
+        let mut times = [u16::MAX; 2];
+        let mut time_count = 0;
+        let mut mode = u16::MAX;
+        let mut prior_idle = u16::MAX;
+        loop {
             let Some(c) = self.next_non_ws() else {
                 return Err(self.error(EOF));
             };
             if c.1 == ')' {
-                self.add_macro(Macro::TimedDualAction(tap, hold, t1, u16::MAX))
+                break;
+            }
+            let arg = self.read_arg();
+            let text = self.name(&arg);
+            if let Some(value) = text.strip_prefix("require_prior_idle=") {
+                if prior_idle != u16::MAX {
+                    return Err(error_span("Duplicate require_prior_idle", arg));
+                }
+                prior_idle = self.parse_duration(arg.end - value.len()..arg.end, 0, 5000)?;
+            } else if mode == u16::MAX
+                && prior_idle == u16::MAX
+                && time_count < 2
+                && text.starts_with(|c: char| c.is_ascii_digit())
+            {
+                times[time_count] = self.parse_duration(arg, 0, 5000)?;
+                time_count += 1;
+            } else if mode == u16::MAX && prior_idle == u16::MAX {
+                mode = spec::parse_dual_action_mode(text).map_err(|e| error_span(e, arg))?;
             } else {
-                let t2 = self.read_timeout()?;
-                self.expect(')')?;
-                self.add_macro(Macro::TimedDualAction(tap, hold, t1, t2))
+                return Err(error_span("Unexpected argument", arg));
             }
+        }
+
+        let [t1, t2] = times;
+        Ok(if mode != u16::MAX || prior_idle != u16::MAX {
+            self.add_macro(Macro::ModalDualAction(tap, hold, t1, t2, mode, prior_idle))
+        } else if time_count == 0 {
+            self.add_macro(Macro::DualAction(tap, hold))
+        } else {
+            self.add_macro(Macro::TimedDualAction(tap, hold, t1, t2))
         })
     }
 
@@ -706,6 +1138,19 @@ impl<'source> Parser<'source> {
                 self.expect(',')?;
                 self.tapdance(timeout)?
             }
+            "ifled" => {
+                self.iter.next();
+                let led_name = self.read_arg();
+                let led = self.led(led_name)?;
+                self.expect(',')?;
+                let on_name = self.read_arg();
+                let on = self.read_action(on_name)?;
+                self.expect(',')?;
+                let off_name = self.read_arg();
+                let off = self.read_action(off_name)?;
+                self.expect(')')?;
+                self.add_macro(Macro::IfLed(led, on, off))
+            }
             "unicode" => {
                 self.iter.next();
                 let uc = self.read_hex_codes()?;
@@ -713,6 +1158,12 @@ impl<'source> Parser<'source> {
                 let mac = Macro::Tap(self.config.unicode_to_seq(uc));
                 self.add_macro(mac)
             }
+            "leader" => {
+                self.assert_not_in_macro_sequence(name_range)?;
+                self.iter.next();
+                self.expect(')')?;
+                self.add_macro(Macro::Leader)
+            }
             "delay" => {
                 self.iter.next();
                 let nr = self.read_arg();
@@ -954,7 +1405,44 @@ impl<'source> Parser<'source> {
         }
     }
 
-    fn build_config(self) -> KeyboardConfig<'source> {
+    /// The positions named `name` in the matrix or aliases sections.
+    fn aliases(&mut self, name: &str) -> Option<Vec<u16>> {
+        let positions = self.config.get_aliases(name)?.clone();
+        self.used_names.insert(matrix_key(name));
+        Some(positions)
+    }
+
+    /// Warn if `keypos` has already been assigned in this layer section.
+    fn note_assignment(&mut self, name: &str, composite: u32, keypos: u16, span: SourceRange) {
+        let index = if composite == 0 {
+            self.config.layers[name].index
+        } else {
+            0
+        };
+        if !self.assigned.insert((index, composite, keypos)) {
+            self.config.warnings.push(error_span(
+                "Key assigned more than once in this layer; only the last assignment is used",
+                span,
+            ));
+        }
+    }
+
+    fn build_config(mut self) -> KeyboardConfig<'source> {
+        let mut warned = HashSet::new();
+        for (name, span) in self.aliases {
+            if !self.used_names.contains(&name) && warned.insert(name) {
+                let warning = format!(
+                    "Alias '{}' is never used",
+                    &self.config.source[span.clone()]
+                );
+                self.config.warnings.push(error_span(warning, span));
+            }
+        }
+        let warnings = self.config.layer_warnings();
+        self.config.warnings.extend(warnings);
+        self.config
+            .warnings
+            .sort_by_key(|w| w.span.as_ref().map(|s| s.start));
         self.config
     }
 
@@ -1004,11 +1492,6 @@ const DEFAULT_LAYERS: [(&str, u8); 6] = [
     ("main", 0),
 ];
 
-#[cfg(test)]
-fn data_to_usize(x: u16) -> usize {
-    u16::from_le(x) as usize
-}
-
 impl<'source> KeyboardConfig<'source> {
     fn new(path: PathBuf, source: &'source str) -> Self {
         let mut layers: HashMap<String, ConfigLayer> = Default::default();
@@ -1027,12 +1510,65 @@ impl<'source> KeyboardConfig<'source> {
             composites: Default::default(),
             macros_names: Default::default(),
             macros: Default::default(),
+            combos: Default::default(),
+            leader_sequences: Default::default(),
+            caps_word_keys: Default::default(),
+            hands: Default::default(),
+            led_layers: Default::default(),
+            section_spans: Default::default(),
+            warnings: Default::default(),
             next_layer: DEFAULT_LAYERS.len() as u16,
             row_count: 0,
             col_count: 0,
         }
     }
 
+    /// The number of rows and columns in the key matrix.
+    pub fn matrix_size(&self) -> (u8, u8) {
+        (self.row_count, self.col_count)
+    }
+
+    /// Grow the key matrix to `row_count` rows and `col_count` columns so the serialized layout can
+    /// be loaded by firmware built for a larger matrix. The matrix never shrinks.
+    pub fn resize_matrix(&mut self, row_count: u8, col_count: u8) {
+        self.row_count = self.row_count.max(row_count);
+        self.col_count = self.col_count.max(col_count);
+    }
+
+    /// The first matrix position (`row << 8 | col`) given the name `name` in the `matrix` or
+    /// `aliases` sections. `name` may also be a hex position as used in the `matrix` section.
+    pub fn matrix_position(&self, name: &str) -> Option<u16> {
+        self.key_position(name)
+            .or_else(|| self.get_aliases(name).and_then(|v| v.first().copied()))
+    }
+
+    /// The names given to the key at matrix position `pos` (`row << 8 | col`) in the `matrix` and
+    /// `aliases` sections.
+    pub fn position_names(&self, pos: u16) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .matrix_map
+            .iter()
+            .filter(|(_, positions)| positions.contains(&pos))
+            .map(|(name, _)| {
+                u16::from_str_radix(name, 16)
+                    .ok()
+                    .filter(|_| name.len() == 4 && !name.contains(|c: char| c.is_lowercase()))
+                    .and_then(keycodes::key_name)
+                    .map_or_else(|| name.clone(), |n| n.to_string())
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// The name of the layer numbered `index`.
+    pub fn layer_name(&self, index: u16) -> Option<&str> {
+        self.layers
+            .iter()
+            .find(|(_, l)| l.index == index)
+            .map(|(name, _)| name.as_str())
+    }
+
     pub fn layer_count(&self) -> usize {
         self.layers.len() + self.composites.len()
     }
@@ -1041,52 +1577,119 @@ impl<'source> KeyboardConfig<'source> {
         self.macros.len()
     }
 
-    #[cfg(test)]
-    fn deserialize(data: &[u16]) -> Self {
-        assert!(data.len() > 14);
-        assert_eq!(data[0], PROTOCOL_VERSION);
+    pub fn combo_count(&self) -> usize {
+        self.combos.len()
+    }
 
-        let mut config = Self::new(PathBuf::from(""), "");
+    pub fn leader_sequence_count(&self) -> usize {
+        self.leader_sequences.len()
+    }
 
-        config.row_count = (u16::from_le(data[1]) >> 8) as u8;
-        config.col_count = (u16::from_le(data[1]) & 0xff) as u8;
-        let layer_count = (u16::from_le(data[2]) & 0xff) as usize;
-        // TODO let macros_count = u16::from_le(data[3]) as usize;
-        let globals_count = u16::from_le(data[4]) as usize;
+    /// Decode a layout binary made by [`Self::serialize`]. The layers added by the config are named
+    /// `layer<index>` and no matrix names are recovered.
+    pub fn deserialize(data: &[u16]) -> Result<Self> {
+        let invalid = || ConfigError::from("Invalid layout binary");
+        let data: Vec<u16> = data.iter().map(|w| u16::from_le(*w)).collect();
+        if data.len() < 5 {
+            return Err(invalid());
+        }
+        if data[0] != PROTOCOL_VERSION {
+            return Err(ConfigError::from("Unsupported layout binary version"));
+        }
 
-        config.deserialize_globals(&mut data[5..5 + globals_count].iter().copied());
+        let mut config = Self::new(PathBuf::new(), "");
 
-        let data = &data[5 + globals_count..]; // TODO read globals
+        config.row_count = (data[1] >> 8) as u8;
+        config.col_count = (data[1] & 0xff) as u8;
+        let (row_count, col_count) = (config.row_count as usize, config.col_count as usize);
+        let layer_count = (data[2] & 0xff) as usize;
+        let composite_count = (data[2] >> 8) as usize;
+        let macros_count = data[3] as usize;
+        let layer_base = 5 + data[4] as usize;
 
-        for (i, (name, _)) in DEFAULT_LAYERS.into_iter().enumerate() {
-            let layer = config.layers.get_mut(name).unwrap();
-            layer.deserialize(
-                &data[data_to_usize(data[i])..data_to_usize(data[1 + i])],
-                config.row_count as usize,
-                config.col_count as usize,
-            );
-        }
+        config
+            .deserialize_globals(&mut data.get(5..layer_base).ok_or_else(invalid)?.iter().copied());
+
+        let data = &data[layer_base..];
+        let part = |i: usize| {
+            let start = *data.get(i).ok_or_else(invalid)? as usize;
+            let end = *data.get(i + 1).ok_or_else(invalid)? as usize;
+            data.get(start..end)
+                .filter(|d| !d.is_empty())
+                .ok_or_else(invalid)
+        };
 
-        for i in DEFAULT_LAYERS.len()..layer_count {
-            let name = format!("layer{i}");
-            config.new_layer(name.as_str(), 0);
+        if layer_count < DEFAULT_LAYERS.len() {
+            return Err(invalid());
+        }
+        for i in 0..layer_count {
+            let name = match DEFAULT_LAYERS.get(i) {
+                Some((name, _)) => name.to_string(),
+                None => {
+                    let name = format!("layer{i}");
+                    config.new_layer(name.as_str(), 0);
+                    name
+                }
+            };
             let layer = config.layers.get_mut(name.as_str()).unwrap();
-            layer.deserialize(
-                &data[data_to_usize(data[i])..data_to_usize(data[1 + i])],
-                config.row_count as usize,
-                config.col_count as usize,
-            );
+            if layer.deserialize(part(i)?, row_count, col_count) != Some(0) {
+                return Err(invalid());
+            }
+        }
+
+        for i in layer_count..layer_count + composite_count {
+            let mut layer = ConfigLayer::new(0, 0);
+            match layer.deserialize(part(i)?, row_count, col_count) {
+                Some(0) | None => return Err(invalid()),
+                Some(composite) => config.composites.insert(composite, layer),
+            };
         }
 
-        // TODO read in macros
+        for i in layer_count + composite_count..layer_count + composite_count + macros_count {
+            match Macro::deserialize(part(i)?).ok_or_else(invalid)? {
+                m @ Macro::Combo(..) => config.combos.push(m),
+                m @ Macro::LeaderSequence(..) => config.leader_sequences.push(m),
+                Macro::CapsWordKeys(keys) => config.caps_word_keys = keys,
+                Macro::LedLayers(pairs) => {
+                    config.led_layers = pairs.chunks_exact(2).map(|p| (p[0] as u8, p[1])).collect();
+                }
+                Macro::Hands(bitmap) => {
+                    for i in 0..row_count * col_count {
+                        let hand = (bitmap.get(i / 8).ok_or_else(invalid)? >> ((i % 8) * 2)) & 3;
+                        if hand != 0 {
+                            let keypos = (((i / col_count) << 8) | (i % col_count)) as u16;
+                            config.hands.push((keypos, hand as u8));
+                        }
+                    }
+                }
+                m => {
+                    config
+                        .macros_names
+                        .insert(m.serialize(), config.macros.len() as u16);
+                    config.macros.push(m);
+                }
+            }
+        }
 
-        config
+        Ok(config)
     }
 
     pub fn serialize(&self) -> Vec<u16> {
         let layer_count = self.layers.len();
         let composite_count = self.composites.len();
-        let macros_count = self.macros.len();
+        let caps_word_keys = if self.caps_word_keys.is_empty() {
+            None
+        } else {
+            Some(Macro::CapsWordKeys(self.caps_word_keys.clone()))
+        };
+        let led_layers = self.serialize_led_layers();
+        let hands = self.serialize_hands();
+        let macros_count = self.macros.len()
+            + led_layers.iter().len()
+            + hands.iter().len()
+            + caps_word_keys.iter().len()
+            + self.leader_sequences.len()
+            + self.combos.len();
 
         let globals = self.serialize_globals();
 
@@ -1131,7 +1734,19 @@ impl<'source> KeyboardConfig<'source> {
         }
         let macro_base = composite_base + composite_count;
 
-        for (i, mut m) in self.macros.iter().map(|m| m.serialize()).enumerate() {
+        // LED layers, hands, caps word keys, leader sequences and combos are never referenced by
+        // keycode so they go last where the firmware can find them
+        for (i, mut m) in self
+            .macros
+            .iter()
+            .chain(led_layers.iter())
+            .chain(hands.iter())
+            .chain(caps_word_keys.iter())
+            .chain(self.leader_sequences.iter())
+            .chain(self.combos.iter())
+            .map(|m| m.serialize())
+            .enumerate()
+        {
             out[macro_base + i] = ((out.len() - layer_base) as u16).to_le();
             out.append(&mut m);
         }
@@ -1141,7 +1756,33 @@ impl<'source> KeyboardConfig<'source> {
         out
     }
 
-    #[cfg(test)]
+    /// Pairs of LED bit and layer index.
+    fn serialize_led_layers(&self) -> Option<Macro> {
+        if self.led_layers.is_empty() {
+            return None;
+        }
+        Some(Macro::LedLayers(
+            self.led_layers
+                .iter()
+                .flat_map(|(led, layer)| [*led as u16, *layer])
+                .collect(),
+        ))
+    }
+
+    /// Two bits per matrix position for the hand; eight positions per word.
+    fn serialize_hands(&self) -> Option<Macro> {
+        if self.hands.is_empty() {
+            return None;
+        }
+        let col_count = self.col_count as usize;
+        let mut bitmap = vec![0; (self.row_count as usize * col_count).div_ceil(8)];
+        for (keypos, hand) in self.hands.iter() {
+            let i = (keypos >> 8) as usize * col_count + (keypos & 0xff) as usize;
+            bitmap[i / 8] |= (*hand as u16) << ((i % 8) * 2);
+        }
+        Some(Macro::Hands(bitmap))
+    }
+
     fn deserialize_globals(&mut self, data: &mut impl Iterator<Item = u16>) {
         while let Some(gp) = GlobalProp::deserialize(data) {
             if let Some(name) = gp.default_name() {
@@ -1244,26 +1885,19 @@ impl<'source> KeyboardConfig<'source> {
     }
 
     fn assign_position_name(&mut self, pos: u16, name: &str) {
-        if let Some(code) = key_code(name) {
-            let name = format!("{code:04X}");
-            if let Some(v) = self.matrix_map.get_mut(name.as_str()) {
-                v.push(pos);
-            } else {
-                self.matrix_map.insert(name, vec![pos]);
-            }
-        } else if let Some(v) = self.matrix_map.get_mut(name) {
-            v.push(pos);
-        } else {
-            self.matrix_map.insert(name.into(), vec![pos]);
-        }
+        self.matrix_map
+            .entry(matrix_key(name))
+            .or_default()
+            .push(pos);
     }
 
     fn get_aliases(&self, name: &str) -> Option<&Vec<u16>> {
-        if let Some(code) = key_code(name) {
-            self.matrix_map.get(format!("{code:04X}").as_str())
-        } else {
-            self.matrix_map.get(name)
-        }
+        self.matrix_map.get(matrix_key(name).as_str())
+    }
+
+    /// The warnings found while compiling, in source order.
+    pub fn warnings(&self) -> &[ConfigError] {
+        &self.warnings
     }
 
     #[cfg(test)]
@@ -1289,7 +1923,7 @@ impl<'source> KeyboardConfig<'source> {
         self.next_layer += 1;
     }
 
-    fn get_layer_index(&self, name: &str) -> Option<u16> {
+    pub fn get_layer_index(&self, name: &str) -> Option<u16> {
         self.layers.get(name).map(|l| l.index)
     }
 
@@ -1383,7 +2017,7 @@ impl<'source> KeyboardConfig<'source> {
                     s..i,
                 ));
             }
-            "aliases" | "global" => {}
+            "aliases" | "combos" | "leader" | "hands" | "leds" | "global" => {}
             _ if name.starts_with("global.") => {}
             _ => {
                 if let Some(pos) = name.find(invalid_section_char) {
@@ -1410,6 +2044,9 @@ impl<'source> KeyboardConfig<'source> {
                 } else if !name.contains('+') {
                     self.new_layer(name, code);
                 }
+                self.section_spans
+                    .entry(name.to_string())
+                    .or_insert(s..s + name.len());
             }
         }
 
@@ -1430,6 +2067,93 @@ impl<'source> KeyboardConfig<'source> {
         self.firmware_get(arg).map(|v| self.text(&v))
     }
 
+    /// Warn about layers no action can activate and composite layers whose parts are never all
+    /// active together.
+    fn layer_warnings(&self) -> Vec<ConfigError> {
+        let led_layers = self.led_layers.iter().map(|(_, layer)| *layer);
+        let mut reachable: HashSet<u16> = (0..DEFAULT_LAYERS.len() as u16)
+            .chain(led_layers.clone())
+            .collect();
+        // only pushed layers can be part of an active composite; the main layer and those set by
+        // setlayout() are not
+        let mut pushed: HashSet<u16> = (0..5).chain(led_layers).collect();
+
+        let combos = self.combos.iter().chain(&self.leader_sequences);
+        let mut pending: Vec<u16> = combos
+            .filter_map(|mac| match mac {
+                Macro::Combo(code, _) | Macro::LeaderSequence(code, _) => Some(*code),
+                _ => None,
+            })
+            .collect();
+        let mut scanned_layers = HashSet::new();
+        let mut scanned_macros = HashSet::new();
+        loop {
+            for layer in self.layers.values() {
+                if reachable.contains(&layer.index) && scanned_layers.insert(layer.index as u32) {
+                    pending.extend(layer.codes.values());
+                }
+            }
+            for (&bits, layer) in &self.composites {
+                let active = (0..32).all(|i| bits & (1 << i) == 0 || pushed.contains(&i));
+                if active && scanned_layers.insert(bits | (1 << 31)) {
+                    pending.extend(layer.codes.values());
+                }
+            }
+            if pending.is_empty() {
+                break;
+            }
+            while let Some(code) = pending.pop() {
+                match code {
+                    key_range::LAYER_MIN..=key_range::TOGGLE_MAX
+                    | key_range::ONESHOT_MIN..=key_range::ONESHOT_MAX => {
+                        reachable.insert(code & 0xff);
+                        pushed.insert(code & 0xff);
+                    }
+                    key_range::SET_LAYOUT_MIN..=key_range::SET_LAYOUT_MAX => {
+                        reachable.insert(code & 0xff);
+                    }
+                    key_range::MACROS_MIN..=key_range::MACROS_MAX
+                        if scanned_macros.insert(code) =>
+                    {
+                        let mac = self.macros.get((code - key_range::MACROS_MIN) as usize);
+                        pending.extend(mac.map(Macro::children).unwrap_or_default());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut warnings = vec![];
+        for (name, span) in &self.section_spans {
+            if name == "firmware" {
+                // holds firmware settings, not key assignments
+                continue;
+            }
+            if name.contains('+') {
+                if let Some(part) = name
+                    .split('+')
+                    .find(|l| !pushed.contains(&self.layers[*l].index))
+                {
+                    warnings.push(error_span(
+                        format!(
+                            "Composite layer '{name}' can never be active; '{part}' is never \
+                             activated by layer(), toggle(), oneshot() or overload()"
+                        ),
+                        span.clone(),
+                    ));
+                }
+            } else if let Some(layer) = self.layers.get(name)
+                && !reachable.contains(&layer.index)
+            {
+                warnings.push(error_span(
+                    format!("Layer '{name}' can not be reached; no action activates it"),
+                    span.clone(),
+                ));
+            }
+        }
+        warnings
+    }
+
     fn ensure_composite(&mut self, name: &str) -> Result<u32> {
         let mut composite = 0;
         let mut i = 0;
@@ -1472,31 +2196,35 @@ impl ConfigLayer {
         }
     }
 
-    #[cfg(test)]
-    fn deserialize(&mut self, data: &[u16], row_count: usize, col_count: usize) {
-        self.suffix = u16::from_le(data[0]) as u8;
+    /// Decode a layer made by [`Self::serialize`] returning the composite bits; `None` if the data
+    /// is too short.
+    fn deserialize(&mut self, data: &[u16], row_count: usize, col_count: usize) -> Option<u32> {
+        let head = *data.first()?;
+        self.suffix = head as u8;
+        self.composite_part = head & COMPOSITE_PART_BIT != 0;
+
+        let (composite, data) = if head & COMPOSITE_BIT != 0 {
+            let [_, lo, hi, data @ ..] = data else {
+                return None;
+            };
+            (*lo as u32 | ((*hi as u32) << 16), data)
+        } else {
+            (0, &data[1..])
+        };
 
-        let data = &data[1..];
         if row_count * col_count == data.len() {
             for row in 0..row_count {
                 for col in 0..col_count {
-                    self.codes.insert(
-                        ((row << 8) + col) as u16,
-                        u16::from_le(data[row * col_count + col]),
-                    );
+                    self.codes
+                        .insert(((row << 8) + col) as u16, data[row * col_count + col]);
                 }
             }
         } else {
-            let mut k = 0;
-            for (i, v) in data.iter().enumerate() {
-                let v = u16::from_le(*v);
-                if i & 1 == 0 {
-                    k = v;
-                } else {
-                    self.codes.insert(k, v);
-                }
+            for pair in data.chunks_exact(2) {
+                self.codes.insert(pair[0], pair[1]);
             }
         }
+        Some(composite)
     }
 
     fn serialize(&self, row_count: usize, col_count: usize, composite: u32) -> Vec<u16> {
@@ -1546,15 +2274,36 @@ impl ConfigLayer {
     }
 }
 
+/// The `matrix_map` key of a position name; key names are stored by keycode so that any of their
+/// names can be used.
+fn matrix_key(name: &str) -> String {
+    match key_code(name) {
+        Some(code) => format!("{code:04X}"),
+        None => name.to_string(),
+    }
+}
+
 fn error_span(message: impl Into<String>, range: SourceRange) -> ConfigError {
     ConfigError::new(message.into(), range)
 }
 
 pub fn compile(path: PathBuf, source: &str) -> Result<KeyboardConfig<'_>> {
+    compile_all(path, source).map_err(|mut errors| errors.swap_remove(0))
+}
+
+/// Like [`compile`] but returns every error found in the source, in source order.
+pub fn compile_all(
+    path: PathBuf,
+    source: &str,
+) -> core::result::Result<KeyboardConfig<'_>, Vec<ConfigError>> {
     let mut parser = Parser::new(path, source);
 
-    parser.parse_sections()?;
-    Ok(parser.build_config())
+    parser.parse_sections();
+    if parser.errors.is_empty() {
+        Ok(parser.build_config())
+    } else {
+        Err(parser.errors)
+    }
 }
 
 #[cfg(test)]
@@ -1,6 +1,7 @@
 use std::path::Path;
 
 use key_range::{LAYER_MIN, MACROS_MIN, TOGGLE_MIN};
+use rpk_common::globals::{debounce_modes, dual_action_modes};
 
 use crate::{globals::spec::GlobalType, test::kc};
 
@@ -179,6 +180,210 @@ b = overload(control, C-c, 36, 45)
     assert_eq!(control_cc, &exp);
 }
 
+#[test]
+fn dual_action_mode() {
+    let src = r#"
+[global]
+dual_action_mode = balanced
+
+[matrix:1x3]
+0x00 = a b c
+
+[main]
+
+a = dualaction(rightshift, a, permissive_hold)
+b = overload(control, b, 200, hold_on_other_key_press)
+c = dualaction(rightshift, c, 200, 30, timeout)
+"#;
+
+    let config = pretty_compile(src).expect("should allow dual action modes");
+
+    match config.global("dual_action_mode").unwrap().spec {
+        GlobalType::DualActionMode(v) => assert_eq!(v, dual_action_modes::BALANCED),
+        _ => panic!("expected GlobalType::DualActionMode"),
+    }
+
+    assert_eq!(
+        config.macros[0],
+        Macro::ModalDualAction(
+            kc("a"),
+            kc("rightshift"),
+            u16::MAX,
+            u16::MAX,
+            dual_action_modes::PERMISSIVE_HOLD,
+            u16::MAX
+        )
+    );
+    assert_eq!(
+        config.macros[1],
+        Macro::ModalDualAction(
+            kc("b"),
+            LAYER_MIN,
+            200,
+            u16::MAX,
+            dual_action_modes::HOLD_ON_OTHER_KEY_PRESS,
+            u16::MAX
+        )
+    );
+    assert_eq!(
+        config.macros[2],
+        Macro::ModalDualAction(
+            kc("c"),
+            kc("rightshift"),
+            200,
+            30,
+            dual_action_modes::TIMEOUT,
+            u16::MAX
+        )
+    );
+    assert_eq!(
+        config.macros[2].serialize(),
+        vec![
+            macro_types::DUAL_ACTION,
+            kc("c"),
+            kc("rightshift"),
+            200,
+            30,
+            0
+        ]
+    );
+
+    let src = r#"
+[matrix:1x1]
+0x00 = a
+
+[main]
+a = dualaction(rightshift, a, 200, eager)
+"#;
+
+    let err = test_compile(src).err().unwrap();
+    assert_eq!(
+        err.message,
+        "Invalid dual action mode; only timeout, hold_on_other_key_press, permissive_hold, \
+balanced, bilateral are valid"
+    );
+    assert_eq!(err.span.unwrap(), 66..71);
+
+    let src = r#"
+[global]
+dual_action_mode = eager
+"#;
+
+    let err = test_compile(src).err().unwrap();
+    assert!(err.message.starts_with("Invalid dual action mode"));
+    assert_eq!(err.span.unwrap(), 29..34);
+}
+
+#[test]
+fn debounce_mode() {
+    let src = r#"
+[global]
+debounce_mode = asymmetric
+"#;
+
+    let config = pretty_compile(src).expect("should allow debounce modes");
+
+    match config.global("debounce_mode").unwrap().spec {
+        GlobalType::DebounceMode(v) => assert_eq!(v, debounce_modes::ASYMMETRIC),
+        _ => panic!("expected GlobalType::DebounceMode"),
+    }
+
+    let src = r#"
+[global]
+debounce_mode = lazy
+"#;
+
+    let err = test_compile(src).err().unwrap();
+    assert_eq!(
+        err.message,
+        "Invalid debounce mode; only eager, defer, asymmetric are valid"
+    );
+    assert_eq!(err.span.unwrap(), 26..30);
+}
+
+#[test]
+fn dual_action_require_prior_idle() {
+    compile_global!(src, config, "dual_action_require_prior_idle", 150, {
+        let config = config.unwrap();
+
+        match config
+            .global("dual_action_require_prior_idle")
+            .unwrap()
+            .spec
+        {
+            GlobalType::Timeout { value, .. } => assert_eq!(value, 150),
+            _ => panic!("expected GlobalType::Timeout"),
+        }
+    });
+
+    let src = r#"
+[matrix:1x2]
+0x00 = a b
+
+[main]
+
+a = dualaction(rightshift, a, require_prior_idle=120)
+b = overload(control, b, 200, balanced, require_prior_idle=0)
+"#;
+
+    let config = pretty_compile(src).expect("should allow require_prior_idle");
+
+    assert_eq!(
+        config.macros[0],
+        Macro::ModalDualAction(kc("a"), kc("rightshift"), u16::MAX, u16::MAX, u16::MAX, 120)
+    );
+    assert_eq!(
+        config.macros[0].serialize(),
+        vec![
+            macro_types::DUAL_ACTION,
+            kc("a"),
+            kc("rightshift"),
+            u16::MAX,
+            u16::MAX,
+            u16::MAX,
+            120
+        ]
+    );
+    assert_eq!(
+        config.macros[1],
+        Macro::ModalDualAction(
+            kc("b"),
+            LAYER_MIN,
+            200,
+            u16::MAX,
+            dual_action_modes::BALANCED,
+            0
+        )
+    );
+
+    let src = r#"
+[matrix:1x1]
+0x00 = a
+
+[main]
+a = dualaction(rightshift, a, require_prior_idle=5001)
+"#;
+
+    let err = test_compile(src).err().unwrap();
+    assert_eq!(
+        err.message,
+        "Invalid duration; only 0 to 5000 milliseconds are valid"
+    );
+    assert_eq!(err.span.unwrap(), 80..84);
+
+    let src = r#"
+[matrix:1x1]
+0x00 = a
+
+[main]
+a = dualaction(rightshift, a, require_prior_idle=10, 200)
+"#;
+
+    let err = test_compile(src).err().unwrap();
+    assert_eq!(err.message, "Unexpected argument");
+    assert_eq!(err.span.unwrap(), 84..87);
+}
+
 #[test]
 fn mod_tapdance_action() {
     let src = r#"
@@ -236,6 +441,298 @@ a = tapdancet(120, layer(shift), x)
     assert_eq!(exp.serialize(), &[7, 120, 1537, 27]);
 }
 
+#[test]
+fn combos() {
+    let src = r#"
+[matrix:2x3]
+0x00 = a b c
+0x10 = d e f
+
+[aliases]
+0x12 = home
+
+[combos]
+a+b = escape
+0x10+home+c = macro(h i)
+"#;
+
+    let config = pretty_compile(src).expect("should allow combos");
+
+    assert_eq!(config.combo_count(), 2);
+    assert_eq!(config.macro_count(), 1);
+    assert_eq!(
+        config.combos[0],
+        Macro::Combo(kc("escape"), vec![0x000, 0x001])
+    );
+    assert_eq!(
+        config.combos[1],
+        Macro::Combo(MACROS_MIN, vec![0x100, 0x102, 0x002])
+    );
+
+    let bin = config.serialize();
+    assert_eq!(bin[3], 3);
+    assert_eq!(
+        &bin[bin.len() - 9..],
+        &[
+            8,
+            kc("escape"),
+            0x000,
+            0x001,
+            8,
+            MACROS_MIN,
+            0x100,
+            0x102,
+            0x002
+        ]
+    );
+}
+
+#[test]
+fn invalid_combos() {
+    let src = r#"
+[matrix:1x2]
+0x00 = a b
+
+[combos]
+a+a = escape
+"#;
+
+    let err = test_compile(src).err().unwrap();
+    assert_eq!(err.message, "A combo must have 2 to 4 different keys");
+    assert_eq!(err.span.unwrap(), 37..38);
+
+    let src = r#"
+[matrix:1x2]
+0x00 = a b
+
+[combos]
+a = escape
+"#;
+
+    let err = test_compile(src).err().unwrap();
+    assert_eq!(err.message, "A combo must have 2 to 4 different keys");
+    assert_eq!(err.span.unwrap(), 35..36);
+
+    let src = r#"
+[matrix:1x2]
+0x00 = a b
+
+[combos]
+a+x = escape
+"#;
+
+    let err = test_compile(src).err().unwrap();
+    assert_eq!(err.message, "key not found! x");
+    assert_eq!(err.span.unwrap(), 37..38);
+}
+
+#[test]
+fn leader_sequences() {
+    let src = r#"
+[matrix:1x3]
+0x00 = a b c
+
+[main]
+c = leader()
+
+[leader]
+a = escape
+a  \; b = C-s # comment
+"#;
+
+    let config = pretty_compile(src).expect("should allow leader sequences");
+
+    assert_eq!(config.leader_sequence_count(), 2);
+    assert_eq!(config.macros[0], Macro::Leader);
+    assert_eq!(config.code_at("main", 0x002), MACROS_MIN);
+    assert_eq!(
+        config.leader_sequences[0],
+        Macro::LeaderSequence(kc("escape"), vec![kc("a")])
+    );
+    assert_eq!(
+        config.leader_sequences[1],
+        Macro::LeaderSequence(MACROS_MIN + 1, vec![kc("a"), kc(";"), kc("b")])
+    );
+
+    let bin = config.serialize();
+    assert_eq!(bin[3], 4);
+    assert_eq!(
+        &bin[bin.len() - 8..],
+        &[
+            10,
+            kc("escape"),
+            kc("a"),
+            10,
+            MACROS_MIN + 1,
+            kc("a"),
+            kc(";"),
+            kc("b")
+        ]
+    );
+}
+
+#[test]
+fn hands() {
+    let src = r#"
+[matrix:2x3]
+0x00 = a b c
+0x10 = e f g
+
+[hands]
+left = a b
+left = e
+right = c f 0x12
+"#;
+
+    let config = pretty_compile(src).expect("should allow hands");
+    assert_eq!(config.layer_count(), 6);
+    assert!(!config.layers.contains_key("hands"));
+
+    let bin = config.serialize();
+    assert_eq!(bin[3], 1);
+    assert_eq!(
+        &bin[bin.len() - 2..],
+        &[
+            macro_types::HANDS,
+            1 | 1 << 2 | 2 << 4 | 1 << 6 | 2 << 8 | 2 << 10
+        ]
+    );
+
+    let src = r#"
+[matrix:1x2]
+0x00 = a b
+
+[hands]
+middle = a
+"#;
+
+    let err = test_compile(src).err().unwrap();
+    assert_eq!(err.message, "Expected left or right");
+    assert_eq!(err.span.unwrap(), 34..40);
+
+    let src = r#"
+[matrix:1x2]
+0x00 = a b
+
+[hands]
+left = a x
+"#;
+
+    let err = test_compile(src).err().unwrap();
+    assert_eq!(err.message, "key not found! x");
+    assert_eq!(err.span.unwrap(), 43..44);
+}
+
+#[test]
+fn leds() {
+    let src = r#"
+[matrix:1x3]
+0x00 = a b c
+
+[main]
+a = ifled(numlock, 1, end)
+
+[nav]
+b = left
+
+[leds]
+capslock = nav
+numlock = shift
+"#;
+
+    let config = pretty_compile(src).expect("should allow leds");
+    assert_eq!(config.layer_count(), 7);
+    assert!(!config.layers.contains_key("leds"));
+
+    assert_eq!(
+        config.macros.first(),
+        Some(&Macro::IfLed(leds::NUM_LOCK, kc("1"), kc("end")))
+    );
+
+    let bin = config.serialize();
+    assert_eq!(bin[3], 2);
+    assert_eq!(
+        &bin[bin.len() - 8..],
+        &[
+            macro_types::IF_LED | (leds::NUM_LOCK as u16) << 8,
+            kc("1"),
+            kc("end"),
+            macro_types::LED_LAYERS,
+            leds::CAPS_LOCK as u16,
+            config.get_layer_index("nav").unwrap(),
+            leds::NUM_LOCK as u16,
+            1,
+        ]
+    );
+
+    let src = r#"
+[matrix:1x2]
+0x00 = a b
+
+[leds]
+shiftlock = shift
+"#;
+
+    let err = test_compile(src).err().unwrap();
+    assert_eq!(
+        err.message,
+        "Invalid LED; only numlock, capslock, scrolllock, compose, kana are valid"
+    );
+    assert_eq!(err.span.unwrap(), 33..42);
+
+    let src = r#"
+[matrix:1x2]
+0x00 = a b
+
+[leds]
+capslock = nav
+"#;
+
+    let err = test_compile(src).err().unwrap();
+    assert_eq!(err.message, "Unknown layer name nav");
+    assert_eq!(err.span.unwrap(), 44..47);
+}
+
+#[test]
+fn invalid_leader_sequences() {
+    let src = r#"
+[matrix:1x2]
+0x00 = a b
+
+[leader]
+a foo = escape
+"#;
+
+    let err = test_compile(src).err().unwrap();
+    assert_eq!(err.message, "Unknown keycode");
+    assert_eq!(err.span.unwrap(), 37..40);
+
+    let src = r#"
+[matrix:1x2]
+0x00 = a b
+
+[leader]
+a b a b a b = escape
+"#;
+
+    let err = test_compile(src).err().unwrap();
+    assert_eq!(err.message, "A leader sequence must have 1 to 5 keys");
+    assert_eq!(err.span.unwrap(), 35..46);
+
+    let src = r#"
+[matrix:1x2]
+0x00 = a b
+
+[leader]
+a b = escape
+a  b = x
+"#;
+
+    let err = test_compile(src).err().unwrap();
+    assert_eq!(err.message, "Duplicate leader sequence");
+    assert_eq!(err.span.unwrap(), 48..52);
+}
+
 #[test]
 fn tapdance_not_allowed_in_macro() {
     let src = r#"
@@ -417,7 +914,7 @@ b = S-G-z
     assert_eq!(
         &codes,
         &[
-            1, 258, 6, 2, 0, 9, 10, 11, 12, 13, 14, 17, 19, 21, 1, 2, 4, 8, 64, 0, m0, m1, 256, 27,
+            2, 258, 6, 2, 0, 9, 10, 11, 12, 13, 14, 17, 19, 21, 1, 2, 4, 8, 64, 0, m0, m1, 256, 27,
             2560, 29
         ]
     );
@@ -454,12 +951,57 @@ fn compiling_to_binary() {
     assert_eq!(
         &codes,
         &[
-            1, 771, 6, 0, 0, 7, 8, 18, 19, 20, 21, 31, 1, 2, 4, 5, 6, 7, 8, 9, 0, 0, 0, 4, 8, 64,
+            2, 771, 6, 0, 0, 7, 8, 18, 19, 20, 21, 31, 1, 2, 4, 5, 6, 7, 8, 9, 0, 0, 0, 4, 8, 64,
             0, 36, 37, 38, 33, 34, 35, 30, 31, 1537
         ]
     );
 }
 
+#[test]
+fn resize_matrix() {
+    let src = r#"
+[matrix:2x3]
+0x00 = a b c
+0x10 = d e f
+
+[main]
+f = x
+"#;
+
+    let mut config = pretty_compile(src).unwrap();
+    config.resize_matrix(8, 2);
+    assert_eq!(config.matrix_size(), (8, 3));
+
+    let c2 = KeyboardConfig::deserialize(&config.serialize()).unwrap();
+    assert_eq!((c2.row_count, c2.col_count), (8, 3));
+    assert_eq!(c2.layers.get("main").unwrap().code_at(0x0102), kc("x"));
+}
+
+#[test]
+fn position_names() {
+    let src = r#"
+[matrix:2x3]
+0x00 = a b c
+0x10 = d e f
+
+[aliases]
+0x12 = home
+0x01 = thumb
+"#;
+
+    let config = pretty_compile(src).unwrap();
+
+    assert_eq!(config.matrix_size(), (2, 3));
+    assert_eq!(config.position_names(0x0001), vec!["b", "thumb"]);
+    assert_eq!(config.position_names(0x0102), vec!["f", "home"]);
+    assert!(config.position_names(0x0103).is_empty());
+    assert_eq!(config.matrix_position("thumb"), Some(0x0001));
+    assert_eq!(config.matrix_position("f"), Some(0x0102));
+    assert_eq!(config.matrix_position("enc0_cw"), None);
+    assert_eq!(config.layer_name(5), Some("main"));
+    assert_eq!(config.layer_name(99), None);
+}
+
 #[test]
 fn aliases() {
     let src = r#"
@@ -560,7 +1102,7 @@ a = z
     assert_eq!(
         &bytes,
         &[
-            1, 514, 518, 0, 0, 9, 10, 11, 12, 13, 14, 19, 24, 31, 513, 514, 516, 520, 64, 0, 4, 5,
+            2, 514, 518, 0, 0, 9, 10, 11, 12, 13, 14, 19, 24, 31, 513, 514, 516, 520, 64, 0, 4, 5,
             6, 7, 262, 6, 0, 0, 29, 269, 13, 0, 0, 31, 0, 44
         ]
     );
@@ -798,12 +1340,12 @@ b = 1
     assert_eq!(
         &bytes,
         &[
-            1, 515, 7, 0, 0, 8, 9, 14, 15, 16, 17, 24, 27, 1, 2, 0, 29, 256, 39, 4, 8, 64, 0, 1542,
+            2, 515, 7, 0, 0, 8, 9, 14, 15, 16, 17, 24, 27, 1, 2, 0, 29, 256, 39, 4, 8, 64, 0, 1542,
             5, 225, 6, 7, 229, 0, 1, 30
         ]
     );
 
-    let c2 = KeyboardConfig::deserialize(&bytes);
+    let c2 = KeyboardConfig::deserialize(&bytes).unwrap();
 
     assert_eq!(c2.row_count, 2);
     assert_eq!(c2.col_count, 3);
@@ -921,7 +1463,7 @@ max_ticks_per_ms = 5.0
     let bin = config.serialize();
     assert_eq!(bin.len(), 60);
 
-    let c2 = KeyboardConfig::deserialize(&bin);
+    let c2 = KeyboardConfig::deserialize(&bin).unwrap();
 
     let GlobalType::MouseProfile(mp2) = c2.global("mouse_profile1").unwrap().spec else {
         panic!("expected mouse profile");
@@ -1062,7 +1604,7 @@ fn global_dual_action_timeout() {
         assert_eq!(
             bin,
             [
-                1, 0, 6, 0, 2, 3, 500, 7, 8, 9, 10, 11, 12, 13, 1, 2, 4, 8, 64, 0
+                2, 0, 6, 0, 2, 3, 500, 7, 8, 9, 10, 11, 12, 13, 1, 2, 4, 8, 64, 0
             ]
         );
     });
@@ -1090,7 +1632,7 @@ fn global_dual_action_timeout2() {
         assert_eq!(
             bin,
             [
-                1, 0, 6, 0, 2, 4, 50, 7, 8, 9, 10, 11, 12, 13, 1, 2, 4, 8, 64, 0
+                2, 0, 6, 0, 2, 4, 50, 7, 8, 9, 10, 11, 12, 13, 1, 2, 4, 8, 64, 0
             ]
         );
     });
@@ -1114,7 +1656,7 @@ fn tapdance_tap_timeout() {
         assert_eq!(
             bin,
             [
-                1, 0, 6, 0, 2, 6, 50, 7, 8, 9, 10, 11, 12, 13, 1, 2, 4, 8, 64, 0
+                2, 0, 6, 0, 2, 6, 50, 7, 8, 9, 10, 11, 12, 13, 1, 2, 4, 8, 64, 0
             ]
         );
     });
@@ -1139,12 +1681,44 @@ fn global_debounce_settle_time() {
         assert_eq!(
             bin,
             [
-                1, 0, 6, 0, 2, 5, 617, 7, 8, 9, 10, 11, 12, 13, 1, 2, 4, 8, 64, 0
+                2, 0, 6, 0, 2, 5, 617, 7, 8, 9, 10, 11, 12, 13, 1, 2, 4, 8, 64, 0
             ]
         );
     });
 }
 
+#[test]
+fn caps_word_keys() {
+    let src = r#"
+[global]
+caps_word_keys = dot  comma
+unicode_prefix = C-u
+
+[matrix:1x2]
+0x00 = a b
+
+[main]
+b = caps_word
+"#;
+
+    let config = pretty_compile(src).expect("should allow caps_word_keys");
+
+    assert_eq!(config.caps_word_keys, vec![kc("dot"), kc("comma")]);
+    assert_eq!(config.code_at("main", 0x001), kc("caps_word"));
+
+    let bin = config.serialize();
+    assert_eq!(&bin[bin.len() - 3..], &[11, kc("dot"), kc("comma")]);
+
+    let src = r#"
+[global]
+caps_word_keys = dot foo
+"#;
+
+    let err = test_compile(src).err().unwrap();
+    assert_eq!(err.message, "Unknown keycode");
+    assert_eq!(err.span.unwrap(), 31..34);
+}
+
 #[test]
 fn invalid_global_name() {
     compile_global!(src, config, "overload_tap_tmeout", "500", {
@@ -1175,3 +1749,190 @@ fn defaults() {
         assert_eq!(layer.suffix, v);
     }
 }
+
+#[test]
+fn all_errors() {
+    let src = r#"
+[matrix:1x3]
+0x00 = a b c
+
+[main]
+a = nokey
+b = x
+c = layer(nav)
+
+[combos:C]
+a+b = escape
+
+[global]
+dual_action_timeout = soon
+combo_timeout = 30
+stray
+
+[main]
+c = tapdance(a b
+a = q
+
+[nav+main]
+a = b
+"#;
+
+    let errors = compile_all(PathBuf::from(""), src).err().unwrap();
+    let errors: Vec<_> = errors
+        .iter()
+        .map(|e| (e.message.as_str(), &src[e.span.clone().unwrap()]))
+        .collect();
+
+    assert_eq!(
+        errors,
+        [
+            ("Unknown action/keycode", "nokey"),
+            ("Unknown layer name nav", "nav"),
+            ("suffix not allowed here", ":C"),
+            (
+                "Invalid duration; only 0 to 5000 milliseconds are valid",
+                "soon"
+            ),
+            ("Missing =", "stray\n\n"),
+            ("Expected , ", " "),
+            ("Unknown layer name nav", "nav"),
+        ]
+    );
+
+    assert_eq!(
+        pretty_compile(src).err().unwrap().message,
+        "Unknown action/keycode"
+    );
+}
+
+fn warnings(src: &str) -> Vec<String> {
+    let config = compile(PathBuf::from(""), src).unwrap();
+    config
+        .warnings()
+        .iter()
+        .map(|e| format!("{}: {}", &src[e.span.clone().unwrap()], e.message))
+        .collect()
+}
+
+#[test]
+fn duplicate_key_warnings() {
+    let src = r#"
+[matrix:2x3]
+0x00 = a b c
+0x10 = d e f
+
+[aliases]
+a = thumb
+d = thumb
+
+[main]
+a = x
+0x0001 = y z
+c = q
+thumb = space
+b = w
+
+[nav]
+a = x
+
+[main]
+c = r
+"#;
+
+    assert_eq!(
+        warnings(src),
+        [
+            "c: Key assigned more than once in this layer; only the last assignment is used",
+            "thumb: Key assigned more than once in this layer; only the last assignment is used",
+            "b: Key assigned more than once in this layer; only the last assignment is used",
+            "nav: Layer 'nav' can not be reached; no action activates it",
+            "c: Key assigned more than once in this layer; only the last assignment is used",
+        ]
+    );
+}
+
+#[test]
+fn unused_alias_warnings() {
+    let src = r#"
+[matrix:2x3]
+0x00 = a b c
+0x10 = d e f
+
+[aliases]
+a = esc
+b = fn
+c = left_thumb
+d = spare
+f = a
+
+[main]
+esc = escape
+f = x
+
+[combos]
+left_thumb+d = tab
+
+[hands]
+left = fn
+right = e
+"#;
+
+    assert_eq!(warnings(src), ["spare: Alias 'spare' is never used"]);
+}
+
+#[test]
+fn layer_warnings() {
+    let src = r#"
+[matrix:2x3]
+0x00 = a b c
+0x10 = d e f
+
+[main]
+a = overload(nav, tab)
+b = setlayout(qwerty)
+c = macro(x layer(sym))
+
+[nav]
+a = toggle(fun)
+
+[qwerty]
+a = oneshot(hidden)
+
+[sym]
+[fun]
+[hidden]
+[unused]
+
+[combos]
+d+e = layer(combo)
+
+[combo]
+[leds]
+capslock = lit
+
+[lit]
+a = layer(lit_only)
+
+[lit_only]
+
+[nav+sym]
+a = x
+
+[nav+qwerty]
+a = x
+
+[main+nav]
+a = x
+"#;
+
+    assert_eq!(
+        warnings(src),
+        [
+            "unused: Layer 'unused' can not be reached; no action activates it",
+            "nav+qwerty: Composite layer 'nav+qwerty' can never be active; 'qwerty' is never \
+             activated by layer(), toggle(), oneshot() or overload()",
+            "main+nav: Composite layer 'main+nav' can never be active; 'main' is never activated \
+             by layer(), toggle(), oneshot() or overload()",
+        ]
+    );
+}
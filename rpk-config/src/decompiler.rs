@@ -0,0 +1,678 @@
+//! Convert a layout binary back into config text.
+//!
+//! Macros are numbered by the compiler in the order they are first used so the statements are
+//! written in an order which uses them in the same order. The `unicode_prefix` and
+//! `unicode_suffix` globals are recovered from the `unicode()` macros which use them. Macros not
+//! used by anything are written as comments since the binary does not say where they came from;
+//! such a config compiles to a different binary.
+
+use std::{collections::HashSet, fmt::Write};
+
+use rpk_common::{
+    globals::{debounce_modes, dual_action_modes},
+    keycodes::{
+        key_range::{self, BASIC_0, BASIC_1, BASIC_A, MACROS_MAX, MACROS_MIN},
+        leds,
+    },
+    mouse::MouseAnalogSetting,
+};
+
+use crate::{
+    ConfigError,
+    compiler::{KeyboardConfig, Macro},
+    globals::spec::{self, GlobalType},
+    keycodes,
+};
+
+type Result<T> = core::result::Result<T, ConfigError>;
+
+/// Decompile a layout binary made by [`KeyboardConfig::serialize`] into config text which compiles
+/// back to the same binary.
+pub fn decompile(data: &[u16]) -> Result<String> {
+    let config = KeyboardConfig::deserialize(data)?;
+    Decompiler::new(&config)?.write()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Section {
+    Global,
+    Layer(u16),
+    Composite(u32),
+    Combos,
+    Leader,
+}
+
+struct Statement {
+    section: Section,
+    text: String,
+    codes: Vec<u16>,
+}
+
+struct Block {
+    section: Section,
+    created_before: usize,
+    lines: Vec<String>,
+}
+
+/// The layers before these are the predefined modifier layers and `main`.
+const DEFAULT_LAYER_COUNT: u16 = 6;
+
+struct Decompiler<'a> {
+    config: &'a KeyboardConfig<'a>,
+    /// The `unicode_prefix` and `unicode_suffix` codes of the `unicode()` macros.
+    unicode: Option<(u16, u16)>,
+}
+
+fn macro_id(code: u16) -> Option<usize> {
+    matches!(code, MACROS_MIN..=MACROS_MAX).then(|| (code - MACROS_MIN) as usize)
+}
+
+/// Split a `unicode()` macro sequence into its prefix, character and suffix.
+fn unicode_parts(seq: &[u16]) -> Option<(u16, char, u16)> {
+    let [prefix, digits @ .., suffix] = seq else {
+        return None;
+    };
+    if digits.is_empty() || digits.len() > 7 || digits[0] == BASIC_0 {
+        return None;
+    }
+    let mut uc = 0;
+    for d in digits {
+        uc = (uc << 4)
+            + match *d {
+                BASIC_0 => 0,
+                BASIC_1..BASIC_0 => (d - BASIC_1 + 1) as u32,
+                BASIC_A..=0x09 => (d - BASIC_A + 10) as u32,
+                _ => return None,
+            };
+    }
+    Some((*prefix, char::from_u32(uc)?, *suffix))
+}
+
+fn settle_time_text(value: u16) -> Result<String> {
+    let ms = value as f64 * 39063.0 / 1024.0 / 1000.0;
+    for precision in 0..=6 {
+        for ms in [ms, ms - 0.0005] {
+            let text = format!("{ms:.precision$}");
+            if spec::parse_key_settle_time(&text) == Ok(value) {
+                return Ok(text);
+            }
+        }
+    }
+    Err(ConfigError::from("Invalid debounce_settle_time"))
+}
+
+impl<'a> Decompiler<'a> {
+    fn new(config: &'a KeyboardConfig<'a>) -> Result<Self> {
+        for (id, mac) in config.macros.iter().enumerate() {
+            if mac
+                .children()
+                .into_iter()
+                .filter_map(macro_id)
+                .any(|child| child >= id)
+            {
+                return Err(ConfigError::from("Invalid layout binary"));
+            }
+        }
+
+        // the most used prefix and suffix; any other sequences are written as plain macros
+        let mut counts: Vec<((u16, u16), usize)> = vec![];
+        for mac in config.macros.iter() {
+            if let Macro::Tap(seq) = mac
+                && let Some((prefix, _, suffix)) = unicode_parts(seq)
+            {
+                match counts.iter_mut().find(|(p, _)| *p == (prefix, suffix)) {
+                    Some((_, n)) => *n += 1,
+                    None => counts.push(((prefix, suffix), 1)),
+                }
+            }
+        }
+        let unicode = counts.iter().rev().max_by_key(|(_, n)| *n).map(|(p, _)| *p);
+
+        Ok(Self { config, unicode })
+    }
+
+    /// The character of `mac` if it was compiled from `unicode()`.
+    fn unicode_char(&self, mac: &Macro) -> Option<char> {
+        let Macro::Tap(seq) = mac else {
+            return None;
+        };
+        let (prefix, c, suffix) = unicode_parts(seq)?;
+        (self.unicode == Some((prefix, suffix))).then_some(c)
+    }
+
+    fn write(&self) -> Result<String> {
+        let mut out = String::new();
+        out += "# Decompiled from a layout binary; key positions are written as 0xRRCC\n";
+        self.write_globals(&mut out)?;
+        let (rows, cols) = self.config.matrix_size();
+        let _ = writeln!(out, "\n[matrix:{rows}x{cols}]");
+
+        let mut declared = HashSet::new();
+        for block in self.blocks()? {
+            let header = match block.section {
+                Section::Global => "global".to_string(),
+                Section::Layer(index) => {
+                    let name = self.layer_name(index)?;
+                    let suffix = self.config.layers[name].suffix;
+                    if index < DEFAULT_LAYER_COUNT {
+                        if block.lines.is_empty() {
+                            continue;
+                        }
+                        name.to_string()
+                    } else if declared.insert(index) && suffix != 0 {
+                        format!("{name}:{}", keycodes::modifiers_to_string(suffix))
+                    } else {
+                        name.to_string()
+                    }
+                }
+                Section::Composite(bits) => self.composite_name(bits)?,
+                Section::Combos => "combos".to_string(),
+                Section::Leader => "leader".to_string(),
+            };
+            let _ = writeln!(out, "\n[{header}]");
+            for line in block.lines {
+                let _ = writeln!(out, "{line}");
+            }
+        }
+
+        self.write_hands(&mut out);
+        self.write_leds(&mut out)?;
+
+        Ok(out)
+    }
+
+    fn write_globals(&self, out: &mut String) -> Result<()> {
+        let mut globals: Vec<_> = self.config.global_map.iter().collect();
+        globals.sort_by_key(|(_, g)| g.index);
+
+        let mut lines = String::new();
+        let mut profiles = String::new();
+        for (name, global) in globals {
+            let name = *name;
+            let value = match global.spec {
+                GlobalType::Timeout { value, .. } => value.to_string(),
+                GlobalType::DebounceTimeout(value) => settle_time_text(value)?,
+                GlobalType::DualActionMode(value) => dual_action_modes::NAMES
+                    .get(value as usize)
+                    .ok_or_else(|| ConfigError::from("Invalid dual_action_mode"))?
+                    .to_string(),
+                GlobalType::DebounceMode(value) => debounce_modes::NAMES
+                    .get(value as usize)
+                    .ok_or_else(|| ConfigError::from("Invalid debounce_mode"))?
+                    .to_string(),
+                GlobalType::MouseProfile(config) => {
+                    for (field, setting) in
+                        [("movement", config.movement), ("scroll", config.scroll)]
+                    {
+                        write_mouse_setting(&mut profiles, name, field, setting);
+                    }
+                    continue;
+                }
+            };
+            let _ = writeln!(lines, "{name} = {value}");
+        }
+
+        if !self.config.caps_word_keys.is_empty() {
+            let keys = self
+                .config
+                .caps_word_keys
+                .iter()
+                .map(|c| self.key_name(*c))
+                .collect::<Result<Vec<_>>>()?;
+            let _ = writeln!(lines, "caps_word_keys = {}", keys.join(" "));
+        }
+
+        if !lines.is_empty() {
+            *out += "\n[global]\n";
+            *out += &lines;
+        }
+        *out += &profiles;
+        Ok(())
+    }
+
+    fn write_hands(&self, out: &mut String) {
+        if self.config.hands.is_empty() {
+            return;
+        }
+        *out += "\n[hands]\n";
+        for (name, hand) in [("left", 1), ("right", 2)] {
+            let keys: Vec<_> = self
+                .config
+                .hands
+                .iter()
+                .filter(|(_, h)| *h == hand)
+                .map(|(pos, _)| format!("0x{pos:04x}"))
+                .collect();
+            if !keys.is_empty() {
+                let _ = writeln!(out, "{name} = {}", keys.join(" "));
+            }
+        }
+    }
+
+    fn write_leds(&self, out: &mut String) -> Result<()> {
+        if self.config.led_layers.is_empty() {
+            return Ok(());
+        }
+        *out += "\n[leds]\n";
+        for (led, layer) in self.config.led_layers.iter() {
+            let _ = writeln!(
+                out,
+                "{} = {}",
+                self.led_name(*led)?,
+                self.layer_name(*layer)?
+            );
+        }
+        Ok(())
+    }
+
+    fn statements(&self) -> Result<Vec<Statement>> {
+        let (rows, cols) = self.config.matrix_size();
+        let matrix_size = rows as usize * cols as usize;
+        let mut statements = vec![];
+
+        let mut layers: Vec<_> = self
+            .config
+            .layers
+            .values()
+            .map(|l| (Section::Layer(l.index), l))
+            .chain(
+                self.config
+                    .composites
+                    .iter()
+                    .map(|(bits, l)| (Section::Composite(*bits), l)),
+            )
+            .collect();
+        layers.sort_by_key(|(section, _)| *section);
+
+        for (section, layer) in layers {
+            let mut codes: Vec<_> = layer.codes.iter().map(|(p, c)| (*p, *c)).collect();
+            codes.sort();
+            if codes.len() == matrix_size {
+                // dense; keep enough unassigned keys for the layer to stay dense
+                let mut kept = codes.iter().filter(|(_, c)| *c != 0).count();
+                codes.retain(|(_, c)| {
+                    if *c != 0 {
+                        true
+                    } else if kept * 3 <= matrix_size {
+                        kept += 1;
+                        true
+                    } else {
+                        false
+                    }
+                });
+            }
+            for (pos, code) in codes {
+                statements.push(Statement {
+                    section,
+                    text: format!("0x{pos:04x} = {}", self.action_text(code)?),
+                    codes: vec![code],
+                });
+            }
+        }
+
+        for combo in self.config.combos.iter() {
+            let Macro::Combo(action, keys) = combo else {
+                continue;
+            };
+            let keys: Vec<_> = keys.iter().map(|k| format!("0x{k:04x}")).collect();
+            statements.push(Statement {
+                section: Section::Combos,
+                text: format!("{} = {}", keys.join("+"), self.action_text(*action)?),
+                codes: vec![*action],
+            });
+        }
+
+        for sequence in self.config.leader_sequences.iter() {
+            let Macro::LeaderSequence(action, keys) = sequence else {
+                continue;
+            };
+            let keys = keys
+                .iter()
+                .map(|k| self.key_name(*k))
+                .collect::<Result<Vec<_>>>()?;
+            statements.push(Statement {
+                section: Section::Leader,
+                text: format!("{} = {}", keys.join(" "), self.action_text(*action)?),
+                codes: vec![*action],
+            });
+        }
+
+        if let Some((prefix, suffix)) = self.unicode {
+            for (name, code) in [("unicode_prefix", prefix), ("unicode_suffix", suffix)] {
+                if code != 0 {
+                    statements.push(Statement {
+                        section: Section::Global,
+                        text: format!("{name} = {}", self.action_text(code)?),
+                        codes: vec![code],
+                    });
+                }
+            }
+        }
+
+        Ok(statements)
+    }
+
+    /// The macros that compiling `codes` would add given the `created` macros.
+    fn creates(&self, codes: &[u16], created: &[bool]) -> Vec<usize> {
+        fn visit(d: &Decompiler, code: u16, created: &[bool], out: &mut Vec<usize>) {
+            let Some(id) = macro_id(code) else {
+                return;
+            };
+            if created.get(id) != Some(&false) || out.contains(&id) {
+                return;
+            }
+            let mac = &d.config.macros[id];
+            // unicode() uses the prefix and suffix macros made by the globals
+            if d.unicode_char(mac).is_none() {
+                for child in mac.children() {
+                    visit(d, child, created, out);
+                }
+            }
+            out.push(id);
+        }
+
+        let mut out = vec![];
+        for code in codes {
+            visit(self, *code, created, &mut out);
+        }
+        out
+    }
+
+    /// Arrange the statements into section blocks so that the macros are created in the same order
+    /// as the binary.
+    fn blocks(&self) -> Result<Vec<Block>> {
+        let mut statements: Vec<Option<Statement>> =
+            self.statements()?.into_iter().map(Some).collect();
+        let total = self.config.macros.len();
+        let mut created = vec![false; total];
+        let mut count = 0;
+
+        let mut sections: Vec<_> = (self.config.layers.values().map(|l| Section::Layer(l.index)))
+            .chain(
+                self.config
+                    .composites
+                    .keys()
+                    .map(|b| Section::Composite(*b)),
+            )
+            .collect();
+        sections.sort();
+        let mut initial: Vec<Block> = sections
+            .into_iter()
+            .map(|section| Block {
+                section,
+                created_before: 0,
+                lines: vec![],
+            })
+            .collect();
+
+        let mut ordered: Vec<Block> = vec![];
+        fn push(ordered: &mut Vec<Block>, section: Section, count: usize, text: String) {
+            match ordered.last_mut() {
+                Some(block) if block.section == section => block.lines.push(text),
+                _ => ordered.push(Block {
+                    section,
+                    created_before: count,
+                    lines: vec![text],
+                }),
+            }
+        }
+
+        // combos and leader sequences must stay in order
+        let is_queued = |s: &Statement| matches!(s.section, Section::Combos | Section::Leader);
+        let head = |statements: &[Option<Statement>], section: Section| {
+            statements
+                .iter()
+                .position(|s| s.as_ref().is_some_and(|s| s.section == section))
+        };
+
+        loop {
+            for section in [Section::Combos, Section::Leader] {
+                while let Some(i) = head(&statements, section) {
+                    let s = statements[i].as_ref().unwrap();
+                    if count < total && !self.creates(&s.codes, &created).is_empty() {
+                        break;
+                    }
+                    let s = statements[i].take().unwrap();
+                    push(&mut ordered, s.section, count, s.text);
+                }
+            }
+            if count == total {
+                break;
+            }
+
+            let current = ordered.last().map(|b| b.section);
+            let heads = [
+                head(&statements, Section::Combos),
+                head(&statements, Section::Leader),
+            ];
+            let candidate = statements
+                .iter()
+                .enumerate()
+                .filter_map(|(i, s)| Some((i, s.as_ref()?)))
+                .filter(|(i, s)| !is_queued(s) || heads.contains(&Some(*i)))
+                .filter_map(|(i, s)| {
+                    let new = self.creates(&s.codes, &created);
+                    (!new.is_empty() && new.iter().enumerate().all(|(j, id)| *id == count + j))
+                        .then_some((i, s.section, new))
+                })
+                .min_by_key(|(i, section, _)| (Some(*section) != current, *section, *i));
+
+            let (section, text, new) = match candidate {
+                Some((i, _, new)) => {
+                    let s = statements[i].take().unwrap();
+                    (s.section, s.text, new)
+                }
+                None => (
+                    Section::Global,
+                    format!("# unused macro: {}", self.macro_text(count)?),
+                    vec![count],
+                ),
+            };
+            for id in new.iter() {
+                created[*id] = true;
+            }
+            push(&mut ordered, section, count, text);
+            count += new.len();
+        }
+
+        let mut trailing: Vec<Block> = vec![];
+        for s in statements.into_iter().flatten() {
+            let last_macro = s.codes.iter().filter_map(|c| macro_id(*c)).max();
+            if s.section == Section::Global {
+                // must come before the macros which use it
+                let at = ordered
+                    .iter()
+                    .position(|b| last_macro.is_none_or(|m| b.created_before > m))
+                    .unwrap_or(ordered.len());
+                match ordered.get_mut(at) {
+                    Some(block) if block.section == Section::Global => block.lines.push(s.text),
+                    block => {
+                        let created_before = block.map_or(total, |b| b.created_before);
+                        ordered.insert(
+                            at,
+                            Block {
+                                section: Section::Global,
+                                created_before,
+                                lines: vec![s.text],
+                            },
+                        );
+                    }
+                }
+                continue;
+            }
+            let fits = |b: &&mut Block| {
+                b.section == s.section && last_macro.is_none_or(|m| b.created_before > m)
+            };
+            if let Some(block) = initial.iter_mut().chain(ordered.iter_mut()).find(fits) {
+                block.lines.push(s.text);
+            } else if let Some(block) = trailing.iter_mut().find(|b| b.section == s.section) {
+                block.lines.push(s.text);
+            } else {
+                trailing.push(Block {
+                    section: s.section,
+                    created_before: total,
+                    lines: vec![s.text],
+                });
+            }
+        }
+
+        initial.extend(ordered);
+        initial.extend(trailing);
+        Ok(initial)
+    }
+
+    fn action_text(&self, code: u16) -> Result<String> {
+        use key_range::*;
+
+        let layer_action = |name: &str, base: u16| -> Result<String> {
+            Ok(format!("{name}({})", self.layer_name(code - base)?))
+        };
+
+        match code {
+            MACROS_MIN..=MACROS_MAX => self.macro_text((code - MACROS_MIN) as usize),
+            LAYER_MIN..=LAYER_MAX => layer_action("layer", LAYER_MIN),
+            TOGGLE_MIN..=TOGGLE_MAX => layer_action("toggle", TOGGLE_MIN),
+            SET_LAYOUT_MIN..=SET_LAYOUT_MAX => layer_action("setlayout", SET_LAYOUT_MIN),
+            ONESHOT_MIN..=ONESHOT_MAX => layer_action("oneshot", ONESHOT_MIN),
+            _ => self.key_name(code).map(str::to_string),
+        }
+    }
+
+    fn macro_text(&self, id: usize) -> Result<String> {
+        let seq = |seq: &[u16]| -> Result<String> {
+            Ok(seq
+                .iter()
+                .map(|c| self.action_text(*c))
+                .collect::<Result<Vec<_>>>()?
+                .join(" "))
+        };
+
+        let mac = self
+            .config
+            .macros
+            .get(id)
+            .ok_or_else(|| ConfigError::from("Invalid macro"))?;
+
+        if let Some(c) = self.unicode_char(mac) {
+            return Ok(format!("unicode({:x})", c as u32));
+        }
+
+        Ok(match mac {
+            Macro::Modifier { keycode, modifiers } if *modifiers != 0 => format!(
+                "{}-{}",
+                keycodes::modifiers_to_string(*modifiers),
+                self.key_name(*keycode)?
+            ),
+            Macro::Tap(s) => format!("macro({})", seq(s)?),
+            Macro::Hold(s) => format!("hold({})", seq(s)?),
+            Macro::Release(s) => format!("release({})", seq(s)?),
+            Macro::HoldRelease { hold, release } => format!("macro({})", seq(&[*hold, *release])?),
+            Macro::DualAction(tap, hold) => self.dual_action_text(*tap, *hold, &[])?,
+            Macro::TimedDualAction(tap, hold, t1, t2) => {
+                self.dual_action_text(*tap, *hold, &[t1.to_string(), t2.to_string()])?
+            }
+            Macro::ModalDualAction(tap, hold, t1, t2, mode, prior_idle) => {
+                let mut args = vec![t1.to_string(), t2.to_string()];
+                if *mode != u16::MAX {
+                    args.push(
+                        dual_action_modes::NAMES
+                            .get(*mode as usize)
+                            .ok_or_else(|| ConfigError::from("Invalid dual action mode"))?
+                            .to_string(),
+                    );
+                }
+                if *prior_idle != u16::MAX {
+                    args.push(format!("require_prior_idle={prior_idle}"));
+                }
+                self.dual_action_text(*tap, *hold, &args)?
+            }
+            Macro::TapDance(timeout, actions) => {
+                let actions = actions
+                    .iter()
+                    .map(|c| self.action_text(*c))
+                    .collect::<Result<Vec<_>>>()?
+                    .join(", ");
+                if *timeout == u16::MAX {
+                    format!("tapdance({actions})")
+                } else {
+                    format!("tapdancet({timeout}, {actions})")
+                }
+            }
+            Macro::IfLed(led, on, off) => format!(
+                "ifled({}, {}, {})",
+                self.led_name(*led)?,
+                self.action_text(*on)?,
+                self.action_text(*off)?
+            ),
+            Macro::Delay(n) => format!("delay({n})"),
+            Macro::Leader => "leader()".to_string(),
+            _ => return Err(ConfigError::from("Unexpected macro")),
+        })
+    }
+
+    fn dual_action_text(&self, tap: u16, hold: u16, args: &[String]) -> Result<String> {
+        let max = u16::MAX.to_string();
+        let mut text = if let key_range::LAYER_MIN..=key_range::LAYER_MAX = hold {
+            format!(
+                "overload({}, {}",
+                self.layer_name(hold - key_range::LAYER_MIN)?,
+                self.action_text(tap)?
+            )
+        } else {
+            format!(
+                "dualaction({}, {}",
+                self.action_text(hold)?,
+                self.action_text(tap)?
+            )
+        };
+        for arg in args.iter().filter(|a| **a != max) {
+            text += ", ";
+            text += arg;
+        }
+        text += ")";
+        Ok(text)
+    }
+
+    fn key_name(&self, code: u16) -> Result<&'static str> {
+        keycodes::key_name(code)
+            .ok_or_else(|| ConfigError::from(format!("Unknown keycode 0x{code:04x}").as_str()))
+    }
+
+    fn led_name(&self, led: u8) -> Result<&'static str> {
+        leds::NAMES
+            .get(led.trailing_zeros() as usize)
+            .copied()
+            .ok_or_else(|| ConfigError::from("Invalid led"))
+    }
+
+    fn layer_name(&self, index: u16) -> Result<&'a str> {
+        self.config
+            .layer_name(index)
+            .ok_or_else(|| ConfigError::from(format!("Unknown layer {index}").as_str()))
+    }
+
+    fn composite_name(&self, bits: u32) -> Result<String> {
+        let names = (0..32)
+            .filter(|i| bits & (1 << i) != 0)
+            .map(|i| self.layer_name(i))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(names.join("+"))
+    }
+}
+
+fn write_mouse_setting(out: &mut String, name: &str, field: &str, setting: MouseAnalogSetting) {
+    let _ = write!(
+        out,
+        "\n[global.{name}.{field}]\ncurve = [{}, {}]\nmax_time = {}\n\
+         min_ticks_per_ms = {}\nmax_ticks_per_ms = {}\n",
+        setting.curve.0,
+        setting.curve.1,
+        setting.max_time,
+        setting.min_ticks_per_ms,
+        setting.max_ticks_per_ms
+    );
+}
+
+#[cfg(test)]
+#[path = "decompiler_test.rs"]
+mod test;
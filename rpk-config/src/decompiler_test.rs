@@ -0,0 +1,241 @@
+use std::path::PathBuf;
+
+use crate::compiler::compile;
+
+use super::*;
+
+fn binary(source: &str) -> Vec<u16> {
+    compile(PathBuf::from("test.rpk.conf"), source)
+        .unwrap()
+        .serialize()
+}
+
+fn assert_round_trip(source: &str) -> String {
+    let bin = binary(source);
+    let text = decompile(&bin).unwrap();
+    let config = compile(PathBuf::from("decompiled.rpk.conf"), &text)
+        .unwrap_or_else(|e| panic!("{}\n{text}", e.long_format(&PathBuf::from("d"), &text)));
+    assert_eq!(config.serialize(), bin, "\n{text}");
+    text
+}
+
+#[test]
+fn default_layout() {
+    assert_round_trip(include_str!(
+        "../../rpk-macros/test/default-layout.rpk.conf"
+    ));
+    assert_round_trip(include_str!(
+        "../../rpk-macros/test/direct-pins-layout.rpk.conf"
+    ));
+    assert_round_trip(include_str!("../tests/default.conf"));
+}
+
+#[test]
+fn globals() {
+    let text = assert_round_trip(
+        r#"
+[global]
+dual_action_timeout = 300
+debounce_settle_time = 2.5
+dual_action_mode = balanced
+debounce_mode = defer
+caps_word_keys = minus backspace
+
+[global.mouse_profile2.movement]
+curve = [0.1, 0.75]
+max_time = 1.5
+min_ticks_per_ms = 0.25
+max_ticks_per_ms = 4
+
+[matrix:1x2]
+0x00 = a b
+"#,
+    );
+
+    assert!(text.contains("[global]\ndual_action_timeout = 300\ndebounce_settle_time = 2.5\n"));
+    assert!(text.contains("dual_action_mode = balanced\ndebounce_mode = defer\n"));
+    assert!(text.contains("caps_word_keys = minus backspace\n"));
+    assert!(text.contains("[global.mouse_profile2.movement]\ncurve = [0.1, 0.75]\n"));
+}
+
+#[test]
+fn macros() {
+    let text = assert_round_trip(
+        r#"
+[matrix:3x4]
+0x00 = a b c d
+0x10 = e f g h
+0x20 = i j k l
+
+[main]
+a = macro(x C-y delay(20) z)
+b = dualaction(leftshift, a)
+c = overload(nav, tab, 250, 100, permissive_hold, require_prior_idle=120)
+d = tapdance(a, b, c, d)
+e = tapdancet(150, escape, layer(nav))
+f = ifled(capslock, C-c, macro(hold(leftshift) release(leftshift)))
+g = macro(hold(leftcontrol) release(leftcontrol))
+h = unicode(20ac)
+i = leader()
+j = dualaction(leftcontrol, j, 200)
+k = dualaction(leftalt, k, bilateral)
+
+[nav:C-S]
+a = toggle(sym)
+b = setlayout(main)
+c = oneshot(sym)
+0x0203 = macro(x y)
+
+[sym]
+l = C-y
+
+[nav+sym]
+a = audio_vol_up
+b = macro(x y)
+"#,
+    );
+
+    assert!(text.contains("[layer6:C-S]"));
+    assert!(text.contains("= macro(x C-y delay(20) z)"));
+    assert!(
+        text.contains("= overload(layer6, tab, 250, 100, permissive_hold, require_prior_idle=120)")
+    );
+    assert!(text.contains("[layer6+layer7]"));
+}
+
+#[test]
+fn combos_leader_hands_and_leds() {
+    assert_round_trip(
+        r#"
+[matrix:2x3]
+0x00 = a b c
+0x10 = d e f
+
+[main]
+a = macro(y z)
+f = leader()
+
+[combos]
+a+b = C-x
+b+c = escape
+d+e = macro(y z)
+
+[leader]
+a b = C-x
+c = macro(q r)
+
+[hands]
+left = a b d
+right = c e f
+
+[leds]
+capslock = nav
+numlock = main
+
+[nav]
+a = left
+"#,
+    );
+}
+
+#[test]
+fn unicode() {
+    let text = assert_round_trip(
+        r#"
+[global]
+unicode_prefix = C-S-u
+unicode_suffix = macro(space)
+
+[matrix:1x3]
+0x00 = a b c
+
+[main]
+a = C-a
+b = unicode(20ac)
+c = macro(x unicode(e9))
+"#,
+    );
+
+    assert!(text.contains("[global]\nunicode_prefix = C-S-u\nunicode_suffix = macro(spacebar)\n"));
+    assert!(text.contains("0x0001 = unicode(20ac)\n"), "{text}");
+    assert!(text.contains("0x0002 = macro(x unicode(e9))\n"), "{text}");
+
+    let text = assert_round_trip(
+        r#"
+[global]
+unicode_suffix = enter
+
+[matrix:1x2]
+0x00 = a b
+
+[main]
+a = macro(C-a)
+b = unicode(1F600)
+"#,
+    );
+
+    assert!(
+        text.contains("[global]\nunicode_suffix = return\n"),
+        "{text}"
+    );
+    assert!(text.contains("0x0001 = unicode(1f600)\n"), "{text}");
+}
+
+#[test]
+fn unused_macros() {
+    let bin = binary(
+        r#"
+[global]
+unicode_prefix = C-S-u
+
+[matrix:1x2]
+0x00 = a b
+
+[main]
+a = C-a
+"#,
+    );
+    let text = decompile(&bin).unwrap();
+
+    assert!(text.contains("[global]\n# unused macro: C-S-u\n"), "{text}");
+    assert!(text.contains("0x0000 = C-a\n"), "{text}");
+}
+
+#[test]
+fn dense_layer() {
+    let text = assert_round_trip(
+        r#"
+[matrix:2x3]
+0x00 = a b c
+0x10 = d e f
+
+[nav]
+a = q
+b = transparent
+c = w
+
+[sym]
+a = q
+b = transparent
+"#,
+    );
+    assert!(text.contains("[layer6]\n0x0000 = q\n0x0001 = transparent\n0x0002 = w\n"));
+    assert!(text.ends_with("[layer7]\n0x0000 = q\n0x0001 = transparent\n"));
+}
+
+#[test]
+fn invalid_binary() {
+    assert_eq!(decompile(&[]).unwrap_err().message, "Invalid layout binary");
+    let mut bin = binary("[matrix:1x1]\n0x00 = a\n");
+    bin[0] += 1;
+    assert_eq!(
+        decompile(&bin).unwrap_err().message,
+        "Unsupported layout binary version"
+    );
+    bin[0] -= 1;
+    bin.truncate(bin.len() - 2);
+    assert_eq!(
+        decompile(&bin).unwrap_err().message,
+        "Invalid layout binary"
+    );
+}
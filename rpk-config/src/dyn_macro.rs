@@ -0,0 +1,67 @@
+use rpk_common::keycodes::dyn_macro;
+
+use crate::keycodes;
+
+/// A dynamic macro recorded on the keyboard.
+#[derive(Debug, PartialEq)]
+pub struct DynMacro {
+    pub slot: u16,
+    pub items: Vec<u16>,
+}
+
+impl DynMacro {
+    /// Decode the contents of a dynamic macro file; including its header.
+    pub fn from_file(data: &[u8]) -> Option<Self> {
+        let start = 14 + *data.get(13)? as usize;
+        let mut words = data
+            .get(start..)?
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]));
+        Some(Self {
+            slot: words.next()?,
+            items: words.collect(),
+        })
+    }
+
+    pub fn name(&self) -> String {
+        format!("dyn_macro_{}", self.slot + 1)
+    }
+
+    /// Convert to a macro expression which can be used in a config file.
+    pub fn to_config_text(&self) -> String {
+        let mut groups: Vec<(&str, Vec<String>)> = vec![];
+
+        for item in self.items.iter().copied() {
+            let (kind, text) = if item & dyn_macro::DELAY != 0 {
+                groups.push(("delay", vec![(item & dyn_macro::MAX_DELAY).to_string()]));
+                continue;
+            } else if item & dyn_macro::RELEASE != 0 {
+                ("release", code_name(item & !dyn_macro::RELEASE))
+            } else {
+                ("hold", code_name(item))
+            };
+            match groups.last_mut() {
+                Some((k, codes)) if *k == kind => codes.push(text),
+                _ => groups.push((kind, vec![text])),
+            }
+        }
+
+        let groups: Vec<String> = groups
+            .into_iter()
+            .map(|(kind, codes)| format!("{}({})", kind, codes.join(" ")))
+            .collect();
+
+        format!("macro({})", groups.join(" "))
+    }
+}
+
+fn code_name(code: u16) -> String {
+    match keycodes::key_name(code) {
+        Some(name) => name.to_string(),
+        None => format!("0x{code:x}"),
+    }
+}
+
+#[cfg(test)]
+#[path = "dyn_macro_test.rs"]
+mod test;
@@ -0,0 +1,53 @@
+use rpk_common::keycodes::key_range;
+
+use crate::text_to_binary;
+
+use super::*;
+
+#[test]
+fn from_file() {
+    let mut data = vec![0; 12];
+    data.extend_from_slice(&[1, 4, 0, b'a', b'b', b'c', 2, 0, 4, 0, 5]);
+
+    let ans = DynMacro::from_file(&data).unwrap();
+    assert_eq!(ans.slot, 2);
+    assert_eq!(ans.items, vec![4]);
+    assert_eq!(ans.name(), "dyn_macro_3");
+
+    assert_eq!(DynMacro::from_file(&data[..12]), None);
+    assert_eq!(DynMacro::from_file(&data[..19]), None);
+}
+
+#[test]
+fn to_config_text() {
+    let m = DynMacro {
+        slot: 0,
+        items: vec![
+            key_range::MODIFIER_MIN + 1,
+            4,
+            dyn_macro::DELAY | 120,
+            dyn_macro::RELEASE | 4,
+            dyn_macro::RELEASE | (key_range::MODIFIER_MIN + 1),
+            key_range::CONSUMER_MIN + 0x6f,
+            dyn_macro::RELEASE | (key_range::CONSUMER_MIN + 0x6f),
+        ],
+    };
+
+    let text = m.to_config_text();
+    assert_eq!(
+        text,
+        "macro(hold(leftshift a) delay(120) release(a leftshift) hold(brightness_up) \
+         release(brightness_up))"
+    );
+
+    let config = format!(
+        r#"
+[matrix:1x1]
+0x00 = a
+
+[main]
+a = {text}
+"#
+    );
+    assert!(text_to_binary(&config).is_ok());
+}
@@ -4,7 +4,7 @@ use lazy_static::lazy_static;
 
 pub(crate) mod spec {
     use rpk_common::{
-        globals,
+        globals::{self, debounce_modes, dual_action_modes},
         mouse::{MouseAnalogSetting, MouseConfig},
     };
 
@@ -12,6 +12,8 @@ pub(crate) mod spec {
     pub(crate) enum GlobalType {
         Timeout { value: u16, max: u16, min: u16 },
         DebounceTimeout(u16),
+        DualActionMode(u16),
+        DebounceMode(u16),
         MouseProfile(MouseConfig),
     }
     impl GlobalType {
@@ -23,6 +25,8 @@ pub(crate) mod spec {
                     min: *min,
                 }),
                 DebounceTimeout(..) => Ok(DebounceTimeout(parse_key_settle_time(text)?)),
+                DualActionMode(..) => Ok(DualActionMode(parse_dual_action_mode(text)?)),
+                DebounceMode(..) => Ok(DebounceMode(parse_debounce_mode(text)?)),
                 _ => panic!("Unsupported"),
             }
         }
@@ -49,6 +53,32 @@ pub(crate) mod spec {
         }
     }
 
+    pub fn parse_dual_action_mode(text: &str) -> Result<u16, String> {
+        dual_action_modes::NAMES
+            .iter()
+            .position(|n| *n == text)
+            .map(|i| i as u16)
+            .ok_or_else(|| {
+                format!(
+                    "Invalid dual action mode; only {} are valid",
+                    dual_action_modes::NAMES.join(", ")
+                )
+            })
+    }
+
+    pub fn parse_debounce_mode(text: &str) -> Result<u16, String> {
+        debounce_modes::NAMES
+            .iter()
+            .position(|n| *n == text)
+            .map(|i| i as u16)
+            .ok_or_else(|| {
+                format!(
+                    "Invalid debounce mode; only {} are valid",
+                    debounce_modes::NAMES.join(", ")
+                )
+            })
+    }
+
     use GlobalType::*;
 
     use crate::f32_to_u16;
@@ -67,20 +97,19 @@ pub(crate) mod spec {
                 .copied()
         }
 
-        #[cfg(test)]
         pub(crate) fn default_name(&self) -> Option<&'static str> {
             super::INDEX_TO_NAME.get(self.index as usize).copied()
         }
 
-        #[cfg(test)]
         pub(crate) fn deserialize(data: &mut impl Iterator<Item = u16>) -> Option<Self> {
             let index = data.next()?;
             let name = super::INDEX_TO_NAME.get(index as usize).copied()?;
             let mut gp = GlobalProp::new_default(name).ok()?;
             match gp.spec {
-                Timeout { ref mut value, .. } | DebounceTimeout(ref mut value) => {
-                    *value = data.next()?
-                }
+                Timeout { ref mut value, .. }
+                | DebounceTimeout(ref mut value)
+                | DualActionMode(ref mut value)
+                | DebounceMode(ref mut value) => *value = data.next()?,
                 MouseProfile(ref mut config) => {
                     config.movement = MouseAnalogSetting::deserialize(data)?;
                     config.scroll = MouseAnalogSetting::deserialize(data)?;
@@ -92,9 +121,10 @@ pub(crate) mod spec {
 
         pub(crate) fn serialize(self) -> Box<dyn Iterator<Item = u16>> {
             match self.spec {
-                Timeout { value, .. } | DebounceTimeout(value) => {
-                    Box::new([self.index, value].into_iter())
-                }
+                Timeout { value, .. }
+                | DebounceTimeout(value)
+                | DualActionMode(value)
+                | DebounceMode(value) => Box::new([self.index, value].into_iter()),
                 MouseProfile(MouseConfig { movement, scroll }) => Box::new(
                     [self.index]
                         .into_iter()
@@ -153,7 +183,7 @@ pub(crate) mod spec {
         a.trim().parse::<f32>().map_err(|e| format!("{e} {a}"))
     }
 
-    pub(super) const GLOBALS: [GlobalProp; 7] = [
+    pub(super) const GLOBALS: [GlobalProp; 13] = [
         GlobalProp {
             index: globals::MOUSE_PROFILE1,
             spec: GlobalType::MouseProfile(MouseConfig::slow()),
@@ -194,6 +224,46 @@ pub(crate) mod spec {
                 max: 5000,
             },
         },
+        GlobalProp {
+            index: globals::COMBO_TIMEOUT,
+            spec: GlobalType::Timeout {
+                value: globals::COMBO_TIMEOUT_DEFAULT,
+                min: 0,
+                max: 5000,
+            },
+        },
+        GlobalProp {
+            index: globals::LEADER_TIMEOUT,
+            spec: GlobalType::Timeout {
+                value: globals::LEADER_TIMEOUT_DEFAULT,
+                min: 0,
+                max: 10000,
+            },
+        },
+        GlobalProp {
+            index: globals::CAPS_WORD_TIMEOUT,
+            spec: GlobalType::Timeout {
+                value: globals::CAPS_WORD_TIMEOUT_DEFAULT,
+                min: 0,
+                max: 60000,
+            },
+        },
+        GlobalProp {
+            index: globals::DUAL_ACTION_MODE,
+            spec: GlobalType::DualActionMode(globals::DUAL_ACTION_MODE_DEFAULT),
+        },
+        GlobalProp {
+            index: globals::DUAL_ACTION_REQUIRE_PRIOR_IDLE,
+            spec: GlobalType::Timeout {
+                value: globals::DUAL_ACTION_REQUIRE_PRIOR_IDLE_DEFAULT,
+                min: 0,
+                max: 5000,
+            },
+        },
+        GlobalProp {
+            index: globals::DEBOUNCE_MODE,
+            spec: GlobalType::DebounceMode(globals::DEBOUNCE_MODE_DEFAULT),
+        },
     ];
 }
 
@@ -206,6 +276,12 @@ lazy_static! {
         "dual_action_timeout2",
         "debounce_settle_time",
         "tapdance_tap_timeout",
+        "combo_timeout",
+        "leader_timeout",
+        "caps_word_timeout",
+        "dual_action_mode",
+        "dual_action_require_prior_idle",
+        "debounce_mode",
     ];
     pub(crate) static ref DEFAULTS: HashMap<&'static str, spec::GlobalProp> = {
         let mut m = HashMap::new();
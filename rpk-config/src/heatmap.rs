@@ -0,0 +1,150 @@
+use std::fmt::Write;
+
+use crate::{compiler::KeyboardConfig, vendor_coms::KeyboardStats};
+
+const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+
+const CELL_WIDTH: usize = 8;
+
+#[derive(Debug, PartialEq)]
+pub struct HeatmapKey {
+    pub row: u8,
+    pub col: u8,
+    /// The names given to the key in the config's `matrix` and `aliases` sections.
+    pub names: Vec<String>,
+    pub presses: u32,
+}
+
+/// Key press counts laid out by matrix position.
+#[derive(Debug, PartialEq)]
+pub struct Heatmap {
+    pub rows: u8,
+    pub cols: u8,
+    pub keys: Vec<HeatmapKey>,
+}
+impl Heatmap {
+    pub fn new(stats: &KeyboardStats, config: Option<&KeyboardConfig>) -> Self {
+        let cols = stats.cols.max(1) as usize;
+        let keys = stats
+            .key_presses
+            .iter()
+            .enumerate()
+            .map(|(i, presses)| {
+                let (row, col) = ((i / cols) as u8, (i % cols) as u8);
+                HeatmapKey {
+                    row,
+                    col,
+                    names: config.map_or_else(Vec::new, |c| {
+                        c.position_names((row as u16) << 8 | col as u16)
+                    }),
+                    presses: *presses,
+                }
+            })
+            .collect();
+        Self {
+            rows: stats.rows,
+            cols: stats.cols,
+            keys,
+        }
+    }
+
+    fn max_presses(&self) -> u32 {
+        self.keys.iter().map(|k| k.presses).max().unwrap_or(0)
+    }
+
+    /// Render the matrix with each key's name above its count. The count is prefixed with a shade
+    /// showing how often the key is pressed compared to the most pressed key.
+    pub fn to_text(&self) -> String {
+        let max = self.max_presses();
+        let mut out = String::from("   ");
+        for col in 0..self.cols {
+            let _ = write!(out, " {col:<w$}", w = CELL_WIDTH - 1);
+        }
+        for keys in self.keys.chunks(self.cols.max(1) as usize) {
+            let _ = write!(out, "\n{:>3}", keys[0].row);
+            for key in keys {
+                let name: String = key.names.join("/").chars().take(CELL_WIDTH - 2).collect();
+                let _ = write!(out, " {name:<w$}", w = CELL_WIDTH - 1);
+            }
+            out.push_str("\n   ");
+            for key in keys {
+                let shade = if max == 0 {
+                    SHADES[0]
+                } else {
+                    SHADES[(key.presses as u64 * 4).div_ceil(max as u64) as usize]
+                };
+                let _ = write!(out, " {shade}{:>w$}", key.presses, w = CELL_WIDTH - 2);
+            }
+        }
+        out.lines()
+            .map(|l| l.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("row,col,name,presses\n");
+        for key in &self.keys {
+            let _ = writeln!(
+                out,
+                "{},{},{},{}",
+                key.row,
+                key.col,
+                csv_field(&key.names.join(" ")),
+                key.presses
+            );
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let keys: Vec<String> = self
+            .keys
+            .iter()
+            .map(|k| {
+                let names: Vec<String> = k.names.iter().map(|n| json_string(n)).collect();
+                format!(
+                    "    {{\"row\": {}, \"col\": {}, \"names\": [{}], \"presses\": {}}}",
+                    k.row,
+                    k.col,
+                    names.join(", "),
+                    k.presses
+                )
+            })
+            .collect();
+        format!(
+            "{{\n  \"rows\": {},\n  \"cols\": {},\n  \"keys\": [\n{}\n  ]\n}}\n",
+            self.rows,
+            self.cols,
+            keys.join(",\n")
+        )
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+#[path = "heatmap_test.rs"]
+mod test;
@@ -0,0 +1,83 @@
+use std::path::Path;
+
+use super::*;
+
+fn stats() -> KeyboardStats {
+    KeyboardStats {
+        rows: 2,
+        cols: 3,
+        key_presses: vec![0, 4, 1, 8, 0, 2],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn new() {
+    let src = r#"
+[matrix:2x3]
+0x00 = a b c
+0x10 = d e f
+
+[aliases]
+0x01 = thumb
+"#;
+    let config = crate::pretty_compile(Path::new("test"), src).unwrap();
+
+    let mut heatmap = Heatmap::new(&stats(), Some(&config));
+
+    assert_eq!((heatmap.rows, heatmap.cols), (2, 3));
+    assert_eq!(
+        heatmap.keys[1],
+        HeatmapKey {
+            row: 0,
+            col: 1,
+            names: vec!["b".to_string(), "thumb".to_string()],
+            presses: 4,
+        }
+    );
+    assert_eq!(heatmap.keys[3].names, vec!["d"]);
+    assert_eq!((heatmap.keys[5].row, heatmap.keys[5].col), (1, 2));
+
+    heatmap.keys[2].names = vec!["x,\"y\"".to_string()];
+
+    assert_eq!(
+        heatmap.to_csv(),
+        "row,col,name,presses\n\
+         0,0,a,0\n\
+         0,1,b thumb,4\n\
+         0,2,\"x,\"\"y\"\"\",1\n\
+         1,0,d,8\n\
+         1,1,e,0\n\
+         1,2,f,2\n"
+    );
+}
+
+#[test]
+fn to_text() {
+    let heatmap = Heatmap::new(&stats(), None);
+
+    assert_eq!(
+        heatmap.to_text(),
+        "    0       1       2\n  0\n          0 ▒     4 ░     1\n  1\n    █     8       0 ░     2"
+    );
+}
+
+#[test]
+fn to_json() {
+    let mut heatmap = Heatmap::new(&stats(), None);
+    heatmap.keys.truncate(2);
+    heatmap.keys[1].names = vec!["a\"\\".to_string(), "b".to_string()];
+
+    assert_eq!(
+        heatmap.to_json(),
+        r#"{
+  "rows": 2,
+  "cols": 3,
+  "keys": [
+    {"row": 0, "col": 0, "names": [], "presses": 0},
+    {"row": 0, "col": 1, "names": ["a\"\\", "b"], "presses": 4}
+  ]
+}
+"#
+    );
+}
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use lazy_static::lazy_static;
-use rpk_common::keycodes::key_range;
+use rpk_common::keycodes::{key_range, leds};
 
 const DASH_USCORE: [char; 2] = ['_', '-'];
 
@@ -29,6 +29,8 @@ lazy_static! {
         m.insert("release", key_range::MACROS_MIN);
         m.insert("unicode", key_range::MACROS_MIN);
         m.insert("delay", key_range::MACROS_MIN);
+        m.insert("leader", key_range::MACROS_MIN);
+        m.insert("ifled", key_range::MACROS_MIN);
         m
     };
     static ref SHIFT_KEY_NAMES : HashMap<char, char> = {
@@ -270,10 +272,32 @@ MouseAccel1 MouseAccel2 MouseAccel3
         ins("Clear_Layers", key_range::FW_CLEAR_LAYERS);
         ins("Stop_Active", key_range::FW_STOP_ACTIVE);
         ins("Reset_To_Usb_Boot", key_range::FW_RESET_TO_USB_BOOT);
+        ins("Caps_Word", key_range::FW_CAPS_WORD);
+        ins("Dyn_Macro_Record_1", key_range::FW_DYN_MACRO_RECORD);
+        ins("Dyn_Macro_Record_2", key_range::FW_DYN_MACRO_RECORD + 1);
+        ins("Dyn_Macro_Record_3", key_range::FW_DYN_MACRO_RECORD + 2);
+        ins("Dyn_Macro_Record_4", key_range::FW_DYN_MACRO_RECORD + 3);
+        ins("Dyn_Macro_Play_1", key_range::FW_DYN_MACRO_PLAY);
+        ins("Dyn_Macro_Play_2", key_range::FW_DYN_MACRO_PLAY + 1);
+        ins("Dyn_Macro_Play_3", key_range::FW_DYN_MACRO_PLAY + 2);
+        ins("Dyn_Macro_Play_4", key_range::FW_DYN_MACRO_PLAY + 3);
 // clear_layers clear_input clear_all
 
         m
     };
+        static ref KEY_CODE_NAMES: HashMap<u16, String> = {
+            let mut m: HashMap<u16, &str> = HashMap::new();
+            for (name, code) in FULL_KEY_NAMES.iter() {
+                if !name.is_ascii() {
+                    continue;
+                }
+                match m.get(code) {
+                    Some(n) if (name.len(), *n) < (n.len(), *name) => {}
+                    _ => { m.insert(*code, name); }
+                }
+            }
+            m.into_iter().map(|(c, n)| (c, n.to_lowercase())).collect()
+        };
         static ref KEY_NAMES: HashMap<String, u16> = {
             let mut m = HashMap::new();
             for (r, v) in FULL_KEY_NAMES.iter() {
@@ -326,6 +350,16 @@ pub fn key_code(name: &str) -> Option<u16> {
     }
 }
 
+/// The preferred name of a keycode for writing config text.
+pub fn key_name(code: u16) -> Option<&'static str> {
+    KEY_CODE_NAMES.get(&code).map(|n| n.as_str())
+}
+
+/// The bit of the host LED named `name`; see [`leds::NAMES`].
+pub fn led_bit(name: &str) -> Option<u8> {
+    leds::NAMES.iter().position(|n| *n == name).map(|i| 1 << i)
+}
+
 pub(crate) fn action_code(name: &str) -> Option<u16> {
     ACTION_NAMES.get(name).copied()
 }
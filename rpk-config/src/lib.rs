@@ -3,7 +3,10 @@ use std::{ops::Range, path::Path};
 
 pub mod builder;
 pub mod compiler;
+pub mod decompiler;
+pub mod dyn_macro;
 pub mod globals;
+pub mod heatmap;
 pub mod keycodes;
 pub mod vendor_coms;
 
@@ -115,31 +118,67 @@ impl ConfigError {
     }
 }
 
+/// Compile `src` printing every error found to stderr. The first error is returned.
 pub fn pretty_compile<'s>(
     file: &Path,
     src: &'s str,
 ) -> Result<compiler::KeyboardConfig<'s>, ConfigError> {
-    match compiler::compile(PathBuf::from(file), src) {
+    match compiler::compile_all(PathBuf::from(file), src) {
         Ok(config) => Ok(config),
-        Err(err) => {
-            use ariadne::{ColorGenerator, Label, Report, ReportKind, Source};
-            let filename = file.to_str().unwrap_or("<unknown>");
-            let mut colors = ColorGenerator::new();
-
-            let a = colors.next();
-            if let Some(span) = err.char_span(src) {
-                Report::build(ReportKind::Error, (filename, span.start..span.end))
-                    .with_message("Invalid config".to_string())
-                    .with_label(
-                        Label::new((filename, span))
-                            .with_message(&err.message)
-                            .with_color(a),
-                    )
-                    .finish()
-                    .eprint((filename, Source::from(src)))
-                    .unwrap();
+        Err(mut errors) => {
+            print_reports(
+                file,
+                src,
+                ariadne::ReportKind::Error,
+                "Invalid config",
+                &errors,
+            );
+            if errors.len() > 1 {
+                eprintln!("{} errors found", errors.len());
             }
-            Err(err)
+            Err(errors.swap_remove(0))
+        }
+    }
+}
+
+/// Print the warnings found while compiling `src` to stderr.
+pub fn pretty_warnings(file: &Path, src: &str, warnings: &[ConfigError]) {
+    print_reports(
+        file,
+        src,
+        ariadne::ReportKind::Warning,
+        "Suspicious config",
+        warnings,
+    );
+    if warnings.len() > 1 {
+        eprintln!("{} warnings found", warnings.len());
+    }
+}
+
+fn print_reports(
+    file: &Path,
+    src: &str,
+    kind: ariadne::ReportKind,
+    title: &str,
+    errors: &[ConfigError],
+) {
+    use ariadne::{ColorGenerator, Label, Report, Source};
+    let filename = file.to_str().unwrap_or("<unknown>");
+    let mut colors = ColorGenerator::new();
+
+    for err in errors.iter() {
+        let a = colors.next();
+        if let Some(span) = err.char_span(src) {
+            Report::build(kind, (filename, span.start..span.end))
+                .with_message(title)
+                .with_label(
+                    Label::new((filename, span))
+                        .with_message(&err.message)
+                        .with_color(a),
+                )
+                .finish()
+                .eprint((filename, Source::from(src)))
+                .unwrap();
         }
     }
 }
@@ -13,34 +13,63 @@ use std::{
 use anyhow::{Result, anyhow};
 use chrono::{DateTime, Local, Utc};
 use futures_lite::future::block_on;
+use miniz_oxide::{deflate::compress_to_vec_zlib, inflate::decompress_to_vec_zlib};
 use nusb::transfer::{Direction, RequestBuffer};
-use rpk_common::usb_vendor_message::{self as msg, MAX_BULK_LEN, READ_FILE_BY_INDEX, host_recv};
+use rpk_common::usb_vendor_message::{
+    self as msg, MAX_BULK_LEN, READ_FILE_BY_INDEX, file_type, host_recv,
+};
 
 fn u16tou8(words: &[u16]) -> impl Iterator<Item = u8> + use<'_> {
     words.iter().flat_map(|a| a.to_le_bytes())
 }
 
+/// Take the next `N` bytes from `iter`; padding with zeros if `iter` runs out.
+fn le_bytes<const N: usize>(iter: &mut impl Iterator<Item = u8>) -> [u8; N] {
+    core::array::from_fn(|_| iter.next().unwrap_or(0))
+}
+
 pub trait KeyboardInterface {
     fn bulk_out(&self, endpoint: u8, buf: Vec<u8>) -> Result<()>;
     fn bulk_in(&self, endpoint: u8, max_len: u16) -> Result<Vec<u8>>;
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum FileType {
     #[default]
     Config,
+    DynMacro,
+    ConfigSource,
+    Active,
 }
 impl FileType {
     pub fn as_u8(&self) -> u8 {
         use FileType::*;
         match self {
-            Config => 0,
+            Config => file_type::CONFIG,
+            DynMacro => file_type::DYN_MACRO,
+            ConfigSource => file_type::CONFIG_SOURCE,
+            Active => file_type::ACTIVE,
         }
     }
 }
 impl From<u8> for FileType {
-    fn from(_value: u8) -> Self {
-        Self::Config
+    fn from(value: u8) -> Self {
+        match value {
+            file_type::DYN_MACRO => Self::DynMacro,
+            file_type::CONFIG_SOURCE => Self::ConfigSource,
+            file_type::ACTIVE => Self::Active,
+            _ => Self::Config,
+        }
+    }
+}
+impl Display for FileType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FileType::Config => "config",
+            FileType::DynMacro => "dyn_macro",
+            FileType::ConfigSource => "source",
+            FileType::Active => "active",
+        })
     }
 }
 
@@ -88,8 +117,8 @@ impl Display for FileInfo {
         let dt = DateTime::<Local>::from(self.timestamp);
 
         f.write_fmt(format_args!(
-            "{} {:5} {} {}",
-            dt, self.length, self.filename, self.index
+            "{} {:5} {:9} {} {}",
+            dt, self.length, self.file_type, self.filename, self.index
         ))
     }
 }
@@ -150,13 +179,92 @@ impl HostRecvReceiver {
     }
 }
 
+#[derive(Debug, Default, PartialEq)]
 pub struct KeyboardStats {
     pub uptime: Duration,
+    pub rows: u8,
+    pub cols: u8,
+    pub dual_action_taps: u32,
+    pub dual_action_holds: u32,
+    /// The most words used by the memo stack.
+    pub memo_high_water: u16,
+    /// The most words used by the macro stack.
+    pub macro_stack_high_water: u16,
+    /// The number of times the key report queue overflowed and was cleared.
+    pub report_overflows: u32,
+    /// How long each layer has been active; indexed by layer number.
+    pub layer_active: Vec<Duration>,
+    /// The number of presses of each key; indexed by `row * cols + col`.
+    pub key_presses: Vec<u32>,
 }
 impl From<&[u8]> for KeyboardStats {
+    /// Decode the stats sent by the keyboard. Older firmware only sends the uptime so missing
+    /// values are left as their default.
     fn from(value: &[u8]) -> Self {
+        let mut iter = value.iter().copied();
+        let iter = &mut iter;
+
+        let uptime = Duration::from_millis(u32::from_le_bytes(le_bytes(iter)) as u64);
+        let [rows, cols] = le_bytes(iter);
+        let dual_action_taps = u32::from_le_bytes(le_bytes(iter));
+        let dual_action_holds = u32::from_le_bytes(le_bytes(iter));
+        let memo_high_water = u16::from_le_bytes(le_bytes(iter));
+        let macro_stack_high_water = u16::from_le_bytes(le_bytes(iter));
+        let report_overflows = u32::from_le_bytes(le_bytes(iter));
+        let [layer_count] = le_bytes(iter);
+        let layer_active = (0..layer_count)
+            .map(|_| Duration::from_millis(u32::from_le_bytes(le_bytes(iter)) as u64))
+            .collect();
+        let key_presses = (0..rows as usize * cols as usize)
+            .map(|_| u32::from_le_bytes(le_bytes(iter)))
+            .collect();
+
         Self {
-            uptime: Duration::from_millis(u32::from_le_bytes(value[..4].try_into().unwrap()) as u64),
+            uptime,
+            rows,
+            cols,
+            dual_action_taps,
+            dual_action_holds,
+            memo_high_water,
+            macro_stack_high_water,
+            report_overflows,
+            layer_active,
+            key_presses,
+        }
+    }
+}
+
+/// A key switch event reported by the keyboard while key logging is on.
+#[derive(Debug, PartialEq)]
+pub struct KeyScan {
+    pub row: u8,
+    pub col: u8,
+    pub is_down: bool,
+    /// Keyboard uptime when the event was scanned.
+    pub time: Duration,
+}
+impl KeyScan {
+    fn from_msg(value: &[u8]) -> Option<Self> {
+        let data = value.get(1..7)?;
+        Some(Self {
+            row: data[0] & 0x7f,
+            col: data[1],
+            is_down: data[0] & 0x80 != 0,
+            time: Duration::from_millis(u32::from_le_bytes(data[2..].try_into().unwrap()) as u64),
+        })
+    }
+}
+
+pub struct KeyScanIterator(HostRecvReceiver);
+impl Iterator for KeyScanIterator {
+    type Item = KeyScan;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let data = (self.0).0.recv().ok()?;
+            if let Some(scan) = KeyScan::from_msg(&data) {
+                return Some(scan);
+            }
         }
     }
 }
@@ -209,17 +317,44 @@ impl<I: KeyboardInterface> KeyboardCtl<I> {
         }
     }
 
-    pub fn save_config(&self, data: &[u16], file_name: Option<&OsStr>) -> Result<()> {
+    /// Save the config binary `data`. If `source` is supplied it is compressed and saved as a
+    /// [`FileType::ConfigSource`] file straight after the config so that it can be recovered later.
+    pub fn save_config(
+        &self,
+        data: &[u16],
+        file_name: Option<&OsStr>,
+        source: Option<&str>,
+    ) -> Result<()> {
+        self.save_file(FileType::Config, data.len() << 1, u16tou8(data), file_name)?;
+        if let Some(source) = source {
+            let data = compress_to_vec_zlib(source.as_bytes(), 9);
+            self.save_file(
+                FileType::ConfigSource,
+                data.len(),
+                data.into_iter(),
+                file_name,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn save_file(
+        &self,
+        file_type: FileType,
+        len: usize,
+        data: impl Iterator<Item = u8>,
+        file_name: Option<&OsStr>,
+    ) -> Result<()> {
         self.out(vec![msg::OPEN_SAVE_CONFIG])?;
         let (file_name, file_name_len) = file_name_iter(file_name);
 
-        let iter = (18 + file_name_len as u32 + ((data.len() as u32) << 1))
+        let iter = (18 + file_name_len as u32 + len as u32)
             .to_le_bytes()
             .into_iter()
             .chain(chrono::Local::now().timestamp_millis().to_le_bytes())
-            .chain([FileType::Config.as_u8(), file_name_len as u8])
+            .chain([file_type.as_u8(), file_name_len as u8])
             .chain(file_name.copied())
-            .chain(u16tou8(data));
+            .chain(data);
 
         for chunk in chunked(iter, MAX_BULK_LEN as usize) {
             if chunk.len() < MAX_BULK_LEN as usize {
@@ -258,20 +393,132 @@ impl<I: KeyboardInterface> KeyboardCtl<I> {
     }
 
     pub fn fetch_stats(&self) -> Result<KeyboardStats> {
-        let msg = vec![msg::FETCH_STATS];
+        let mut receiver = self.handle_incomming(host_recv::STATS)?;
+        let result = self.receive_stats(&mut receiver);
+        self.release_handler(host_recv::STATS);
+        result
+    }
 
-        let mut receiver = self.handle_incomming(host_recv::STATS).unwrap();
+    fn receive_stats(&self, receiver: &mut HostRecvReceiver) -> Result<KeyboardStats> {
+        self.out(vec![msg::FETCH_STATS])?;
 
-        self.intf
-            .bulk_out(self.epout, msg)
-            .map_err(|e| anyhow!(e))?;
+        let mut ans = vec![];
+        loop {
+            let data = receiver.recv()?;
+            ans.extend_from_slice(data.get(1..).unwrap_or_default());
+            if data.len() < MAX_BULK_LEN as usize {
+                break;
+            }
+        }
 
-        let data = match receiver.recv() {
-            Ok(data) => data,
-            Err(err) => return Err(anyhow!(err)),
-        };
+        Ok(KeyboardStats::from(ans.as_slice()))
+    }
+
+    /// Turn the reporting of key switch events on or off.
+    pub fn log_keys(&self, on: bool) -> Result<()> {
+        self.out(vec![msg::SCAN_KEYS, on as u8])
+    }
 
-        Ok(KeyboardStats::from(&data[1..]))
+    /// Iterate over the key switch events reported by the keyboard. Events are only reported
+    /// while [`log_keys`](Self::log_keys) is on.
+    pub fn key_scans(&self) -> Result<KeyScanIterator> {
+        Ok(KeyScanIterator(self.handle_incomming(host_recv::KEY_SCAN)?))
+    }
+
+    /// Read `len` bytes of the file at `location` starting from `offset`; fewer if the file ends
+    /// first.
+    pub fn read_file(&self, location: u32, offset: u32, len: u32) -> Result<Vec<u8>> {
+        let mut receiver = self.handle_incomming(host_recv::FILE_DATA)?;
+        let result = self.read_file_data(&mut receiver, location, offset, len);
+        self.release_handler(host_recv::FILE_DATA);
+        result
+    }
+
+    /// Fetch the whole contents, including the header, of the file at `location`.
+    pub fn fetch_file_by_location(&self, location: u32) -> Result<Vec<u8>> {
+        let ans = self.read_file(location, 0, u32::MAX)?;
+        if ans.is_empty() {
+            return Err(anyhow!("File not found"));
+        }
+        Ok(ans)
+    }
+
+    /// Load the config file at `index` and make it the config loaded on boot.
+    pub fn activate_file_by_index(&self, index: u32) -> Result<u32> {
+        self.activate_file(msg::ACTIVATE_FILE_BY_INDEX, index)
+    }
+
+    /// Load the config file at `location` and make it the config loaded on boot.
+    pub fn activate_file_by_location(&self, location: u32) -> Result<u32> {
+        self.activate_file(msg::ACTIVATE_FILE_BY_LOCATION, location)
+    }
+
+    fn activate_file(&self, kind: u8, n: u32) -> Result<u32> {
+        let mut receiver = self.handle_incomming(host_recv::ACTIVATE)?;
+        let result = self.receive_activate(&mut receiver, kind, n);
+        self.release_handler(host_recv::ACTIVATE);
+        result
+    }
+
+    fn receive_activate(&self, receiver: &mut HostRecvReceiver, kind: u8, n: u32) -> Result<u32> {
+        let mut msg = vec![kind];
+        msg.extend_from_slice(&n.to_le_bytes());
+        self.out(msg)?;
+
+        let data = receiver.recv()?;
+        match data
+            .get(1..5)
+            .map(|l| u32::from_le_bytes(l.try_into().unwrap()))
+        {
+            Some(location) if location != 0 => Ok(location),
+            _ => Err(anyhow!("Not a config file")),
+        }
+    }
+
+    /// Find the config that is active; the config loaded on boot. `files` must be the complete
+    /// list of files in index order.
+    pub fn active_config<'a>(&self, files: &'a [FileInfo]) -> Result<Option<&'a FileInfo>> {
+        let mut active = None;
+        for info in files {
+            match info.file_type {
+                FileType::Config if active.is_none_or(|l| l == info.location) => {
+                    return Ok(Some(info));
+                }
+                FileType::Active if active.is_none() => {
+                    let data = self.fetch_file_by_location(info.location)?;
+                    active = Some(active_location_from_file(&data)?);
+                }
+                _ => {}
+            }
+        }
+        // the active config no longer exists so the newest config is loaded
+        Ok(files.iter().find(|i| i.file_type == FileType::Config))
+    }
+
+    fn read_file_data(
+        &self,
+        receiver: &mut HostRecvReceiver,
+        location: u32,
+        mut offset: u32,
+        len: u32,
+    ) -> Result<Vec<u8>> {
+        let end = offset.saturating_add(len);
+        let mut ans = vec![];
+        while offset < end {
+            let mut msg = vec![msg::READ_FILE_DATA];
+            msg.extend_from_slice(&location.to_le_bytes());
+            msg.extend_from_slice(&offset.to_le_bytes());
+            self.out(msg)?;
+
+            let data = receiver.recv()?;
+            if data.len() < 2 {
+                break;
+            }
+            ans.extend_from_slice(&data[1..]);
+            offset += data.len() as u32 - 1;
+        }
+        ans.truncate(len as usize);
+        Ok(ans)
     }
 
     pub fn listen(&self) {
@@ -297,6 +544,10 @@ impl<I: KeyboardInterface> KeyboardCtl<I> {
         }
     }
 
+    fn release_handler(&self, id: u8) {
+        self.handlers.lock().unwrap().remove(&id);
+    }
+
     fn handle_incomming(&self, id: u8) -> Result<HostRecvReceiver> {
         let mut guard = self.handlers.lock().unwrap();
 
@@ -310,6 +561,36 @@ impl<I: KeyboardInterface> KeyboardCtl<I> {
     }
 }
 
+/// Decompress the config source from the contents of a [`FileType::ConfigSource`] file.
+pub fn config_source_from_file(data: &[u8]) -> Result<String> {
+    let corrupt = || anyhow!("Config source file is corrupt");
+    let start = 14 + *data.get(13).ok_or_else(corrupt)? as usize;
+    let src =
+        decompress_to_vec_zlib(data.get(start..).ok_or_else(corrupt)?).map_err(|_| corrupt())?;
+    Ok(String::from_utf8(src)?)
+}
+
+/// Read the layout binary from the contents of a [`FileType::Config`] file.
+pub fn config_from_file(data: &[u8]) -> Result<Vec<u16>> {
+    let corrupt = || anyhow!("Config file is corrupt");
+    let start = 14 + *data.get(13).ok_or_else(corrupt)? as usize;
+    Ok(data
+        .get(start..)
+        .ok_or_else(corrupt)?
+        .chunks_exact(2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .collect())
+}
+
+/// Read the location of the active config from the contents of a [`FileType::Active`] file.
+pub fn active_location_from_file(data: &[u8]) -> Result<u32> {
+    let start = 14 + *data.get(13).unwrap_or(&u8::MAX) as usize;
+    match data.get(start..start + 4) {
+        Some(l) => Ok(u32::from_le_bytes(l.try_into().unwrap())),
+        None => Err(anyhow!("Active marker file is corrupt")),
+    }
+}
+
 pub fn file_name_iter(file_name: Option<&OsStr>) -> (impl Iterator<Item = &u8>, usize) {
     let file_name = file_name.unwrap_or(OsStr::new("")).as_encoded_bytes();
     let file_name = &file_name[..min(50, file_name.len())];
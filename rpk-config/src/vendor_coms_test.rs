@@ -34,7 +34,7 @@ impl KeyboardInterface for TestInterface {
         let mut guard = self.inp.lock().unwrap();
         let (ep, msg) = guard.pop().unwrap_or((endpoint, vec![]));
         assert_eq!(ep, endpoint);
-        assert!(max_len as usize > msg.len());
+        assert!(max_len as usize >= msg.len());
         Ok(msg)
     }
 }
@@ -116,3 +116,252 @@ fn stats() {
 
     assert_eq!(stats.uptime, Duration::from_millis(uptime as u64));
 }
+
+#[test]
+fn rich_stats() {
+    let ctl = new_ctl();
+
+    let mut receiver = ctl.handle_incomming(host_recv::STATS).unwrap();
+
+    let mut data = vec![];
+    data.extend_from_slice(&5000u32.to_le_bytes());
+    data.extend_from_slice(&[2, 10]);
+    data.extend_from_slice(&7u32.to_le_bytes());
+    data.extend_from_slice(&3u32.to_le_bytes());
+    data.extend_from_slice(&12u16.to_le_bytes());
+    data.extend_from_slice(&4u16.to_le_bytes());
+    data.extend_from_slice(&1u32.to_le_bytes());
+    data.push(6);
+    for i in 0..6u32 {
+        data.extend_from_slice(&(i * 100).to_le_bytes());
+    }
+    for i in 0..20u32 {
+        data.extend_from_slice(&i.to_le_bytes());
+    }
+    for chunk in data.chunks(63) {
+        let mut msg = vec![host_recv::STATS];
+        msg.extend_from_slice(chunk);
+        ctl.intf.add_in(2, msg);
+    }
+
+    let ctl2 = ctl.clone();
+    spawn(move || {
+        ctl2.listen();
+    });
+
+    let stats = ctl.receive_stats(&mut receiver).unwrap();
+
+    assert_eq!(stats.uptime, Duration::from_millis(5000));
+    assert_eq!((stats.rows, stats.cols), (2, 10));
+    assert_eq!(stats.dual_action_taps, 7);
+    assert_eq!(stats.dual_action_holds, 3);
+    assert_eq!(stats.memo_high_water, 12);
+    assert_eq!(stats.macro_stack_high_water, 4);
+    assert_eq!(stats.report_overflows, 1);
+    assert_eq!(stats.layer_active.len(), 6);
+    assert_eq!(stats.layer_active[5], Duration::from_millis(500));
+    assert_eq!(stats.key_presses, (0..20).collect::<Vec<u32>>());
+}
+
+#[test]
+fn file_type_from() {
+    assert_eq!(
+        FileType::from(FileType::DynMacro.as_u8()),
+        FileType::DynMacro
+    );
+    assert_eq!(FileType::from(FileType::Config.as_u8()), FileType::Config);
+    assert_eq!(FileType::DynMacro.to_string(), "dyn_macro");
+}
+
+#[test]
+fn read_file() {
+    let ctl = new_ctl();
+
+    let mut receiver = ctl.handle_incomming(host_recv::FILE_DATA).unwrap();
+
+    let data: Vec<u8> = (0..70).collect();
+    let mut msg = vec![host_recv::FILE_DATA];
+    msg.extend_from_slice(&data[..62]);
+    ctl.intf.add_in(2, msg);
+    let mut msg = vec![host_recv::FILE_DATA];
+    msg.extend_from_slice(&data[62..]);
+    ctl.intf.add_in(2, msg);
+
+    let ctl2 = ctl.clone();
+    spawn(move || {
+        ctl2.listen();
+    });
+
+    let ans = ctl.read_file_data(&mut receiver, 1234, 0, 70).unwrap();
+    assert_eq!(ans, data);
+
+    let out = ctl.intf.get_out();
+    assert_eq!(out.len(), 2);
+    assert_eq!(out[0].1, vec![8, 210, 4, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(out[1].1, vec![8, 210, 4, 0, 0, 62, 0, 0, 0]);
+
+    // handler is released after reading
+    drop(receiver);
+    ctl.handlers.lock().unwrap().clear();
+    assert_eq!(ctl.read_file(1234, 0, 0).unwrap(), Vec::<u8>::new());
+    assert!(ctl.handlers.lock().unwrap().is_empty());
+}
+
+#[test]
+fn fetch_file() {
+    let ctl = new_ctl();
+
+    let mut receiver = ctl.handle_incomming(host_recv::FILE_DATA).unwrap();
+
+    let data: Vec<u8> = (0..126).collect();
+    for chunk in data.chunks(63) {
+        let mut msg = vec![host_recv::FILE_DATA];
+        msg.extend_from_slice(chunk);
+        ctl.intf.add_in(2, msg);
+    }
+    ctl.intf.add_in(2, vec![host_recv::FILE_DATA]);
+
+    let ctl2 = ctl.clone();
+    spawn(move || {
+        ctl2.listen();
+    });
+
+    // reads until the end of the file
+    let ans = ctl
+        .read_file_data(&mut receiver, 1234, 0, u32::MAX)
+        .unwrap();
+    assert_eq!(ans, data);
+
+    let out = ctl.intf.get_out();
+    assert_eq!(out.len(), 3);
+    assert_eq!(out[0].1, vec![8, 210, 4, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(out[1].1, vec![8, 210, 4, 0, 0, 63, 0, 0, 0]);
+    assert_eq!(out[2].1, vec![8, 210, 4, 0, 0, 126, 0, 0, 0]);
+}
+
+#[test]
+fn save_config_with_source() {
+    let ctl = new_ctl();
+
+    let src = "[main]\na = b\n".repeat(20);
+    ctl.save_config(&[1, 2, 3], Some(OsStr::new("ab.conf")), Some(&src))
+        .unwrap();
+
+    let out = ctl.intf.get_out();
+    assert_eq!(out[0].1, vec![msg::OPEN_SAVE_CONFIG]);
+    assert_eq!(out[1].1[0], msg::CLOSE_SAVE_CONFIG);
+    assert_eq!(out[1].1[13], FileType::Config.as_u8());
+    assert_eq!(out[2].1, vec![msg::OPEN_SAVE_CONFIG]);
+    assert_eq!(out[3].1[0], msg::CLOSE_SAVE_CONFIG);
+
+    let mut file = out[3].1[1..].to_vec();
+    assert_eq!(file[12], FileType::ConfigSource.as_u8());
+    assert!(file.len() < src.len());
+
+    // files are allocated a little more space than they need
+    file.extend_from_slice(&[0xff; 4]);
+    assert_eq!(config_source_from_file(&file).unwrap(), src);
+
+    assert!(config_source_from_file(&file[..20]).is_err());
+}
+
+#[test]
+fn read_config_file() {
+    let ctl = new_ctl();
+
+    ctl.save_config(&[1, 0x203, 3], Some(OsStr::new("ab.conf")), None)
+        .unwrap();
+
+    let out = ctl.intf.get_out();
+    let mut file = out[1].1[1..].to_vec();
+    file.extend_from_slice(&[0xff; 4]);
+    assert_eq!(
+        config_from_file(&file).unwrap(),
+        [1, 0x203, 3, 0xffff, 0xffff]
+    );
+
+    assert!(config_from_file(&file[..10]).is_err());
+}
+
+#[test]
+fn activate_file() {
+    let ctl = new_ctl();
+
+    let mut receiver = ctl.handle_incomming(host_recv::ACTIVATE).unwrap();
+
+    let mut msg = vec![host_recv::ACTIVATE];
+    msg.extend_from_slice(&1234u32.to_le_bytes());
+    ctl.intf.add_in(2, msg);
+    let mut msg = vec![host_recv::ACTIVATE];
+    msg.extend_from_slice(&0u32.to_le_bytes());
+    ctl.intf.add_in(2, msg);
+
+    let ctl2 = ctl.clone();
+    spawn(move || {
+        ctl2.listen();
+    });
+
+    let ans = ctl
+        .receive_activate(&mut receiver, msg::ACTIVATE_FILE_BY_INDEX, 3)
+        .unwrap();
+    assert_eq!(ans, 1234);
+
+    let ans = ctl.receive_activate(&mut receiver, msg::ACTIVATE_FILE_BY_LOCATION, 4321);
+    assert_eq!(ans.unwrap_err().to_string(), "Not a config file");
+
+    let out = ctl.intf.get_out();
+    assert_eq!(out[0], (1, vec![9, 3, 0, 0, 0]));
+    assert_eq!(out[1], (1, vec![10, 225, 16, 0, 0]));
+}
+
+#[test]
+fn active_location() {
+    let mut file = vec![0; 12];
+    file.extend_from_slice(&[FileType::Active.as_u8(), 6]);
+    file.extend_from_slice(b"active");
+    file.extend_from_slice(&5678u32.to_le_bytes());
+
+    assert_eq!(active_location_from_file(&file).unwrap(), 5678);
+    assert_eq!(FileType::from(file[12]), FileType::Active);
+    assert!(active_location_from_file(&file[..22]).is_err());
+}
+
+#[test]
+fn key_scans() {
+    let ctl = new_ctl();
+
+    let mut keys = ctl.key_scans().unwrap();
+    ctl.log_keys(true).unwrap();
+
+    ctl.intf
+        .add_in(2, vec![host_recv::KEY_SCAN, 0x83, 5, 0x10, 0x27, 0, 0]);
+    ctl.intf.add_in(2, vec![host_recv::KEY_SCAN, 2]);
+    ctl.intf
+        .add_in(2, vec![host_recv::KEY_SCAN, 1, 4, 0x11, 0x27, 0, 0]);
+
+    let ctl2 = ctl.clone();
+    spawn(move || {
+        ctl2.listen();
+    });
+
+    assert_eq!(
+        keys.next(),
+        Some(KeyScan {
+            row: 3,
+            col: 5,
+            is_down: true,
+            time: Duration::from_millis(10000),
+        })
+    );
+    assert_eq!(
+        keys.next(),
+        Some(KeyScan {
+            row: 1,
+            col: 4,
+            is_down: false,
+            time: Duration::from_millis(10001),
+        })
+    );
+
+    assert_eq!(ctl.intf.get_out(), vec![(1, vec![msg::SCAN_KEYS, 1])]);
+}
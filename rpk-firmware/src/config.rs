@@ -1,10 +1,13 @@
 use crate::{
     firmware_functions, mapper,
-    ring_fs::{RingFs, RingFsReader, RingFsWriter},
+    ring_fs::{RingFs, RingFsError, RingFsReader, RingFsWriter},
 };
 use embassy_sync::{blocking_mutex::raw::NoopRawMutex, channel::Channel};
 use embassy_time::Instant;
-use rpk_common::usb_vendor_message::{self as msg, MAX_BULK_LEN, host_recv};
+use rpk_common::{
+    keycodes::dyn_macro,
+    usb_vendor_message::{self as msg, MAX_BULK_LEN, host_recv},
+};
 
 enum ReceiveState {
     Idle,
@@ -25,17 +28,25 @@ impl HostMessage {
         }
     }
 
-    pub fn stats(time: u32) -> Self {
-        let time = time.to_le_bytes();
-        let mut time = time.iter();
-        let data = core::array::from_fn(|i| match i {
-            0 => host_recv::STATS,
-            1..5 => *time.next().unwrap(),
-            _ => 0,
-        });
+    pub fn file_data() -> Self {
+        let mut data = [0; MSG_LEN];
+        data[0] = host_recv::FILE_DATA;
+        Self { len: 0, data }
+    }
+
+    pub fn activate(location: u32) -> Self {
+        let mut data = [0; MSG_LEN];
+        data[0] = host_recv::ACTIVATE;
+        data[1..5].copy_from_slice(&location.to_le_bytes());
         Self { len: 4, data }
     }
 
+    pub fn stats() -> Self {
+        let mut data = [0; MSG_LEN];
+        data[0] = host_recv::STATS;
+        Self { len: 0, data }
+    }
+
     pub fn as_slice(&self) -> &[u8] {
         &self.data[..(self.len + 1)]
     }
@@ -43,12 +54,14 @@ impl HostMessage {
     pub fn key_scan() -> Self {
         let mut data = [0; MSG_LEN];
         data[0] = host_recv::KEY_SCAN;
-        Self { len: 3, data }
+        Self { len: 6, data }
     }
 
-    pub fn set_key(&mut self, memo_bytes: (u8, u8)) {
-        self.data[1] = memo_bytes.0;
-        self.data[2] = memo_bytes.1;
+    pub fn set_key(&mut self, key: &mapper::TimedScanKey) {
+        let (row, col) = key.0.as_memo_bytes();
+        self.data[1] = row;
+        self.data[2] = col;
+        self.data[3..7].copy_from_slice(&(key.1 as u32).to_le_bytes());
     }
 }
 
@@ -70,6 +83,7 @@ pub struct ConfigInterface<'f, 'c, const N: usize> {
     mapper_ctl: &'c mapper::ControlSignal,
     fw: Option<RingFsWriter<'f>>,
     rcv_state: ReceiveState,
+    stats: &'c mapper::stats::Stats,
     pub host_channel: &'c HostChannel<N>,
 }
 
@@ -78,12 +92,14 @@ impl<'f, 'c, const N: usize> ConfigInterface<'f, 'c, N> {
         fs: &'f dyn RingFs<'f>,
         mapper_ctl: &'c mapper::ControlSignal,
         host_channel: &'c HostChannel<N>,
+        stats: &'c mapper::stats::Stats,
     ) -> Self {
         Self {
             fs,
             mapper_ctl,
             fw: None,
             rcv_state: ReceiveState::Idle,
+            stats,
             host_channel,
         }
     }
@@ -116,9 +132,37 @@ impl<'f, 'c, const N: usize> ConfigInterface<'f, 'c, N> {
                     }
                     self.host_channel.0.send(HostMessage::file_info()).await;
                 }
+                msg::READ_FILE_DATA if data.len() == 9 => {
+                    let mut reply = HostMessage::file_data();
+                    if let Ok(mut fr) = self
+                        .fs
+                        .file_reader_by_location(u32::from_le_bytes(data[1..5].try_into().unwrap()))
+                    {
+                        fr.seek(u32::from_le_bytes(data[5..].try_into().unwrap()));
+                        if let Ok(n) = fr.read(&mut reply.data[1..]) {
+                            reply.len = n as usize;
+                        }
+                    }
+                    self.host_channel.0.send(reply).await;
+                }
+                msg::ACTIVATE_FILE_BY_INDEX | msg::ACTIVATE_FILE_BY_LOCATION if data.len() == 5 => {
+                    let n = u32::from_le_bytes(data[1..].try_into().unwrap());
+                    let location = if data[0] == msg::ACTIVATE_FILE_BY_INDEX {
+                        self.fs.file_reader_by_index(n).map(|fr| fr.location()).ok()
+                    } else {
+                        Some(n)
+                    };
+                    let location = location.filter(|l| self.activate(*l)).unwrap_or(0);
+                    self.host_channel
+                        .0
+                        .send(HostMessage::activate(location))
+                        .await;
+                }
+                msg::SCAN_KEYS if data.len() == 2 => {
+                    self.mapper_ctl.log_keys(data[1] != 0);
+                }
                 msg::FETCH_STATS if data.len() == 1 => {
-                    let now = Instant::now().as_millis() as u32;
-                    self.host_channel.0.send(HostMessage::stats(now)).await;
+                    self.send_stats().await;
                 }
                 n => {
                     crate::error!("Unexpected msg [{}; {}]", n, data.len())
@@ -135,7 +179,11 @@ impl<'f, 'c, const N: usize> ConfigInterface<'f, 'c, N> {
                     let data = data.split_at(1).1;
                     self.file_write(data);
                     if let Some(fw) = self.fw.take() {
-                        self.mapper_ctl.load_layout(fw.location());
+                        let location = fw.location();
+                        drop(fw);
+                        if self.is_config_file(location) {
+                            self.mapper_ctl.load_layout(location);
+                        }
                     }
                     self.rcv_state = ReceiveState::Idle;
                 }
@@ -147,6 +195,60 @@ impl<'f, 'c, const N: usize> ConfigInterface<'f, 'c, N> {
         }
     }
 
+    /// Send the [stats](mapper::stats::StatsData::bytes) to the host as a series of `STATS`
+    /// messages. The last message is shorter than a full message.
+    async fn send_stats(&self) {
+        let now = Instant::now().as_millis() as u32;
+        let mut offset = 0;
+        loop {
+            let mut reply = HostMessage::stats();
+            self.stats.lock(|s| {
+                for (d, b) in reply.data[1..].iter_mut().zip(s.bytes(now).skip(offset)) {
+                    *d = b;
+                    reply.len += 1;
+                }
+            });
+            offset += reply.len;
+            let done = reply.len < MSG_LEN - 1;
+            self.host_channel.0.send(reply).await;
+            if done {
+                return;
+            }
+        }
+    }
+
+    /// Load the config file at `location` and mark it as the one to load on boot.
+    fn activate(&self, location: u32) -> bool {
+        let is_config = match self.fs.file_reader_by_location(location) {
+            Ok(mut fr) => file_type(&mut fr) == Some(msg::file_type::CONFIG),
+            Err(_) => false,
+        };
+        if !is_config {
+            return false;
+        }
+        if let Err(err) = save_active(self.fs, location) {
+            crate::info!("error saving active marker {:?}", err);
+            return false;
+        }
+        self.mapper_ctl.load_layout(location);
+        true
+    }
+
+    /// Files saved by the host are config files unless their header says otherwise.
+    fn is_config_file(&self, location: u32) -> bool {
+        match self.fs.file_reader_by_location(location) {
+            Ok(mut fr) => !matches!(
+                file_type(&mut fr),
+                Some(
+                    msg::file_type::DYN_MACRO
+                        | msg::file_type::CONFIG_SOURCE
+                        | msg::file_type::ACTIVE
+                )
+            ),
+            Err(_) => true,
+        }
+    }
+
     fn file_write(&mut self, data: &[u8]) {
         if self.fw.is_none() {
             match self.fs.create_file() {
@@ -165,6 +267,150 @@ impl<'f, 'c, const N: usize> ConfigInterface<'f, 'c, N> {
     }
 }
 
+/// Offset of the file type within a file's header.
+const FILE_TYPE_OFFSET: u32 = 12;
+
+/// Read the [file type](msg::file_type) of a file.
+pub fn file_type(reader: &mut RingFsReader) -> Option<u8> {
+    reader.seek(FILE_TYPE_OFFSET);
+    let mut buf = [0];
+    match reader.read(&mut buf) {
+        Ok(1) => Some(buf[0]),
+        _ => None,
+    }
+}
+
+/// Create a new file and write its header. `name` must have an even length.
+fn create_file<'f>(
+    fs: &'f dyn RingFs<'f>,
+    file_type: u8,
+    name: &[u8],
+    data_len: usize,
+) -> Result<RingFsWriter<'f>, RingFsError> {
+    let mut fw = fs.create_file()?;
+    fw.write(&((14 + name.len() + data_len) as u32).to_le_bytes())?;
+    fw.write(&0i64.to_le_bytes())?;
+    fw.write(&[file_type, name.len() as u8])?;
+    fw.write(name)?;
+    Ok(fw)
+}
+
+/// Save the items of dynamic macro `slot` as a new file and return its location.
+pub fn save_dyn_macro<'f>(
+    fs: &'f dyn RingFs<'f>,
+    slot: u8,
+    items: &[u16],
+) -> Result<u32, RingFsError> {
+    // names are padded to an even length with a leading zero
+    let name = [
+        0,
+        b'd',
+        b'y',
+        b'n',
+        b'_',
+        b'm',
+        b'a',
+        b'c',
+        b'r',
+        b'o',
+        b'_',
+        b'1' + slot,
+    ];
+
+    let mut fw = create_file(fs, msg::file_type::DYN_MACRO, &name, 2 + (items.len() << 1))?;
+    fw.write(&(slot as u16).to_le_bytes())?;
+    for chunk in items.chunks(32) {
+        let mut buf = [0; 64];
+        for (d, s) in buf.chunks_mut(2).zip(chunk) {
+            d.copy_from_slice(&s.to_le_bytes());
+        }
+        fw.write(&buf[..(chunk.len() << 1)])?;
+    }
+    Ok(fw.location())
+}
+
+/// The location of the newest file of each dynamic macro slot.
+pub fn find_dyn_macros<'f>(fs: &'f dyn RingFs<'f>) -> [Option<u32>; dyn_macro::COUNT] {
+    let mut found = [None; dyn_macro::COUNT];
+    let mut index = 0;
+    while let Ok(mut fr) = fs.file_reader_by_index(index) {
+        index += 1;
+        if file_type(&mut fr) == Some(msg::file_type::DYN_MACRO) {
+            let location = fr.location();
+            if let Some(slot) = ConfigFileIter::new(fr).next().map(|s| s as usize)
+                && slot < dyn_macro::COUNT
+                && found[slot].is_none()
+            {
+                found[slot] = Some(location);
+            }
+        }
+    }
+    found
+}
+
+/// The items of dynamic macro `slot` in the file at `location`; `None` if that is not the macro's
+/// file.
+pub fn read_dyn_macro<'f>(
+    fs: &'f dyn RingFs<'f>,
+    location: u32,
+    slot: usize,
+) -> Option<ConfigFileIter<'f>> {
+    let mut fr = fs.file_reader_by_location(location).ok()?;
+    if file_type(&mut fr) != Some(msg::file_type::DYN_MACRO) {
+        return None;
+    }
+    let mut iter = ConfigFileIter::new(fr);
+    (iter.next() == Some(slot as u16)).then_some(iter)
+}
+
+/// Mark the config file at `location` as the one to load on boot.
+pub fn save_active<'f>(fs: &'f dyn RingFs<'f>, location: u32) -> Result<(), RingFsError> {
+    let mut fw = create_file(fs, msg::file_type::ACTIVE, b"active", 4)?;
+    fw.write(&location.to_le_bytes())
+}
+
+/// Read the config file location from an active marker file.
+pub fn read_active(mut reader: RingFsReader) -> Option<u32> {
+    reader.seek(13);
+    let mut buf = [0; 4];
+    reader.read(&mut buf[..1]).ok()?; // filename length
+    reader.seek(14 + buf[0] as u32);
+    match reader.read(&mut buf) {
+        Ok(4) => Some(u32::from_le_bytes(buf)),
+        _ => None,
+    }
+}
+
+/// The location of the config file to load on boot. This is the config the newest active marker
+/// points to; unless a newer config has been uploaded since.
+pub fn boot_config_location<'f>(fs: &'f dyn RingFs<'f>) -> Option<u32> {
+    let mut newest_config = None;
+    let mut active = None;
+    let mut index = 0;
+    while let Ok(mut fr) = fs.file_reader_by_index(index) {
+        index += 1;
+        match file_type(&mut fr) {
+            Some(msg::file_type::CONFIG) => {
+                let location = fr.location();
+                if active == Some(location) {
+                    return active;
+                }
+                if newest_config.is_none() {
+                    newest_config = Some(location);
+                    if active.is_none() {
+                        return newest_config;
+                    }
+                }
+            }
+            Some(msg::file_type::ACTIVE) if active.is_none() => {
+                active = read_active(fr);
+            }
+            _ => {}
+        }
+    }
+    newest_config
+}
+
 pub struct ConfigFileIter<'f>(RingFsReader<'f>);
 
 impl Iterator for ConfigFileIter<'_> {
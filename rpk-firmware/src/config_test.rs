@@ -19,8 +19,9 @@ macro_rules! setup {
         let $ctl_sig = ControlSignal::default();
 
         let host_channel = Default::default();
+        let stats = mapper::stats::Stats::default();
 
-        let mut $ci = ConfigInterface::<'_, '_, 2>::new(&$fs, &$ctl_sig, &host_channel);
+        let mut $ci = ConfigInterface::<'_, '_, 2>::new(&$fs, &$ctl_sig, &host_channel, &stats);
         block_on(async { $x })
     };
 }
@@ -86,16 +87,121 @@ fn read_file() {
 }
 
 #[test]
-fn fetch_stats() {
+fn read_file_data() {
     setup!(ci, ctl_sig, fs, {
+        let mut fw = fs.create_file().unwrap();
+        let mut data: [u8; 100] = core::array::from_fn(|i| i as u8);
+        data[0..4].copy_from_slice(&100u32.to_le_bytes());
+        fw.write(&data).unwrap();
+        let location = fw.location().to_le_bytes();
+        drop(fw);
+
+        let mut req = [msg::READ_FILE_DATA, 0, 0, 0, 0, 10, 0, 0, 0];
+        req[1..5].copy_from_slice(&location);
+        ci.receive(&req).await;
+        let msg = ci.host_channel.0.try_receive().unwrap();
+        assert_eq!(msg.data[0], host_recv::FILE_DATA);
+        assert_eq!(msg.len, 63);
+        assert_eq!(&msg.data[1..], &data[10..73]);
+
+        req[5] = 73;
+        ci.receive(&req).await;
+        let msg = ci.host_channel.0.try_receive().unwrap();
+        assert_eq!(msg.len, 27);
+        assert_eq!(msg.as_slice()[1..], data[73..]);
+
+        req[5] = 200;
+        ci.receive(&req).await;
+        let msg = ci.host_channel.0.try_receive().unwrap();
+        assert_eq!(msg.len, 0);
+    });
+}
+
+#[test]
+fn save_config_source() {
+    setup!(ci, ctl_sig, fs, {
+        // config source files are not loaded as a layout
+        ci.receive(&[msg::OPEN_SAVE_CONFIG]).await;
+        let mut data = [0; 20];
+        data[0] = msg::CLOSE_SAVE_CONFIG;
+        data[1] = 19;
+        data[13] = msg::file_type::CONFIG_SOURCE;
+        ci.receive(&data).await;
+
+        assert!(ctl_sig.try_take().is_none());
+        let mut fr = fs.file_reader_by_index(0).unwrap();
+        assert_eq!(file_type(&mut fr), Some(msg::file_type::CONFIG_SOURCE));
+        assert!(matches!(ci.rcv_state, ReceiveState::Idle));
+    });
+}
+
+#[test]
+fn fetch_stats() {
+    let mut stub = DefaultNorFlashStub::default();
+    let fs = TestFs::new(&mut stub).unwrap();
+    let ctl_sig = ControlSignal::default();
+    let host_channel = HostChannel::<4>::default();
+    let stats = mapper::stats::Stats::default();
+    stats.set_matrix_size(2, 10);
+    stats.key_press(1, 2);
+    stats.key_press(1, 2);
+    stats.dual_action(true);
+    stats.report_overflow();
+    stats.lock(|s| {
+        s.layer_ms[5] = 1000;
+        s.memo_high_water = 12;
+    });
+
+    let mut ci = ConfigInterface::new(&fs, &ctl_sig, &host_channel, &stats);
+    block_on(async {
         crate::time_driver_test_stub::set_time(91235124);
         ci.receive(&[msg::FETCH_STATS]).await;
-        let msg = ci.host_channel.0.try_receive().unwrap();
-        assert_eq!(msg.len, 4);
-        assert_eq!(&msg.data[..4], &[1, 99, 100, 1]);
+
+        let mut data = std::vec::Vec::new();
+        let mut count = 0;
+        while let Ok(msg) = ci.host_channel.0.try_receive() {
+            assert_eq!(msg.data[0], host_recv::STATS);
+            data.extend_from_slice(&msg.as_slice()[1..]);
+            count += 1;
+        }
+        assert_eq!(count, 3);
+        assert_eq!(data.len(), 23 + 6 * 4 + 20 * 4);
+
+        assert_eq!(&data[..4], &[99, 100, 1, 0]);
+        assert_eq!(&data[4..6], &[2, 10]);
+        assert_eq!(&data[6..14], &[1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&data[14..16], &[12, 0]);
+        assert_eq!(&data[18..22], &[1, 0, 0, 0]);
+        assert_eq!(data[22], 6);
+        assert_eq!(&data[43..47], &[232, 3, 0, 0]);
+        assert_eq!(&data[47 + 12 * 4..47 + 13 * 4], &[2, 0, 0, 0]);
     });
 }
 
+#[test]
+fn scan_keys() {
+    setup!(ci, ctl_sig, fs, {
+        ci.receive(&[msg::SCAN_KEYS, 1]).await;
+        assert!(matches!(
+            ctl_sig.try_take(),
+            Some(ControlMessage::LogKeys(true))
+        ));
+
+        ci.receive(&[msg::SCAN_KEYS, 0]).await;
+        assert!(matches!(
+            ctl_sig.try_take(),
+            Some(ControlMessage::LogKeys(false))
+        ));
+    });
+
+    let mut msg = HostMessage::key_scan();
+    msg.set_key(&mapper::TimedScanKey(
+        crate::key_scanner::ScanKey::new(3, 5, true),
+        0x1_0000_0102,
+    ));
+    assert_eq!(msg.as_slice(), &[host_recv::KEY_SCAN, 0x83, 5, 2, 1, 0, 0]);
+}
+
 #[test]
 fn save_config() {
     setup!(ci, ctl_sig, fs, {
@@ -163,3 +269,115 @@ fn config_file_iter() {
     let ans: std::vec::Vec<u16> = iter.collect();
     assert_eq!(&ans, &[4625, 5139, 5653, 6167, 6681, 7195, 7709]);
 }
+
+#[test]
+fn save_dyn_macro() {
+    let mut stub = DefaultNorFlashStub::default();
+    let fs = TestFs::new(&mut stub).unwrap();
+    let items: std::vec::Vec<u16> = (1..40).collect();
+    let location = super::save_dyn_macro(&fs, 2, &items).unwrap();
+
+    let mut fr = fs.file_reader_by_index(0).unwrap();
+    assert_eq!(fr.location(), location);
+    assert_eq!(file_type(&mut fr), Some(msg::file_type::DYN_MACRO));
+    let mut name = [0; 13];
+    fr.read(&mut name).unwrap();
+    assert_eq!(&name, b"\x0c\0dyn_macro_3");
+
+    let ans: std::vec::Vec<u16> = ConfigFileIter::new(fr).collect();
+    assert_eq!(ans[0], 2);
+    assert_eq!(&ans[1..], &items);
+}
+
+#[test]
+fn find_dyn_macros() {
+    let mut stub = DefaultNorFlashStub::default();
+    let fs = TestFs::new(&mut stub).unwrap();
+    assert_eq!(super::find_dyn_macros(&fs), [None; 4]);
+
+    let m1 = super::save_dyn_macro(&fs, 0, &[1, 2]).unwrap();
+    let m2 = super::save_dyn_macro(&fs, 2, &[3]).unwrap();
+    let config = write_config(&fs, msg::file_type::CONFIG);
+    let m3 = super::save_dyn_macro(&fs, 0, &[4, 5, 6]).unwrap();
+
+    // the newest file of each slot
+    assert_eq!(
+        super::find_dyn_macros(&fs),
+        [Some(m3), None, Some(m2), None]
+    );
+
+    let items: std::vec::Vec<u16> = read_dyn_macro(&fs, m1, 0).unwrap().collect();
+    assert_eq!(items, [1, 2]);
+    let items: std::vec::Vec<u16> = read_dyn_macro(&fs, m3, 0).unwrap().collect();
+    assert_eq!(items, [4, 5, 6]);
+    assert!(read_dyn_macro(&fs, m2, 0).is_none());
+    assert!(read_dyn_macro(&fs, config, 0).is_none());
+}
+
+fn write_config<'f>(fs: &'f dyn RingFs<'f>, file_type: u8) -> u32 {
+    let mut fw = create_file(fs, file_type, b"ab", 2).unwrap();
+    fw.write(&[1, 2]).unwrap();
+    fw.location()
+}
+
+#[test]
+fn activate_file() {
+    setup!(ci, ctl_sig, fs, {
+        let c1 = write_config(&fs, msg::file_type::CONFIG);
+        let c2 = write_config(&fs, msg::file_type::CONFIG);
+        let s1 = write_config(&fs, msg::file_type::CONFIG_SOURCE);
+        assert_eq!(boot_config_location(&fs), Some(c2));
+
+        // rollback to c1
+        ci.receive(&[msg::ACTIVATE_FILE_BY_INDEX, 2, 0, 0, 0]).await;
+        let msg = ci.host_channel.0.try_receive().unwrap();
+        assert_eq!(msg.as_slice()[0], host_recv::ACTIVATE);
+        assert_eq!(msg.as_slice()[1..], c1.to_le_bytes());
+        let Some(ControlMessage::LoadLayout { file_location }) = ctl_sig.try_take() else {
+            panic!("expected LoadLayout()")
+        };
+        assert_eq!(file_location, c1);
+
+        let mut fr = fs.file_reader_by_index(0).unwrap();
+        assert_eq!(file_type(&mut fr), Some(msg::file_type::ACTIVE));
+        assert_eq!(read_active(fr), Some(c1));
+        assert_eq!(boot_config_location(&fs), Some(c1));
+
+        // only config files can be activated
+        let mut req = [msg::ACTIVATE_FILE_BY_LOCATION, 0, 0, 0, 0];
+        req[1..].copy_from_slice(&s1.to_le_bytes());
+        ci.receive(&req).await;
+        let msg = ci.host_channel.0.try_receive().unwrap();
+        assert_eq!(msg.as_slice(), &[host_recv::ACTIVATE, 0, 0, 0, 0]);
+        assert!(ctl_sig.try_take().is_none());
+
+        req[1..].copy_from_slice(&c2.to_le_bytes());
+        ci.receive(&req).await;
+        let msg = ci.host_channel.0.try_receive().unwrap();
+        assert_eq!(msg.as_slice()[1..], c2.to_le_bytes());
+        assert!(ctl_sig.try_take().is_some());
+        assert_eq!(boot_config_location(&fs), Some(c2));
+
+        // a new upload is loaded in preference to an older active marker
+        ci.receive(&[msg::ACTIVATE_FILE_BY_INDEX, 4, 0, 0, 0]).await;
+        ci.host_channel.0.try_receive().unwrap();
+        assert_eq!(boot_config_location(&fs), Some(c1));
+        let c3 = write_config(&fs, msg::file_type::CONFIG);
+        assert_eq!(boot_config_location(&fs), Some(c3));
+    });
+}
+
+#[test]
+fn boot_config_location_empty() {
+    let mut stub = DefaultNorFlashStub::default();
+    let fs = TestFs::new(&mut stub).unwrap();
+    assert_eq!(boot_config_location(&fs), None);
+
+    write_config(&fs, msg::file_type::DYN_MACRO);
+    assert_eq!(boot_config_location(&fs), None);
+
+    // active marker pointing to a missing config falls back to the newest config
+    let c1 = write_config(&fs, msg::file_type::CONFIG);
+    save_active(&fs, c1 + 1).unwrap();
+    assert_eq!(boot_config_location(&fs), Some(c1));
+}
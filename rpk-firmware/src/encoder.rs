@@ -0,0 +1,128 @@
+use core::pin::pin;
+
+use embassy_futures::select::{select, select_slice};
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embedded_hal::digital::InputPin;
+use embedded_hal_async::digital::Wait;
+
+use crate::key_scanner::{KeyScannerChannel, ScanKey};
+
+/// The pin state when an encoder is resting in a detent; both pins are pulled up.
+const REST: u8 = 0b11;
+
+/// The step for each `previous << 2 | current` pin state; 0 for no change or an invalid (bounced)
+/// transition.
+const STEPS: [i8; 16] = [0, 1, -1, 0, -1, 0, 0, 1, 1, 0, 0, -1, 0, -1, 1, 0];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Direction {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// Turns the quadrature signal of an encoder's A and B pins into one step per detent.
+#[derive(Debug)]
+pub struct QuadratureDecoder {
+    state: u8,
+    count: i8,
+}
+impl Default for QuadratureDecoder {
+    fn default() -> Self {
+        Self {
+            state: REST,
+            count: 0,
+        }
+    }
+}
+impl QuadratureDecoder {
+    /// Update with the current pin levels. Returns the direction turned when the encoder comes to
+    /// rest in the next detent. Swap the pins to reverse the direction.
+    pub fn update(&mut self, a: bool, b: bool) -> Option<Direction> {
+        let state = (a as u8) << 1 | b as u8;
+        self.count += STEPS[(self.state << 2 | state) as usize];
+        self.state = state;
+        if state != REST {
+            return None;
+        }
+        let count = core::mem::take(&mut self.count);
+        match count {
+            2.. => Some(Direction::Clockwise),
+            ..=-2 => Some(Direction::CounterClockwise),
+            _ => None,
+        }
+    }
+}
+
+/// Reports the steps of rotary encoders as taps of the keys at the encoders' matrix positions.
+pub struct Encoders<'c, I: InputPin + Wait, M: RawMutex, const N: usize, const PS: usize> {
+    a_pins: [I; N],
+    b_pins: [I; N],
+    decoders: [QuadratureDecoder; N],
+    /// The clockwise and counter-clockwise matrix positions (`row << 8 | column`) of each encoder.
+    keys: &'c [[u16; 2]; N],
+    channel: &'c KeyScannerChannel<M, PS>,
+}
+impl<'c, I: InputPin + Wait, M: RawMutex, const N: usize, const PS: usize>
+    Encoders<'c, I, M, N, PS>
+{
+    pub fn new(
+        a_pins: [I; N],
+        b_pins: [I; N],
+        keys: &'c [[u16; 2]; N],
+        channel: &'c KeyScannerChannel<M, PS>,
+    ) -> Self {
+        Self {
+            a_pins,
+            b_pins,
+            decoders: core::array::from_fn(|_| QuadratureDecoder::default()),
+            keys,
+            channel,
+        }
+    }
+
+    /// Read the pins and report any steps. Returns the pin levels read.
+    pub async fn update(&mut self) -> ([bool; N], [bool; N]) {
+        let mut levels = ([false; N], [false; N]);
+        for i in 0..N {
+            let a = self.a_pins[i].is_high().unwrap_or(true);
+            let b = self.b_pins[i].is_high().unwrap_or(true);
+            (levels.0[i], levels.1[i]) = (a, b);
+            if let Some(direction) = self.decoders[i].update(a, b) {
+                let pos = self.keys[i][direction as usize];
+                let (row, col) = ((pos >> 8) as u8, pos as u8);
+                self.channel.send(ScanKey::new(row, col, true)).await;
+                self.channel.send(ScanKey::new(row, col, false)).await;
+            }
+        }
+        levels
+    }
+
+    pub async fn run(&mut self) {
+        loop {
+            let (a_levels, b_levels) = self.update().await;
+
+            let mut a_futs = self.a_pins.iter_mut().zip(a_levels).map(wait_for_change);
+            let mut a_futs: [_; N] = core::array::from_fn(|_| a_futs.next().unwrap());
+            let mut b_futs = self.b_pins.iter_mut().zip(b_levels).map(wait_for_change);
+            let mut b_futs: [_; N] = core::array::from_fn(|_| b_futs.next().unwrap());
+            select(
+                select_slice(pin!(a_futs.as_mut_slice())),
+                select_slice(pin!(b_futs.as_mut_slice())),
+            )
+            .await;
+        }
+    }
+}
+
+async fn wait_for_change<I: Wait>((pin, is_high): (&mut I, bool)) {
+    let _ = if is_high {
+        pin.wait_for_low().await
+    } else {
+        pin.wait_for_high().await
+    };
+}
+
+#[cfg(test)]
+#[path = "encoder_test.rs"]
+mod test;
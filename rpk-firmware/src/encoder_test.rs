@@ -0,0 +1,119 @@
+extern crate std;
+
+use embassy_futures::{
+    block_on,
+    select::{Either, select},
+    yield_now,
+};
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embedded_hal::digital::OutputPin;
+
+use super::*;
+
+use crate::switch_test_stub::Pin;
+
+/// Pin levels (a, b) for one detent clockwise from rest.
+const CW: [(bool, bool); 4] = [(true, false), (false, false), (false, true), (true, true)];
+
+fn turn(decoder: &mut QuadratureDecoder, steps: &[(bool, bool)]) -> std::vec::Vec<Direction> {
+    steps
+        .iter()
+        .filter_map(|(a, b)| decoder.update(*a, *b))
+        .collect()
+}
+
+#[test]
+fn decode_detents() {
+    let mut decoder = QuadratureDecoder::default();
+
+    assert_eq!(turn(&mut decoder, &CW), [Direction::Clockwise]);
+
+    let ccw: std::vec::Vec<_> = CW
+        .iter()
+        .rev()
+        .skip(1)
+        .copied()
+        .chain([(true, true)])
+        .collect();
+    assert_eq!(turn(&mut decoder, &ccw), [Direction::CounterClockwise]);
+
+    assert_eq!(
+        turn(&mut decoder, &[CW, CW].concat()),
+        [Direction::Clockwise, Direction::Clockwise]
+    );
+}
+
+#[test]
+fn decode_ignores_bounce() {
+    let mut decoder = QuadratureDecoder::default();
+
+    // contact bounce on one pin
+    assert_eq!(
+        turn(
+            &mut decoder,
+            &[(true, false), (true, true), (true, false), (true, true)]
+        ),
+        []
+    );
+
+    // turned half way then back
+    assert_eq!(
+        turn(
+            &mut decoder,
+            &[(true, false), (false, false), (true, false), (true, true)]
+        ),
+        []
+    );
+
+    // bounce part way through a detent
+    assert_eq!(
+        turn(
+            &mut decoder,
+            &[
+                (true, false),
+                (false, false),
+                (true, false),
+                (false, false),
+                (false, true),
+                (true, true)
+            ]
+        ),
+        [Direction::Clockwise]
+    );
+}
+
+#[test]
+fn encoders_send_taps() {
+    let a = [Pin::new(1), Pin::new(3)];
+    let b = [Pin::new(2), Pin::new(4)];
+    for p in a.iter().chain(b.iter()) {
+        p.clone().set_high().ok();
+    }
+    let keys = [[0x0300, 0x0301], [0x0302, 0x0303]];
+    let channel = KeyScannerChannel::<NoopRawMutex, 16>::default();
+    let mut encoders = Encoders::new(a.clone(), b.clone(), &keys, &channel);
+
+    block_on(async {
+        let test = async {
+            for (level_a, level_b) in CW {
+                a[1].clone().set_state(level_a.into()).ok();
+                b[1].clone().set_state(level_b.into()).ok();
+                yield_now().await;
+            }
+            assert_eq!(channel.receive().await, ScanKey::new(3, 2, true));
+            assert_eq!(channel.receive().await, ScanKey::new(3, 2, false));
+
+            for (level_a, level_b) in CW.iter().rev().skip(1).chain(&[(true, true)]) {
+                a[0].clone().set_state((*level_a).into()).ok();
+                b[0].clone().set_state((*level_b).into()).ok();
+                yield_now().await;
+            }
+            assert_eq!(channel.receive().await, ScanKey::new(3, 1, true));
+            assert_eq!(channel.receive().await, ScanKey::new(3, 1, false));
+        };
+
+        if let Either::First(_) = select(encoders.run(), test).await {
+            panic!("Unexpected run end");
+        }
+    });
+}
@@ -1,4 +1,4 @@
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use embassy_usb::{
     class::hid::{ReadError, ReportId, RequestHandler},
     driver::{Driver, Endpoint, EndpointError, EndpointIn, EndpointOut},
@@ -8,11 +8,20 @@ use crate::warn;
 
 pub struct HidWriter<'d, D: Driver<'d>, const N: usize> {
     ep_in: D::EndpointIn,
+    boot_protocol: &'d AtomicBool,
 }
 
 impl<'d, D: Driver<'d>, const N: usize> HidWriter<'d, D, N> {
-    pub fn new(ep_in: <D>::EndpointIn) -> Self {
-        Self { ep_in }
+    pub fn new(ep_in: <D>::EndpointIn, boot_protocol: &'d AtomicBool) -> Self {
+        Self {
+            ep_in,
+            boot_protocol,
+        }
+    }
+
+    /// True if the host has selected the boot protocol; reports must then be in the boot format.
+    pub fn is_boot_protocol(&self) -> bool {
+        self.boot_protocol.load(Ordering::Acquire)
     }
 
     /// Writes `report` to its interrupt endpoint.
@@ -36,17 +45,27 @@ impl<'d, D: Driver<'d>, const N: usize> HidWriter<'d, D, N> {
 pub struct HidReader<'d, D: Driver<'d>, const N: usize> {
     ep_out: D::EndpointOut,
     offset: &'d AtomicUsize,
+    boot_protocol: &'d AtomicBool,
 }
 
 impl<'d, D: Driver<'d>, const N: usize> HidReader<'d, D, N> {
-    pub fn new(ep_out: <D>::EndpointOut, offset: &'d AtomicUsize) -> Self {
-        Self { ep_out, offset }
+    pub fn new(
+        ep_out: <D>::EndpointOut,
+        offset: &'d AtomicUsize,
+        boot_protocol: &'d AtomicBool,
+    ) -> Self {
+        Self {
+            ep_out,
+            offset,
+            boot_protocol,
+        }
     }
 
     /// Delivers output reports from the Interrupt Out pipe to `handler`.
     ///
     /// If `use_report_ids` is true, the first byte of the report will be used as
-    /// the `ReportId` value. Otherwise the `ReportId` value will be 0.
+    /// the `ReportId` value. Otherwise, or while the host has selected the boot
+    /// protocol, the `ReportId` value will be 0.
     pub async fn run<T: RequestHandler>(mut self, use_report_ids: bool, handler: &mut T) -> ! {
         let offset = self.offset.load(Ordering::Acquire);
         assert!(offset == 0);
@@ -54,7 +73,11 @@ impl<'d, D: Driver<'d>, const N: usize> HidReader<'d, D, N> {
         loop {
             match self.read(&mut buf).await {
                 Ok(len) => {
-                    let id = if use_report_ids { buf[0] } else { 0 };
+                    let id = if use_report_ids && !self.boot_protocol.load(Ordering::Acquire) {
+                        buf[0]
+                    } else {
+                        0
+                    };
                     handler.set_report(ReportId::Out(id), &buf[..len]);
                 }
                 Err(ReadError::BufferOverflow) => {
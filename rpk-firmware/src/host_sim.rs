@@ -0,0 +1,219 @@
+//! A clock and USB HID endpoint for running the mapper and key reporter on a host; such as to
+//! simulate a config.
+extern crate std;
+
+use core::{cell::RefCell, task::Waker};
+use embassy_usb::driver::{
+    Bus, ControlPipe, Driver, Endpoint, EndpointAddress, EndpointAllocError, EndpointError,
+    EndpointIn, EndpointInfo, EndpointOut, EndpointType, Event, Unsupported,
+};
+use rpk_common::usb_vendor_message::MAX_BULK_LEN;
+use std::{collections::VecDeque, rc::Rc, time::SystemTime, vec::Vec};
+
+struct HostTimeDriver;
+
+impl embassy_time_driver::Driver for HostTimeDriver {
+    fn now(&self) -> u64 {
+        NOW.with_borrow(|now| {
+            if now.0 == 0 {
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap()
+                    .as_micros() as u64
+            } else {
+                now.0
+            }
+        })
+    }
+
+    fn schedule_wake(&self, at: u64, waker: &Waker) {
+        NOW.with_borrow_mut(|now| {
+            if now.0 != 0 && at > now.0 {
+                now.0 = at + now.1;
+            }
+        });
+
+        waker.wake_by_ref();
+    }
+}
+
+std::thread_local! {
+    static NOW: RefCell<(u64,u64)> = const {RefCell::new((0,0))};
+}
+
+embassy_time_driver::time_driver_impl!(static TIME_DRIVER: HostTimeDriver = HostTimeDriver);
+
+/// Set the time, in microseconds, for this thread. A timer waited on moves the time forward to
+/// when it expires so that it expires straight away. A time of 0 uses the system time.
+pub fn set_time(t: u64) {
+    NOW.with_borrow_mut(|now| now.0 = t);
+}
+
+/// Add `t` microseconds to the time a timer moves the time forward to.
+pub fn set_wait_lag(t: u64) {
+    NOW.with_borrow_mut(|now| now.1 = t);
+}
+
+/// The reports written to a [`HostEndpointIn`] and not yet taken.
+#[derive(Clone, Default)]
+pub struct HostReports(Rc<RefCell<VecDeque<Vec<u8>>>>);
+impl HostReports {
+    /// Take the oldest report.
+    pub fn take(&self) -> Option<Vec<u8>> {
+        self.0.borrow_mut().pop_front()
+    }
+}
+
+/// An interrupt endpoint which queues each report written to it in [`HostReports`].
+pub struct HostEndpointIn {
+    reports: HostReports,
+    info: EndpointInfo,
+}
+impl HostEndpointIn {
+    pub fn new(reports: HostReports) -> Self {
+        Self {
+            reports,
+            info: EndpointInfo {
+                addr: EndpointAddress::from(0x81),
+                ep_type: EndpointType::Interrupt,
+                max_packet_size: MAX_BULK_LEN,
+                interval_ms: 1,
+            },
+        }
+    }
+}
+impl Endpoint for HostEndpointIn {
+    fn info(&self) -> &EndpointInfo {
+        &self.info
+    }
+
+    async fn wait_enabled(&mut self) {}
+}
+impl EndpointIn for HostEndpointIn {
+    async fn write(&mut self, buf: &[u8]) -> Result<(), EndpointError> {
+        self.reports.0.borrow_mut().push_back(Vec::from(buf));
+        Ok(())
+    }
+}
+
+/// The USB driver type of a [`HostEndpointIn`]. There is no USB bus on the host so no value of
+/// this type, or of its other parts, can exist.
+pub enum HostDriver {}
+impl Driver<'_> for HostDriver {
+    type EndpointOut = NoUsb;
+
+    type EndpointIn = HostEndpointIn;
+
+    type ControlPipe = NoUsb;
+
+    type Bus = NoUsb;
+
+    fn alloc_endpoint_out(
+        &mut self,
+        _ep_type: EndpointType,
+        _ep_addr: Option<EndpointAddress>,
+        _max_packet_size: u16,
+        _interval_ms: u8,
+    ) -> Result<Self::EndpointOut, EndpointAllocError> {
+        match *self {}
+    }
+
+    fn alloc_endpoint_in(
+        &mut self,
+        _ep_type: EndpointType,
+        _ep_addr: Option<EndpointAddress>,
+        _max_packet_size: u16,
+        _interval_ms: u8,
+    ) -> Result<Self::EndpointIn, EndpointAllocError> {
+        match *self {}
+    }
+
+    fn start(self, _control_max_packet_size: u16) -> (Self::Bus, Self::ControlPipe) {
+        match self {}
+    }
+}
+
+/// The parts of [`HostDriver`] which a host does not have.
+pub enum NoUsb {}
+impl Endpoint for NoUsb {
+    fn info(&self) -> &EndpointInfo {
+        match *self {}
+    }
+
+    async fn wait_enabled(&mut self) {
+        match *self {}
+    }
+}
+impl EndpointOut for NoUsb {
+    async fn read(&mut self, _buf: &mut [u8]) -> Result<usize, EndpointError> {
+        match *self {}
+    }
+}
+impl Bus for NoUsb {
+    async fn enable(&mut self) {
+        match *self {}
+    }
+
+    async fn disable(&mut self) {
+        match *self {}
+    }
+
+    async fn poll(&mut self) -> Event {
+        match *self {}
+    }
+
+    fn endpoint_set_enabled(&mut self, _ep_addr: EndpointAddress, _enabled: bool) {
+        match *self {}
+    }
+
+    fn endpoint_set_stalled(&mut self, _ep_addr: EndpointAddress, _stalled: bool) {
+        match *self {}
+    }
+
+    fn endpoint_is_stalled(&mut self, _ep_addr: EndpointAddress) -> bool {
+        match *self {}
+    }
+
+    async fn remote_wakeup(&mut self) -> Result<(), Unsupported> {
+        match *self {}
+    }
+}
+impl ControlPipe for NoUsb {
+    fn max_packet_size(&self) -> usize {
+        match *self {}
+    }
+
+    async fn setup(&mut self) -> [u8; 8] {
+        match *self {}
+    }
+
+    async fn data_out(
+        &mut self,
+        _buf: &mut [u8],
+        _first: bool,
+        _last: bool,
+    ) -> Result<usize, EndpointError> {
+        match *self {}
+    }
+
+    async fn data_in(
+        &mut self,
+        _data: &[u8],
+        _first: bool,
+        _last: bool,
+    ) -> Result<(), EndpointError> {
+        match *self {}
+    }
+
+    async fn accept(&mut self) {
+        match *self {}
+    }
+
+    async fn reject(&mut self) {
+        match *self {}
+    }
+
+    async fn accept_set_address(&mut self, _addr: u8) {
+        match *self {}
+    }
+}
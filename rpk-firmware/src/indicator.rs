@@ -0,0 +1,85 @@
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, signal::Signal};
+use embedded_hal::digital::OutputPin;
+
+/// What an indicator pin shows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Indicator {
+    /// On while the layer is in the layer stack.
+    Layer(u16),
+    /// On while the host has the LED on; see [`rpk_common::keycodes::leds`].
+    HostLed(u8),
+    /// On while a oneshot layer is waiting for the next key.
+    Oneshot,
+}
+
+/// The part of the mapper state shown by indicators.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct IndicatorState {
+    /// A bit for each of the first 32 layers in the layer stack.
+    pub layers: u32,
+    pub host_leds: u8,
+    pub oneshot: bool,
+}
+impl IndicatorState {
+    pub fn is_on(&self, indicator: Indicator) -> bool {
+        match indicator {
+            Indicator::Layer(layer) => layer < 32 && self.layers & (1 << layer) != 0,
+            Indicator::HostLed(led) => self.host_leds & led != 0,
+            Indicator::Oneshot => self.oneshot,
+        }
+    }
+}
+
+/// Sends changes of the [`IndicatorState`] from the mapper to the indicator task.
+#[derive(Default)]
+pub struct IndicatorChannel(Signal<NoopRawMutex, IndicatorState>);
+impl IndicatorChannel {
+    pub fn send(&self, state: IndicatorState) {
+        self.0.signal(state);
+    }
+
+    pub async fn receive(&self) -> IndicatorState {
+        self.0.wait().await
+    }
+
+    #[cfg(test)]
+    pub fn try_take(&self) -> Option<IndicatorState> {
+        self.0.try_take()
+    }
+}
+
+/// Drives a pin for each of `indicators`; high when on.
+pub struct Indicators<'c, P: OutputPin, const N: usize> {
+    pins: [P; N],
+    indicators: &'c [Indicator; N],
+}
+impl<'c, P: OutputPin, const N: usize> Indicators<'c, P, N> {
+    pub fn new(pins: [P; N], indicators: &'c [Indicator; N]) -> Self {
+        let mut me = Self { pins, indicators };
+        me.update(&IndicatorState::default());
+        me
+    }
+
+    pub fn update(&mut self, state: &IndicatorState) {
+        for (pin, indicator) in self.pins.iter_mut().zip(self.indicators) {
+            let _ = if state.is_on(*indicator) {
+                pin.set_high()
+            } else {
+                pin.set_low()
+            };
+        }
+    }
+
+    pub async fn run(&mut self, channel: &IndicatorChannel) {
+        loop {
+            let state = channel.receive().await;
+            self.update(&state);
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "indicator_test.rs"]
+mod test;
@@ -0,0 +1,78 @@
+extern crate std;
+
+use embassy_futures::{block_on, select::select, yield_now};
+use rpk_common::keycodes::leds;
+
+use super::*;
+
+use crate::switch_test_stub::Pin;
+
+#[test]
+fn is_on() {
+    let state = IndicatorState {
+        layers: 1 << 5 | 1 << 7,
+        host_leds: leds::CAPS_LOCK,
+        oneshot: false,
+    };
+
+    assert!(state.is_on(Indicator::Layer(7)));
+    assert!(!state.is_on(Indicator::Layer(6)));
+    assert!(!state.is_on(Indicator::Layer(39)));
+    assert!(state.is_on(Indicator::HostLed(leds::CAPS_LOCK)));
+    assert!(!state.is_on(Indicator::HostLed(leds::NUM_LOCK)));
+    assert!(!state.is_on(Indicator::Oneshot));
+}
+
+#[test]
+fn update() {
+    let pins = [Pin::new(1), Pin::new(2), Pin::new(3)];
+    let bindings = [
+        Indicator::Layer(6),
+        Indicator::HostLed(leds::NUM_LOCK),
+        Indicator::Oneshot,
+    ];
+    let mut indicators = Indicators::new(pins.clone(), &bindings);
+
+    let states = || {
+        pins.iter()
+            .map(|p| p.get_state())
+            .collect::<std::vec::Vec<_>>()
+    };
+
+    assert_eq!(states(), [Some(false), Some(false), Some(false)]);
+
+    indicators.update(&IndicatorState {
+        layers: 1 << 6,
+        host_leds: leds::NUM_LOCK | leds::CAPS_LOCK,
+        oneshot: false,
+    });
+    assert_eq!(states(), [Some(true), Some(true), Some(false)]);
+
+    indicators.update(&IndicatorState {
+        layers: 1 << 5,
+        host_leds: 0,
+        oneshot: true,
+    });
+    assert_eq!(states(), [Some(false), Some(false), Some(true)]);
+}
+
+#[test]
+fn run() {
+    let pin = Pin::new(1);
+    let bindings = [Indicator::HostLed(leds::SCROLL_LOCK)];
+    let mut indicators = Indicators::new([pin.clone()], &bindings);
+    let channel = IndicatorChannel::default();
+
+    block_on(async {
+        select(indicators.run(&channel), async {
+            channel.send(IndicatorState {
+                host_leds: leds::SCROLL_LOCK,
+                ..Default::default()
+            });
+            yield_now().await;
+        })
+        .await;
+    });
+
+    assert_eq!(pin.get_state(), Some(true));
+}
@@ -4,6 +4,9 @@ use rpk_common::keycodes::key_range::{MODIFIER_MAX, MODIFIER_MIN};
 
 use crate::{add_key_bit, del_key_bit, hid::HidWriter, mapper::KeyEvent, warn};
 
+/// Reported in every key slot of a boot report when more than six keys are down.
+const ERROR_ROLL_OVER: u8 = 1;
+
 pub struct Reporter<'d, D: Driver<'d>, const DESC_SIZE: usize> {
     hid_writer: HidWriter<'d, D, DESC_SIZE>,
     keyboard_report: [u8; crate::KEY_BITS_SIZE + 2],
@@ -19,18 +22,49 @@ impl<'d, D: Driver<'d>, const DESC_SIZE: usize> Reporter<'d, D, DESC_SIZE> {
         }
     }
 
+    /// Write a report with a report id. These are not sent while the host uses the boot protocol.
     async fn write_report(&mut self, report: &[u8]) {
+        if self.hid_writer.is_boot_protocol() {
+            return;
+        }
         if let Err(e) = self.hid_writer.write(report).await {
             warn!("Failed to send report: {:?}", e);
         }
     }
 
     async fn write_keyboard_report(&mut self) {
-        if let Err(e) = self.hid_writer.write(&self.keyboard_report).await {
+        let result = if self.hid_writer.is_boot_protocol() {
+            let report = self.boot_keyboard_report();
+            self.hid_writer.write(&report).await
+        } else {
+            self.hid_writer.write(&self.keyboard_report).await
+        };
+        if let Err(e) = result {
             warn!("Failed to send report: {:?}", e);
         }
     }
 
+    /// The keyboard report in the boot protocol format: modifiers, reserved then up to six keys.
+    fn boot_keyboard_report(&self) -> [u8; 8] {
+        let mut report = [0; 8];
+        report[0] = self.keyboard_report[1];
+        let mut keys = self.keyboard_report[2..]
+            .iter()
+            .enumerate()
+            .flat_map(|(i, bits)| {
+                (0..8)
+                    .filter(move |b| bits & (1 << b) != 0)
+                    .map(move |b| (i * 8 + b) as u8)
+            });
+        for (slot, key) in report[2..].iter_mut().zip(&mut keys) {
+            *slot = key;
+        }
+        if keys.next().is_some() {
+            report[2..].fill(ERROR_ROLL_OVER);
+        }
+        report
+    }
+
     fn add_modifiers(&mut self, modifiers: u8) {
         self.keyboard_report[1] |= modifiers;
     }
@@ -1,3 +1,5 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use embassy_futures::block_on;
 use embassy_time::{Duration, Instant};
 
@@ -10,10 +12,15 @@ use alloc::vec;
 
 macro_rules! setup {
     ($messages:ident, $rep:ident, $x:tt) => {
+        setup!($messages, $rep, _boot_protocol, $x)
+    };
+    ($messages:ident, $rep:ident, $boot:ident, $x:tt) => {
         block_on(async {
             let ep_in = MyEndpointIn::default();
             let $messages = &ep_in.messages.clone();
-            let hid_writer = HidWriter::<'_, MyDriver, 34>::new(ep_in);
+            let boot_protocol = AtomicBool::new(false);
+            let $boot = &boot_protocol;
+            let hid_writer = HidWriter::<'_, MyDriver, 34>::new(ep_in, &boot_protocol);
             let mut $rep = Reporter::new(hid_writer);
 
             $x
@@ -116,3 +123,52 @@ fn sys_ctl_report() {
         assert_eq!(&messages.get(), &vec![3, 0, 0]);
     });
 }
+
+#[test]
+fn boot_protocol_keyboard_report() {
+    setup!(messages, reporter, boot_protocol, {
+        boot_protocol.store(true, Ordering::Release);
+
+        reporter.report(KeyEvent::Modifiers(2, true)).await;
+        reporter.report(KeyEvent::Basic(5, true)).await;
+        reporter.report(KeyEvent::Basic(4, true)).await;
+        reporter.report(KeyEvent::Basic(5, false)).await;
+
+        assert_eq!(&messages.get(), &vec![2, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&messages.get(), &vec![2, 0, 5, 0, 0, 0, 0, 0]);
+        assert_eq!(&messages.get(), &vec![2, 0, 4, 5, 0, 0, 0, 0]);
+        assert_eq!(&messages.get(), &vec![2, 0, 4, 0, 0, 0, 0, 0]);
+    });
+}
+
+#[test]
+fn boot_protocol_roll_over() {
+    setup!(messages, reporter, boot_protocol, {
+        boot_protocol.store(true, Ordering::Release);
+
+        for k in 4..10 {
+            reporter.report(KeyEvent::Basic(k, true)).await;
+            messages.get();
+        }
+        reporter.report(KeyEvent::Basic(4, false)).await;
+        reporter.report(KeyEvent::Basic(4, true)).await;
+        assert_eq!(&messages.get(), &vec![0, 0, 5, 6, 7, 8, 9, 0]);
+        assert_eq!(&messages.get(), &vec![0, 0, 4, 5, 6, 7, 8, 9]);
+        reporter.report(KeyEvent::Basic(10, true)).await;
+        assert_eq!(&messages.get(), &vec![0, 0, 1, 1, 1, 1, 1, 1]);
+        reporter.report(KeyEvent::Basic(4, false)).await;
+        assert_eq!(&messages.get(), &vec![0, 0, 5, 6, 7, 8, 9, 10]);
+    });
+}
+
+#[test]
+fn boot_protocol_skips_other_reports() {
+    setup!(messages, reporter, boot_protocol, {
+        boot_protocol.store(true, Ordering::Release);
+
+        reporter.report(KeyEvent::Consumer(361)).await;
+        reporter.report(KeyEvent::Basic(4, true)).await;
+
+        assert_eq!(&messages.get(), &vec![0, 0, 4, 0, 0, 0, 0, 0]);
+    });
+}
@@ -5,7 +5,12 @@ use embassy_sync::{blocking_mutex::raw::RawMutex, channel::Channel};
 use embassy_time::{Duration, Instant, Timer};
 use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal_async::digital::Wait;
-use rpk_common::globals;
+use rpk_common::globals::{self, debounce_modes};
+
+use crate::usb::BusState;
+
+pub mod direct_pin;
+pub use direct_pin::DirectPinScanner;
 
 const IDLE_WAIT_COUNT: u32 = 100;
 
@@ -89,6 +94,10 @@ impl<M: RawMutex, const N: usize> KeyScannerChannel<M, N> {
         self.0.receive().await
     }
 
+    pub async fn send(&self, msg: ScanKey) {
+        self.0.send(msg).await;
+    }
+
     pub fn try_send(&self, msg: ScanKey) {
         self.0.try_send(msg).ok();
     }
@@ -143,6 +152,12 @@ pub struct KeyScanner<
     /// The configuarable time to wait for a key to debounce. The format is compressed.
     debounce_ms_atomic: &'c atomic::AtomicU16,
     debounce_ms_prev: u16,
+
+    /// When to report a key change; see [`globals::debounce_modes`].
+    debounce_mode_atomic: &'c atomic::AtomicU8,
+
+    /// While the USB bus is suspended we only wait for a key, which then wakes the host.
+    bus: &'c BusState,
 }
 impl<
     'c,
@@ -159,6 +174,8 @@ impl<
         output_pins: [O; OUTPUT_N],
         channel: &'c KeyScannerChannel<M, PS>,
         debounce_ms_atomic: &'c atomic::AtomicU16,
+        debounce_mode_atomic: &'c atomic::AtomicU8,
+        bus: &'c BusState,
     ) -> Self {
         Self {
             input_pins,
@@ -175,6 +192,8 @@ impl<
             scan_free_time: i32::MIN,
             debounce_ms_atomic,
             debounce_ms_prev: 0,
+            debounce_mode_atomic,
+            bus,
         }
     }
 
@@ -183,9 +202,9 @@ impl<
         loop {
             self.scan::<ROW_IS_OUTPUT>().await;
 
-            // If no key for over IDLE_WAIT_MS, wait for interupt
+            // If no key for over IDLE_WAIT_MS, or the bus is suspended, wait for interupt
             if self.all_up {
-                if self.all_up_limit == 0 {
+                if self.all_up_limit == 0 || self.bus.is_suspended() {
                     self.wait_for_key().await;
                 } else {
                     self.all_up_limit -= 1;
@@ -210,6 +229,7 @@ impl<
             let mut futs: [_; INPUT_N] = core::array::from_fn(|_| futs.next().unwrap());
             let _ = select_slice(pin!(futs.as_mut_slice())).await;
         }
+        self.bus.request_wakeup();
 
         for out in self.output_pins.iter_mut() {
             let _ = out.set_high();
@@ -225,16 +245,13 @@ impl<
         let mut now = Instant::now();
         now += self.half_time_per_output_pin();
 
-        // debounce on, down cleared for compare
-        self.scan_count += 1;
-        if self.scan_count > self.scan_count_max {
-            self.scan_count = 0;
-            self.debounce_count += 1;
-            if self.debounce_count > self.debounce_count_max {
-                self.debounce_count = 0;
-            }
-        }
-        let debounce_count = (self.debounce_count << 2) | 128;
+        let debounce_count = next_debounce_count(
+            &mut self.scan_count,
+            &mut self.debounce_count,
+            self.scan_count_max,
+            self.debounce_count_max,
+        );
+        let mode = self.debounce_mode_atomic.load(atomic::Ordering::Relaxed);
 
         // We will soon sleep if all up
         let mut is_all_up = true;
@@ -249,35 +266,16 @@ impl<
             now += self.time_per_output_pin;
 
             for (input_idx, (ip, s)) in self.input_pins.iter_mut().zip(s.iter_mut()).enumerate() {
-                let settle = *s & !3; // down states cleared for compare
-
                 let key_state = if ip.is_low().unwrap_or(false) { 1 } else { 0 };
-                let mut changed = *s & 1 != key_state;
-                if settle != 0 {
-                    if settle == debounce_count {
-                        // we are now settled; just keep down states
-                        *s &= 3;
-                        changed = matches!(*s, 1 | 2);
-                    } else {
-                        // settling keys need to be polled
-                        is_all_up = false;
-                        if changed {
-                            // restart settle counter
-                            *s = start_debounce(
-                                key_state | *s & 2,
-                                self.debounce_count,
-                                self.debounce_count_max,
-                            );
-                            continue;
-                        }
-                    }
-                } else if changed {
-                    is_all_up = false;
-                    *s =
-                        start_debounce(key_state * 3, self.debounce_count, self.debounce_count_max);
-                }
-
-                if key_state == 1 {
+                let (changed, busy) = debounce_key(
+                    s,
+                    key_state,
+                    debounce_count,
+                    self.debounce_count,
+                    self.debounce_count_max,
+                    mode,
+                );
+                if busy {
                     is_all_up = false;
                 }
 
@@ -287,6 +285,9 @@ impl<
                     } else {
                         ScanKey::new(input_idx as u8, output_idx as u8, key_state == 1)
                     };
+                    if key_state == 1 {
+                        self.bus.request_wakeup();
+                    }
                     self.channel.0.send(skey).await;
                 }
             }
@@ -344,25 +345,95 @@ impl<
         let m16 = self.debounce_ms_atomic.load(atomic::Ordering::Relaxed);
         if changed || m16 != self.debounce_ms_prev {
             self.debounce_ms_prev = m16;
-            let dt = Duration::from_micros(globals::key_settle_time_uncompress(m16 as u32) as u64)
-                .as_ticks() as u32;
-
-            let dcycles = max(
-                1,
-                dt / (OUTPUT_N as u32 * self.time_per_output_pin.as_ticks() as u32),
+            (self.debounce_count_max, self.scan_count_max) = debounce_cycles(
+                m16,
+                OUTPUT_N as u32 * self.time_per_output_pin.as_ticks() as u32,
             );
-            let dtmag = dcycles.ilog2();
-            if dtmag > 5 {
-                self.debounce_count_max = 31;
-                self.scan_count_max = (dcycles >> 5) as u16 - 1;
-            } else {
-                self.debounce_count_max = (1 << dtmag) - 1;
-                self.scan_count_max = 0;
-            }
         }
     }
 }
 
+/// The `(debounce_count_max, scan_count_max)` for the compressed debounce time `m16` when a scan
+/// takes `scan_ticks`.
+fn debounce_cycles(m16: u16, scan_ticks: u32) -> (u8, u16) {
+    let dt = Duration::from_micros(globals::key_settle_time_uncompress(m16 as u32) as u64)
+        .as_ticks() as u32;
+
+    let dcycles = max(1, dt / scan_ticks);
+    let dtmag = dcycles.ilog2();
+    if dtmag > 5 {
+        (31, (dcycles >> 5) as u16 - 1)
+    } else {
+        ((1 << dtmag) - 1, 0)
+    }
+}
+
+/// Advance the debounce counters for a new scan. Returns the settle state a debouncing key is
+/// settled at.
+#[inline]
+fn next_debounce_count(
+    scan_count: &mut u16,
+    debounce_count: &mut u8,
+    scan_count_max: u16,
+    debounce_count_max: u8,
+) -> u8 {
+    // debounce on, down cleared for compare
+    *scan_count += 1;
+    if *scan_count > scan_count_max {
+        *scan_count = 0;
+        *debounce_count += 1;
+        if *debounce_count > debounce_count_max {
+            *debounce_count = 0;
+        }
+    }
+    (*debounce_count << 2) | 128
+}
+
+/// Update the debounce state `s` of a key given its switch position `key_state` (1 is down) and
+/// the debounce `mode`. Returns `(changed, busy)`: `changed` when the key change should be reported
+/// and `busy` when the key is down or settling and needs to be polled.
+#[inline]
+fn debounce_key(
+    s: &mut u8,
+    key_state: u8,
+    settled: u8,
+    debounce_count: u8,
+    debounce_count_max: u8,
+    mode: u8,
+) -> (bool, bool) {
+    let settle = *s & !3; // down states cleared for compare
+    let changed = *s & 1 != key_state;
+    if settle != 0 {
+        if settle == settled {
+            // we are now settled; just keep down states
+            *s &= 3;
+            return (matches!(*s, 1 | 2), key_state == 1);
+        }
+        // settling keys need to be polled
+        if changed {
+            // restart settle counter
+            *s = start_debounce(key_state | *s & 2, debounce_count, debounce_count_max);
+        }
+        return (false, true);
+    }
+    if changed {
+        let eager = match mode as u16 {
+            debounce_modes::DEFER => false,
+            debounce_modes::ASYMMETRIC => key_state == 1,
+            _ => true,
+        };
+        // a deferred change is reported once settled as the reported position is still the old one
+        let reported = if eager { key_state } else { key_state ^ 1 };
+        *s = start_debounce(
+            key_state | reported << 1,
+            debounce_count,
+            debounce_count_max,
+        );
+        return (eager, true);
+    }
+    (false, key_state == 1)
+}
+
 #[inline]
 fn start_debounce(key_state: u8, debounce_count: u8, debounce_count_max: u8) -> u8 {
     key_state
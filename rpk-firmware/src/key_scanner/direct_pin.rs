@@ -0,0 +1,162 @@
+use core::{pin::pin, sync::atomic};
+
+use embassy_futures::select::select_slice;
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_time::{Duration, Instant, Timer};
+use embedded_hal::digital::InputPin;
+use embedded_hal_async::digital::Wait;
+
+use super::{
+    IDLE_WAIT_COUNT, KeyScannerChannel, ScanKey, debounce_cycles, debounce_key, next_debounce_count,
+};
+use crate::usb::BusState;
+
+/// Time between reads of the pins.
+const SCAN_INTERVAL: Duration = Duration::from_micros(250);
+
+/// Scans switches wired straight from a pin to ground; one pin per key. Each key is reported as
+/// row 0 with the index of its pin as the column.
+pub struct DirectPinScanner<'c, I: InputPin + Wait, M: RawMutex, const N: usize, const PS: usize> {
+    channel: &'c KeyScannerChannel<M, PS>,
+
+    pins: [I; N],
+
+    /// The debounce state of each key; see [`super::KeyScanner`].
+    state: [u8; N],
+
+    /// are all keys up?
+    all_up: bool,
+
+    /// A countdown of scans when all keys are up before calling `wait_for_key`.
+    all_up_limit: u32,
+
+    scan_count: u16,
+    debounce_count: u8,
+    debounce_count_max: u8,
+    scan_count_max: u16,
+
+    debounce_ms_atomic: &'c atomic::AtomicU16,
+    debounce_ms_prev: u16,
+    debounce_mode_atomic: &'c atomic::AtomicU8,
+
+    bus: &'c BusState,
+}
+impl<'c, I: InputPin + Wait, M: RawMutex, const N: usize, const PS: usize>
+    DirectPinScanner<'c, I, M, N, PS>
+{
+    pub fn new(
+        pins: [I; N],
+        channel: &'c KeyScannerChannel<M, PS>,
+        debounce_ms_atomic: &'c atomic::AtomicU16,
+        debounce_mode_atomic: &'c atomic::AtomicU8,
+        bus: &'c BusState,
+    ) -> Self {
+        let debounce_ms_prev = debounce_ms_atomic.load(atomic::Ordering::Relaxed);
+        let (debounce_count_max, scan_count_max) =
+            debounce_cycles(debounce_ms_prev, SCAN_INTERVAL.as_ticks() as u32);
+        Self {
+            channel,
+            pins,
+            state: [0; N],
+            all_up: false,
+            all_up_limit: 0,
+            scan_count: 0,
+            debounce_count: 0,
+            debounce_count_max,
+            scan_count_max,
+            debounce_ms_atomic,
+            debounce_ms_prev,
+            debounce_mode_atomic,
+            bus,
+        }
+    }
+
+    pub async fn run(&mut self) {
+        self.wait_for_key().await;
+        loop {
+            self.scan().await;
+
+            // If no key for over IDLE_WAIT_MS, or the bus is suspended, wait for interupt
+            if self.all_up {
+                if self.all_up_limit == 0 || self.bus.is_suspended() {
+                    self.wait_for_key().await;
+                } else {
+                    self.all_up_limit -= 1;
+                }
+            }
+        }
+    }
+
+    pub async fn wait_for_key(&mut self) {
+        self.calc_debounce_cycle();
+        self.all_up = false;
+
+        {
+            let mut futs = self.pins.iter_mut().map(|pin| pin.wait_for_low());
+            let mut futs: [_; N] = core::array::from_fn(|_| futs.next().unwrap());
+            let _ = select_slice(pin!(futs.as_mut_slice())).await;
+        }
+        self.bus.request_wakeup();
+    }
+
+    pub async fn scan(&mut self) {
+        let next = Instant::now() + SCAN_INTERVAL;
+        let settled = next_debounce_count(
+            &mut self.scan_count,
+            &mut self.debounce_count,
+            self.scan_count_max,
+            self.debounce_count_max,
+        );
+        let mode = self.debounce_mode_atomic.load(atomic::Ordering::Relaxed);
+
+        // We will soon sleep if all up
+        let mut is_all_up = true;
+        for (idx, (pin, s)) in self.pins.iter_mut().zip(self.state.iter_mut()).enumerate() {
+            let key_state = if pin.is_low().unwrap_or(false) { 1 } else { 0 };
+            let (changed, busy) = debounce_key(
+                s,
+                key_state,
+                settled,
+                self.debounce_count,
+                self.debounce_count_max,
+                mode,
+            );
+            if busy {
+                is_all_up = false;
+            }
+
+            if changed {
+                if key_state == 1 {
+                    self.bus.request_wakeup();
+                }
+                self.channel
+                    .send(ScanKey::new(0, idx as u8, key_state == 1))
+                    .await;
+            }
+        }
+
+        Timer::at(next).await;
+
+        if is_all_up {
+            if !self.all_up {
+                self.all_up = true;
+                self.all_up_limit = IDLE_WAIT_COUNT;
+            }
+        } else {
+            self.all_up = false;
+        }
+    }
+
+    fn calc_debounce_cycle(&mut self) {
+        let m16 = self.debounce_ms_atomic.load(atomic::Ordering::Relaxed);
+        if m16 != self.debounce_ms_prev {
+            self.debounce_ms_prev = m16;
+            (self.debounce_count_max, self.scan_count_max) =
+                debounce_cycles(m16, SCAN_INTERVAL.as_ticks() as u32);
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "direct_pin_test.rs"]
+mod test;
@@ -0,0 +1,97 @@
+extern crate std;
+
+use embassy_futures::{
+    block_on,
+    join::join,
+    select::{Either, select},
+    yield_now,
+};
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embedded_hal::digital::OutputPin;
+
+use super::*;
+
+use crate::switch_test_stub::Pin;
+use crate::time_driver_test_stub::set_time;
+
+fn from_ms(ms: u64) -> u16 {
+    ((ms * 1000) << 10).div_ceil(39063) as u16
+}
+
+macro_rules! setup {
+    ($pins:ident, $channel:ident, $scanner:ident: $debounce_ms:literal $b:block) => {
+        block_on(async move {
+            let $pins = [Pin::new(1), Pin::new(2), Pin::new(3)];
+            for p in $pins.iter() {
+                p.clone().set_high().ok();
+            }
+
+            let $channel = KeyScannerChannel::<NoopRawMutex, 16>::default();
+            let debounce_ms_atomic = atomic::AtomicU16::new(from_ms($debounce_ms));
+            let debounce_mode_atomic = atomic::AtomicU8::new(0);
+            let bus = BusState::default();
+            #[allow(unused_mut)]
+            let mut $scanner = DirectPinScanner::new(
+                $pins.clone(),
+                &$channel,
+                &debounce_ms_atomic,
+                &debounce_mode_atomic,
+                &bus,
+            );
+            set_time(1000);
+
+            $b
+        })
+    };
+}
+
+#[test]
+fn debounce() {
+    setup!(pins, channel, scanner: 5 {
+        assert_eq!(scanner.debounce_count_max, 15);
+        assert_eq!(scanner.scan_count_max, 0);
+
+        scanner.scan().await;
+        assert!(scanner.all_up);
+
+        pins[1].clone().set_low().ok();
+        scanner.scan().await;
+        assert_eq!(channel.0.try_receive().unwrap(), ScanKey::new(0, 1, true));
+        assert!(!scanner.all_up);
+
+        // bounce while settling
+        pins[1].clone().set_high().ok();
+        scanner.scan().await;
+        pins[1].clone().set_low().ok();
+        scanner.scan().await;
+        assert!(channel.0.try_receive().is_err());
+
+        pins[1].clone().set_high().ok();
+        for _ in 0..40 {
+            scanner.scan().await;
+        }
+        assert_eq!(channel.0.try_receive().unwrap(), ScanKey::new(0, 1, false));
+        assert!(channel.0.try_receive().is_err());
+        assert!(scanner.all_up);
+    });
+}
+
+#[test]
+fn wait_for_key() {
+    setup!(pins, channel, scanner: 5 {
+        set_time(0);
+        let press = async {
+            yield_now().await;
+            pins[2].clone().set_low().ok();
+        };
+        let ans = select(
+            join(scanner.wait_for_key(), press),
+            embassy_time::Timer::after_millis(20),
+        )
+        .await;
+        assert!(matches!(ans, Either::First(_)));
+
+        scanner.scan().await;
+        assert_eq!(channel.0.try_receive().unwrap(), ScanKey::new(0, 2, true));
+    });
+}
@@ -11,6 +11,8 @@ use super::*;
 
 use crate::switch_test_stub::{KeyMatrix, Pin};
 use crate::time_driver_test_stub::{self, set_time, set_wait_lag};
+use crate::usb::BusHandler;
+use embassy_usb::Handler;
 
 const TIME_PER_OUTPUT_PIN: u64 = 32;
 
@@ -20,6 +22,9 @@ fn from_ms(ms: u64) -> u16 {
 
 macro_rules! setup {
     ($scan:ident, $km:ident, $channel:ident, $scanner:ident: $debounce_ms:literal $b:block) => {
+        setup!($scan, $km, $channel, _bus, $scanner: $debounce_ms $b)
+    };
+    ($scan:ident, $km:ident, $channel:ident, $bus:ident, $scanner:ident: $debounce_ms:literal $b:block) => {
         block_on(async move {
             let mut p1 = Pin::new(1);
             let p2 = Pin::new(2);
@@ -33,7 +38,16 @@ macro_rules! setup {
 
             let $channel = KeyScannerChannel::<NoopRawMutex, 16>::default();
             let debounce_ms_atomic = atomic::AtomicU16::new(from_ms($debounce_ms));
-            let mut $scanner = KeyScanner::new(inputs, outputs, &$channel, &debounce_ms_atomic);
+            let debounce_mode_atomic = atomic::AtomicU8::new(0);
+            let $bus = BusState::default();
+            let mut $scanner = KeyScanner::new(
+                inputs,
+                outputs,
+                &$channel,
+                &debounce_ms_atomic,
+                &debounce_mode_atomic,
+                &$bus,
+            );
             #[allow(unused_mut)]
             let mut now = 1000;
             time_driver_test_stub::set_time(now);
@@ -223,6 +237,72 @@ fn debounce() {
     });
 }
 
+#[test]
+fn debounce_defer() {
+    setup!(scan, km, channel, scanner: 25 {
+        scanner
+            .debounce_mode_atomic
+            .store(debounce_modes::DEFER as u8, atomic::Ordering::Relaxed);
+        scanner.debounce_count_max = 31;
+        scanner.scan_count_max = 3;
+
+        km.down(0, 1);
+        scan!(1);
+        km.up(0, 1);
+        scan!(1);
+        km.down(0, 1);
+        scan!(120);
+        assert!(channel.0.try_receive().is_err());
+
+        scan!(10, 1);
+        assert_eq!(channel.0.try_receive().unwrap(), ScanKey::new(1, 0, true));
+
+        // a bounce that returns to the reported position is not reported
+        km.up(0, 1);
+        scan!(1);
+        km.down(0, 1);
+        scan!(130, 3);
+        assert!(channel.0.try_receive().is_err());
+
+        km.up(0, 1);
+        scan!(120);
+        assert!(channel.0.try_receive().is_err());
+        scan!(10, 2);
+        assert_eq!(channel.0.try_receive().unwrap(), ScanKey::new(1, 0, false));
+    });
+}
+
+#[test]
+fn debounce_asymmetric() {
+    setup!(scan, km, channel, scanner: 25 {
+        scanner
+            .debounce_mode_atomic
+            .store(debounce_modes::ASYMMETRIC as u8, atomic::Ordering::Relaxed);
+        scanner.debounce_count_max = 31;
+        scanner.scan_count_max = 3;
+
+        km.down(0, 1);
+        scan!(1);
+        assert_eq!(channel.0.try_receive().unwrap(), ScanKey::new(1, 0, true));
+        scan!(130, 3);
+
+        km.up(0, 1);
+        scan!(1);
+        assert!(channel.0.try_receive().is_err());
+        km.down(0, 1);
+        scan!(1);
+        km.up(0, 1);
+        scan!(120);
+        assert!(channel.0.try_receive().is_err());
+        scan!(10, 2);
+        assert_eq!(channel.0.try_receive().unwrap(), ScanKey::new(1, 0, false));
+
+        km.down(0, 1);
+        scan!(1);
+        assert_eq!(channel.0.try_receive().unwrap(), ScanKey::new(1, 0, true));
+    });
+}
+
 #[test]
 fn wait_for_key() {
     setup!(_pscan, km, channel, scanner: 5 {
@@ -254,3 +334,27 @@ fn wait_for_key() {
         assert!(!scanner.all_up);
     });
 }
+
+#[test]
+fn wakeup_when_suspended() {
+    setup!(scan, km, channel, bus, scanner: 5 {
+        let mut handler = BusHandler::new(&bus);
+
+        km.down(0, 1);
+        scan!(1);
+        assert_eq!(channel.0.try_receive().unwrap(), ScanKey::new(1, 0, true));
+        assert!(!bus.try_take_wakeup());
+
+        km.up(0, 1);
+        scan!(50);
+        assert_eq!(channel.0.try_receive().unwrap(), ScanKey::new(1, 0, false));
+
+        handler.remote_wakeup_enabled(true);
+        handler.suspended(true);
+
+        km.down(0, 2);
+        scan!(1);
+        assert_eq!(channel.0.try_receive().unwrap(), ScanKey::new(2, 0, true));
+        assert!(bus.try_take_wakeup());
+    });
+}
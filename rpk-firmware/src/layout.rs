@@ -1,11 +1,14 @@
 use rpk_common::{
+    PROTOCOL_VERSION,
     globals::{self, COMPOSITE_BIT, COMPOSITE_PART_BIT},
-    keycodes::key_range::{self, LAYER_MAX, LAYER_MIN, MACROS_MAX, MACROS_MIN},
+    keycodes::{
+        key_range::{self, LAYER_MAX, LAYER_MIN, MACROS_MAX, MACROS_MIN},
+        macro_types,
+    },
     mouse::{MouseAnalogSetting, MouseConfig},
-    PROTOCOL_VERSION,
 };
 
-use crate::mapper::{macros::Macro, KeyPlusMod};
+use crate::mapper::{KeyPlusMod, TimedScanKey, combo, macros::Macro};
 
 pub const MAIN_BASE: u16 = 5;
 
@@ -19,9 +22,17 @@ pub struct Manager<const ROWS: usize, const COLS: usize, const CODE_SIZE: usize>
     layout_top: usize,
     composite_start_index: usize,
     macro_dir_base: usize,
+    led_layers_start: u16,
+    hands_start: u16,
+    caps_word_start: u16,
+    leader_start: u16,
+    combo_start: u16,
+    combo_end: u16,
     memo_bottom: usize,
     memo_top: usize,
     macro_stack: usize,
+    memo_high_water: usize,
+    macro_stack_high_water: usize,
     active_comp_count: u32,
     active_comp_part_layers: u32,
 }
@@ -46,6 +57,12 @@ impl Default for Globals {
                 globals::DUAL_ACTION_TIMEOUT2_DEFAULT,
                 globals::DEBOUNCE_SETTLE_TIME_DEFAULT,
                 globals::TAPDANCE_TAP_TIMEOUT_DEFAULT,
+                globals::COMBO_TIMEOUT_DEFAULT,
+                globals::LEADER_TIMEOUT_DEFAULT,
+                globals::CAPS_WORD_TIMEOUT_DEFAULT,
+                globals::DUAL_ACTION_MODE_DEFAULT,
+                globals::DUAL_ACTION_REQUIRE_PRIOR_IDLE_DEFAULT,
+                globals::DEBOUNCE_MODE_DEFAULT,
             ],
         }
     }
@@ -94,9 +111,17 @@ impl<const ROWS: usize, const COLS: usize, const LAYOUT_MAX: usize> Default
             layout_top: 0,
             composite_start_index: 0,
             macro_dir_base: 0,
+            led_layers_start: 0,
+            hands_start: 0,
+            caps_word_start: 0,
+            leader_start: 0,
+            combo_start: 0,
+            combo_end: 0,
             memo_bottom: 0,
             memo_top: 0,
             macro_stack: 0,
+            memo_high_water: 0,
+            macro_stack_high_water: 0,
             active_comp_count: 0,
             active_comp_part_layers: 0,
         }
@@ -205,6 +230,18 @@ impl<const ROWS: usize, const COLS: usize, const LAYOUT_MAX: usize>
 
         self.macro_dir_base = layer_count as usize;
 
+        // LED layers, hands, caps word keys, leader sequences then combos are stored after all
+        // the other macros
+        self.combo_end = macros_count;
+        self.combo_start = self.trailing_macros_start(macros_count, macro_types::COMBO);
+        self.leader_start =
+            self.trailing_macros_start(self.combo_start, macro_types::LEADER_SEQUENCE);
+        self.caps_word_start =
+            self.trailing_macros_start(self.leader_start, macro_types::CAPS_WORD_KEYS);
+        self.hands_start = self.trailing_macros_start(self.caps_word_start, macro_types::HANDS);
+        self.led_layers_start =
+            self.trailing_macros_start(self.hands_start, macro_types::LED_LAYERS);
+
         self.layout_bottom = i;
         self.clear_all();
 
@@ -266,18 +303,124 @@ impl<const ROWS: usize, const COLS: usize, const LAYOUT_MAX: usize>
     }
 
     pub fn get_macro(&self, id: u16) -> Macro {
+        match self.macro_data(id) {
+            Some((s, data)) => Macro::decode(s, Some(data)),
+            None => Macro::Noop,
+        }
+    }
+
+    fn macro_data(&self, id: u16) -> Option<(usize, &[u16])> {
         let idx = id as usize + self.macro_dir_base;
         if idx + 1 >= self.mapping.len() {
-            return Macro::Noop;
+            return None;
         }
 
         let s = self.mapping[idx] as usize;
         let e = self.mapping[idx + 1] as usize;
         if e < s || e > self.mapping.len() {
-            return Macro::Noop;
+            return None;
+        }
+
+        self.mapping.get(s..e).map(|data| (s, data))
+    }
+
+    fn trailing_macros_start(&self, end: u16, macro_type: u16) -> u16 {
+        let mut start = end;
+        while start > 0
+            && self
+                .macro_data(start - 1)
+                .is_some_and(|(_, data)| !data.is_empty() && data[0] == macro_type)
+        {
+            start -= 1;
+        }
+        start
+    }
+
+    /// The extra keycodes, besides the defaults, that continue a caps word.
+    pub(crate) fn caps_word_keys(&self) -> &[u16] {
+        if self.caps_word_start < self.leader_start
+            && let Some((_, data)) = self.macro_data(self.caps_word_start)
+        {
+            &data[1..]
+        } else {
+            &[]
         }
+    }
 
-        Macro::decode(s, self.mapping.get(s..e))
+    /// The hand of the key at `row`, `column`; see [`globals::dual_action_modes::hands`].
+    pub(crate) fn hand(&self, row: usize, column: usize) -> u8 {
+        if self.hands_start < self.caps_word_start
+            && let Some((_, data)) = self.macro_data(self.hands_start)
+        {
+            let i = row * COLS + column;
+            data.get(1 + i / 8)
+                .map(|w| ((w >> ((i % 8) * 2)) & 3) as u8)
+                .unwrap_or(globals::dual_action_modes::hands::NONE)
+        } else {
+            globals::dual_action_modes::hands::NONE
+        }
+    }
+
+    /// The layers to turn on while a host LED is on, as pairs of LED bit and layer.
+    pub(crate) fn led_layers(&self) -> &[u16] {
+        if self.led_layers_start < self.hands_start
+            && let Some((_, data)) = self.macro_data(self.led_layers_start)
+        {
+            &data[1..]
+        } else {
+            &[]
+        }
+    }
+
+    /// Search the leader sequences for those starting with `keys`. Returns the action of the
+    /// sequence which is exactly `keys` (if any) and whether a longer sequence could still match.
+    pub(crate) fn find_leader_sequence(&self, keys: &[u16]) -> (Option<u16>, bool) {
+        let mut exact = None;
+        let mut partial = false;
+        for id in self.leader_start..self.combo_start {
+            let Some((_, data)) = self.macro_data(id) else {
+                continue;
+            };
+            if data.len() < 2 {
+                continue;
+            }
+            let sequence = &data[2..];
+            if sequence.starts_with(keys) {
+                if sequence.len() == keys.len() {
+                    exact = Some(data[1]);
+                } else {
+                    partial = true;
+                }
+            }
+        }
+        (exact, partial)
+    }
+
+    /// Search the combos for those containing all of `keys`. Returns the action of the combo
+    /// which has exactly `keys` (if any) and whether a combo with more keys could still match.
+    pub(crate) fn find_combo(&self, keys: &[TimedScanKey]) -> (Option<u16>, bool) {
+        let mut exact = None;
+        let mut partial = false;
+        for id in self.combo_start..self.combo_end {
+            let Some((_, data)) = self.macro_data(id) else {
+                continue;
+            };
+            if data.len() < 2 {
+                continue;
+            }
+            let combo_keys = &data[2..];
+            if keys
+                .iter()
+                .all(|k| combo_keys.contains(&combo::position(k.0)))
+            {
+                if combo_keys.len() == keys.len() {
+                    exact = Some(data[1]);
+                } else {
+                    partial = true;
+                }
+            }
+        }
+        (exact, partial)
     }
 
     pub fn get_layer(&self, layer_num: u16) -> Option<Layer<'_, ROWS, COLS>> {
@@ -303,6 +446,25 @@ impl<const ROWS: usize, const COLS: usize, const LAYOUT_MAX: usize>
         self.macro_stack
     }
 
+    /// The most words used by the memo stack and the macro stack.
+    pub fn high_water_marks(&self) -> (usize, usize) {
+        (self.memo_high_water, self.macro_stack_high_water)
+    }
+
+    /// The layers in the layer stack; including the base layout.
+    pub fn active_layers(&self) -> impl Iterator<Item = u16> + '_ {
+        self.mapping[self.layout_bottom..self.layout_top]
+            .iter()
+            .map(|l| *l & 0xff)
+    }
+
+    /// A bit for each of the first 32 layers in the layer stack.
+    pub fn active_layer_bits(&self) -> u32 {
+        self.active_layers()
+            .filter(|l| *l < 32)
+            .fold(0, |bits, l| bits | 1 << l)
+    }
+
     pub fn set_layout(&mut self, layer_num: u16) {
         self.mapping[self.layout_bottom] = layer_num;
     }
@@ -428,6 +590,9 @@ impl<const ROWS: usize, const COLS: usize, const LAYOUT_MAX: usize>
         self.defrag_stack();
         let (mac, len) = mac.push(&mut self.mapping[self.layout_top..self.macro_stack]);
         self.macro_stack -= len;
+        self.macro_stack_high_water = self
+            .macro_stack_high_water
+            .max(self.memo_bottom - self.macro_stack);
         mac
     }
 
@@ -456,6 +621,7 @@ impl<const ROWS: usize, const COLS: usize, const LAYOUT_MAX: usize>
             self.mapping[self.macro_stack..self.memo_bottom - 1].copy_from_slice(memo);
             self.mapping[self.memo_bottom - 1] = memo.len() as u16;
             self.memo_bottom = self.macro_stack;
+            self.memo_high_water = self.memo_high_water.max(self.memo_top - self.memo_bottom);
             true
         }
     }
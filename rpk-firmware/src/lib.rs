@@ -1,17 +1,22 @@
 #![no_std]
 pub mod config;
+pub mod encoder;
 pub mod firmware_functions;
 pub mod hid;
+pub mod indicator;
 pub mod key_reporter;
 pub mod key_scanner;
 pub mod layout;
 pub mod mapper;
 pub mod norflash_ring_fs;
 pub mod ring_fs;
+pub mod split;
 pub mod usb;
 
 #[cfg(feature = "test-utils")]
 pub mod flash_test_stub;
+#[cfg(feature = "host-sim")]
+pub mod host_sim;
 #[cfg(feature = "test-utils")]
 pub mod switch_test_stub;
 #[cfg(feature = "test-utils")]
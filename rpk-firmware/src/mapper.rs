@@ -1,7 +1,10 @@
 use core::{cell::RefCell, cmp::min, sync::atomic};
 
+use caps_word::CapsWord;
+use combo::Combo;
 use dual_action::DualActionTimer;
-use embassy_futures::select::{Either, select};
+use dyn_macro::DynMacros;
+use embassy_futures::select::{Either, Either3, select, select3};
 use embassy_sync::{
     blocking_mutex::raw::{CriticalSectionRawMutex, NoopRawMutex, RawMutex},
     channel::Channel,
@@ -9,23 +12,34 @@ use embassy_sync::{
     signal::Signal,
 };
 use embassy_time::{Instant, Timer};
+use leader::Leader;
 use macros::{Macro, TapDance};
 use mouse::Mouse;
-use rpk_common::{globals, keycodes::key_range};
+use rpk_common::{
+    globals,
+    keycodes::{dyn_macro as dyn_macro_items, key_range},
+};
+use stats::Stats;
 
 use crate::{
     firmware_functions,
+    indicator::{IndicatorChannel, IndicatorState},
     key_scanner::{KeyScannerChannel, ScanKey},
     layout,
 };
 
 pub mod config_loader;
 
+pub(crate) mod caps_word;
+pub(crate) mod combo;
 pub(crate) mod dual_action;
+pub(crate) mod dyn_macro;
+pub(crate) mod leader;
 pub(crate) mod macros;
 pub(crate) mod mouse;
+pub mod stats;
 
-pub type KeyScanLog = Channel<CriticalSectionRawMutex, ScanKey, 5>;
+pub type KeyScanLog = Channel<CriticalSectionRawMutex, TimedScanKey, 5>;
 
 pub static KEY_SCAN_LOGGER: LazyLock<KeyScanLog> = LazyLock::new(KeyScanLog::new);
 
@@ -78,7 +92,7 @@ enum Oneshot {
     Ready(u16),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum KeyEvent {
     Basic(u8, bool),
@@ -129,7 +143,21 @@ pub enum ControlMessage {
     TimerExpired,
     Exit,
     LogKeys(bool),
+    SaveDynMacro(u8),
+    LoadDynMacro(u8),
+}
+/// What [`Mapper::pending_work`] did.
+enum Pending {
+    /// Nothing is left to do until the next event.
+    Idle,
+    /// More work may be waiting.
+    Busy,
+    /// A recorded dynamic macro needs saving.
+    SaveDynMacro(u8),
+    /// A dynamic macro needs loading to be played.
+    LoadDynMacro(u8),
 }
+
 #[derive(Default)]
 pub struct ControlSignal(Signal<NoopRawMutex, ControlMessage>);
 impl ControlSignal {
@@ -147,6 +175,19 @@ impl ControlSignal {
     }
 }
 
+/// The state of the keyboard LEDs last set by the host; see [`rpk_common::keycodes::leds`].
+#[derive(Default)]
+pub struct HostLeds(Signal<NoopRawMutex, u8>);
+impl HostLeds {
+    pub fn set(&self, leds: u8) {
+        self.0.signal(leds);
+    }
+
+    pub async fn wait(&self) -> u8 {
+        self.0.wait().await
+    }
+}
+
 pub struct MapperTimer {
     expires_at: RefCell<Instant>,
     at_sig: Signal<NoopRawMutex, Instant>,
@@ -206,12 +247,16 @@ impl MapperTimer {
 pub struct MapperChannel<M: RawMutex, const N: usize> {
     key_event: Channel<M, KeyEvent, N>,
     timer: MapperTimer,
+    host_leds: HostLeds,
+    indicators: IndicatorChannel,
 }
 impl<M: RawMutex, const N: usize> Default for MapperChannel<M, N> {
     fn default() -> Self {
         Self {
             key_event: Channel::new(),
             timer: MapperTimer::default(),
+            host_leds: HostLeds::default(),
+            indicators: IndicatorChannel::default(),
         }
     }
 }
@@ -220,6 +265,10 @@ impl<M: RawMutex, const N: usize> MapperChannel<M, N> {
         self.key_event.receive().await
     }
 
+    pub fn try_receive(&self) -> Option<KeyEvent> {
+        self.key_event.try_receive().ok()
+    }
+
     pub fn timer(&self) -> &MapperTimer {
         &self.timer
     }
@@ -232,10 +281,23 @@ impl<M: RawMutex, const N: usize> MapperChannel<M, N> {
         &self.timer.ctl_sig
     }
 
-    fn report(&self, message: KeyEvent) {
+    pub fn host_leds(&self) -> &HostLeds {
+        &self.host_leds
+    }
+
+    pub fn indicators(&self) -> &IndicatorChannel {
+        &self.indicators
+    }
+
+    /// Queue `message` for the reporter. Returns false if the queue overflowed in which case the
+    /// queue is replaced with a `KeyEvent::Clear`.
+    fn report(&self, message: KeyEvent) -> bool {
         if self.key_event.try_send(message).is_err() {
             self.clear_reports();
             let _ = self.key_event.try_send(KeyEvent::Clear);
+            false
+        } else {
+            true
         }
     }
 
@@ -283,14 +345,25 @@ pub struct Mapper<
     oneshot: Oneshot,
     dual_action: DualActionTimer,
     tapdance: TapDance,
+    combo: Combo,
+    leader: Leader,
+    caps_word: CapsWord,
+    dyn_macros: DynMacros,
+    key_logger: Option<&'static KeyScanLog>,
     last_scan_key: TimedScanKey,
+    prior_scan_time: u64,
     macro_running: Macro,
     memo_count: usize,
     now: u64,
     report_count: u16,
     debounce_ms_atomic: &'c atomic::AtomicU16,
+    debounce_mode_atomic: &'c atomic::AtomicU8,
+    stats: &'c Stats,
+    stats_time: u64,
     pending_down_modifiers: u8,
     pending_up_modifiers: u8,
+    host_leds: u8,
+    indicator_state: IndicatorState,
 }
 impl<
     'c,
@@ -305,8 +378,11 @@ impl<
     pub fn new(
         report_channel: &'c MapperChannel<M, REPORT_BUFFER_SIZE>,
         debounce_ms_atomic: &'c atomic::AtomicU16,
+        debounce_mode_atomic: &'c atomic::AtomicU8,
+        stats: &'c Stats,
     ) -> Self {
         assert!(Self::OKAY);
+        stats.set_matrix_size(ROW_COUNT, COL_COUNT);
         Self {
             layout: layout::Manager::default(),
             active_actions: [[KeyPlusMod::none(); COL_COUNT]; ROW_COUNT],
@@ -317,14 +393,25 @@ impl<
             oneshot: Oneshot::None,
             dual_action: Default::default(),
             tapdance: Default::default(),
+            combo: Default::default(),
+            leader: Default::default(),
+            caps_word: Default::default(),
+            dyn_macros: Default::default(),
+            key_logger: None,
             last_scan_key: TimedScanKey::none(),
+            prior_scan_time: 0,
             macro_running: Macro::Noop,
             memo_count: 0,
             now: 1,
             report_count: 0,
             debounce_ms_atomic,
+            debounce_mode_atomic,
+            stats,
+            stats_time: 0,
             pending_down_modifiers: 0,
             pending_up_modifiers: 0,
+            host_leds: 0,
+            indicator_state: IndicatorState::default(),
         }
     }
 
@@ -339,11 +426,16 @@ impl<
         }
         self.macro_running = Macro::Noop;
         self.tapdance.clear();
+        self.combo.clear();
+        self.leader.clear();
+        self.caps_word.clear();
+        self.dyn_macros.clear();
         self.mouse.clear_all();
         self.layout.clear_all();
         self.dual_action = DualActionTimer::NoDual;
         self.report_channel.clear_reports();
         self.report(KeyEvent::Clear);
+        self.update_led_layers(self.host_leds);
     }
 
     fn clear_layers(&mut self) {
@@ -352,6 +444,7 @@ impl<
         }
         self.layout.clear_layers();
         self.report(KeyEvent::Modifiers(0, false));
+        self.update_led_layers(self.host_leds);
     }
 
     fn stop_active(&mut self) {
@@ -366,53 +459,170 @@ impl<
         &mut self,
         key_scan_channel: &'c KeyScannerChannel<M, SCANNER_BUFFER_SIZE>,
     ) -> ControlMessage {
-        let mut key_logger: Option<&KeyScanLog> = None;
-        'outer: loop {
-            // run this first because no macros may be present when running memos
-            while !matches!(self.macro_running, Macro::Noop) {
-                self.wait_for_report_capacity().await;
-                self.check_time();
-            }
-
-            // run second
+        loop {
             self.wait_for_report_capacity().await;
-            if self.run_memo() {
-                continue 'outer;
+            match self.pending_work() {
+                Pending::SaveDynMacro(slot) => return ControlMessage::SaveDynMacro(slot),
+                Pending::LoadDynMacro(slot) => return ControlMessage::LoadDynMacro(slot),
+                Pending::Busy => continue,
+                Pending::Idle => {}
             }
 
-            let event = select(
+            let event = select3(
                 key_scan_channel.receive(),
                 self.report_channel.wait_control(),
+                self.report_channel.host_leds.wait(),
             )
             .await;
 
-            self.now = Instant::now().as_millis();
+            self.advance_time(Instant::now().as_millis());
 
             // now look for events
             match event {
-                Either::First(scan_key) => {
-                    if let Some(logger) = key_logger {
-                        let _ = logger.try_send(scan_key);
-                    }
-                    self.key_switch(TimedScanKey(scan_key, self.now))
-                }
-                Either::Second(ControlMessage::TimerExpired) => self.check_time(),
-                Either::Second(ControlMessage::Exit) => return ControlMessage::Exit,
-                Either::Second(ControlMessage::LogKeys(on)) => {
+                Either3::First(scan_key) => self.scan_key_event(scan_key),
+                Either3::Second(ControlMessage::TimerExpired) => self.check_time(),
+                Either3::Second(ControlMessage::Exit) => return ControlMessage::Exit,
+                Either3::Second(ControlMessage::LogKeys(on)) => {
                     if on {
-                        key_logger = Some(KEY_SCAN_LOGGER.get());
+                        self.key_logger = Some(KEY_SCAN_LOGGER.get());
                     } else {
-                        key_logger = None;
+                        self.key_logger = None;
                     }
                 }
-                Either::Second(ctl) => {
+                Either3::Second(ctl) => {
                     self.clear_all();
                     return ctl;
                 }
+                Either3::Third(leds) => self.set_host_leds(leds),
+            }
+        }
+    }
+
+    /// The time in milliseconds of the last key or timer event.
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Handle `scan_key` as if it came from the scanner at `now` milliseconds. Used with
+    /// [`Self::simulate_step`] to run the mapper without an executor; such as on a host.
+    pub fn simulate_key(&mut self, scan_key: ScanKey, now: u64) {
+        self.advance_time(now);
+        self.scan_key_event(scan_key);
+    }
+
+    /// Do the next piece of work [`Self::run`] does between key events, advancing the time to no
+    /// later than `now` milliseconds. Returns `false` when there is nothing left to do before `now`.
+    /// Key events should be received from the [`MapperChannel`] after each call to make room for
+    /// running macros. Dynamic macros are kept in memory only so just the last one recorded can be
+    /// played.
+    pub fn simulate_step(&mut self, now: u64) -> bool {
+        if !matches!(self.pending_work(), Pending::Idle) {
+            return true;
+        }
+        if self.wait_time <= now {
+            self.advance_time(self.now.max(self.wait_time));
+            self.check_time();
+            return true;
+        }
+        self.advance_time(now);
+        false
+    }
+
+    /// Do the next piece of work waiting to be done before the next event.
+    fn pending_work(&mut self) -> Pending {
+        if let Some(slot) = self.dyn_macros.take_save() {
+            return Pending::SaveDynMacro(slot as u8);
+        }
+        if let Some(slot) = self.dyn_macros.take_load() {
+            return Pending::LoadDynMacro(slot as u8);
+        }
+
+        // run this first because no macros may be present when running memos
+        if !matches!(self.macro_running, Macro::Noop) {
+            self.check_time();
+            return Pending::Busy;
+        }
+
+        // run second
+        if self.run_memo() {
+            return Pending::Busy;
+        }
+
+        self.update_indicators();
+        Pending::Idle
+    }
+
+    /// Set the time of the event being handled.
+    fn advance_time(&mut self, now: u64) {
+        self.now = now;
+        self.update_stats();
+    }
+
+    /// Handle a key from the scanner.
+    fn scan_key_event(&mut self, scan_key: ScanKey) {
+        if scan_key.is_down() {
+            self.stats.key_press(scan_key.row(), scan_key.column());
+        }
+        if let Some(logger) = self.key_logger {
+            let _ = logger.try_send(TimedScanKey(scan_key, self.now));
+        }
+        self.key_switch(TimedScanKey(scan_key, self.now))
+    }
+
+    /// Record the LED state set by the host and turn the LED layers on or off to match.
+    fn set_host_leds(&mut self, leds: u8) {
+        let changed = self.host_leds ^ leds;
+        self.host_leds = leds;
+        self.update_led_layers(changed);
+    }
+
+    /// Push or pop the layers of the `changed` LEDs to match the host LED state.
+    fn update_led_layers(&mut self, changed: u8) {
+        let mut i = 0;
+        while let Some(&[led, layern]) = self.layout.led_layers().get(i..i + 2) {
+            i += 2;
+            if changed & led as u8 != 0 {
+                if self.host_leds & led as u8 != 0 {
+                    self.push_layer(layern);
+                } else {
+                    self.pop_layer(layern);
+                }
             }
         }
     }
 
+    /// Send the indicator state to the indicator task if it has changed.
+    fn update_indicators(&mut self) {
+        let state = IndicatorState {
+            layers: self.layout.active_layer_bits(),
+            host_leds: self.host_leds,
+            oneshot: !matches!(self.oneshot, Oneshot::None),
+        };
+        if state != self.indicator_state {
+            self.indicator_state = state;
+            self.report_channel.indicators.send(state);
+        }
+    }
+
+    /// Add the time since the last event to the active layers and record the stack high-water
+    /// marks.
+    fn update_stats(&mut self) {
+        let elapsed = self.now.saturating_sub(self.stats_time) as u32;
+        self.stats_time = self.now;
+        let (memo, macro_stack) = self.layout.high_water_marks();
+        self.stats.lock(|s| {
+            let mut seen = 0u32;
+            for layer in self.layout.active_layers().map(|l| l as usize) {
+                if layer < stats::MAX_LAYERS && seen & (1 << layer) == 0 {
+                    seen |= 1 << layer;
+                    s.layer_ms[layer] = s.layer_ms[layer].saturating_add(elapsed);
+                }
+            }
+            s.memo_high_water = memo as u16;
+            s.macro_stack_high_water = macro_stack as u16;
+        });
+    }
+
     fn dual_action_expired(&mut self) {
         self.dual_action.timer_expired();
         if let DualActionTimer::Hold { scan_key, .. } = &self.dual_action {
@@ -421,6 +631,117 @@ impl<
     }
 
     pub fn key_switch(&mut self, k: TimedScanKey) {
+        if self.leader.is_running() && k.0.is_down() {
+            self.leader_key_switch(k);
+        } else if !self.combo_key_switch(k) {
+            self.no_combo_key_switch(k);
+        }
+    }
+
+    /// Add the keycode of `k` to the leader sequence; running the sequence's action if nothing
+    /// longer can match.
+    fn leader_key_switch(&mut self, k: TimedScanKey) {
+        let code = self
+            .layout
+            .find_code(k.0.row(), k.0.column())
+            .map(|kc| kc.0)
+            .unwrap_or(0);
+        let timeout = self.layout.global(globals::LEADER_TIMEOUT as usize);
+        if self.leader.push(code, k.1, timeout)
+            && self.layout.find_leader_sequence(self.leader.keys()).1
+        {
+            self.set_wait_time();
+        } else {
+            self.leader_expired();
+        }
+    }
+
+    fn leader_expired(&mut self) {
+        let action = self.layout.find_leader_sequence(self.leader.keys()).0;
+        self.leader.clear();
+        self.set_wait_time();
+        if let Some(action) = action
+            && self.push_action(action, false)
+        {
+            self.run_action(action, true);
+            self.flush_modifiers(false);
+        }
+    }
+
+    /// Hold back key presses which could be part of a combo until either the combo is complete,
+    /// another key is pressed or released, or the chord window closes. Returns true if `k` was
+    /// consumed.
+    fn combo_key_switch(&mut self, k: TimedScanKey) -> bool {
+        if !k.0.is_down() {
+            if self.combo.is_running() {
+                self.combo_expired();
+            }
+            return match self.combo.release(k.0) {
+                Some(action) => {
+                    if action != 0 {
+                        self.run_action(action, false);
+                        self.flush_modifiers(false);
+                    }
+                    true
+                }
+                None => false,
+            };
+        }
+
+        if !self.combo.is_running() {
+            if self.tapdance.is_running()
+                || !matches!(self.dual_action, DualActionTimer::NoDual)
+                || !self.layout.find_combo(&[k]).1
+            {
+                return false;
+            }
+            self.combo
+                .start(k, self.layout.global(globals::COMBO_TIMEOUT as usize));
+            self.set_wait_time();
+            return true;
+        }
+
+        if self.combo.push(k) {
+            match self.layout.find_combo(self.combo.pending()) {
+                (Some(action), false) => {
+                    self.combo_activate(action);
+                    return true;
+                }
+                (_, true) => return true,
+                (None, false) => self.combo.pop(),
+            }
+        }
+
+        self.combo_expired();
+        false
+    }
+
+    fn combo_expired(&mut self) {
+        if let (Some(action), _) = self.layout.find_combo(self.combo.pending()) {
+            self.combo_activate(action);
+        } else {
+            let (pending, len) = self.combo.take_pending();
+            self.set_wait_time();
+            for k in pending[..len].iter() {
+                self.no_combo_key_switch(*k);
+            }
+        }
+    }
+
+    fn combo_activate(&mut self, action: u16) {
+        let scan_key = self.combo.pending()[0];
+        let prev = self.combo.activate(action);
+        if prev != 0 {
+            self.run_action(prev, false);
+        }
+        self.set_wait_time();
+        self.last_scan_key = scan_key;
+        self.run_action(action, true);
+        self.flush_modifiers(false);
+    }
+
+    fn no_combo_key_switch(&mut self, k: TimedScanKey) {
+        self.prior_scan_time = self.last_scan_key.1;
         if self.tapdance.is_running() {
             if !self.last_scan_key.same_key(&k) || self.tapdance.rem == 0 {
                 self.tapdance_timeout();
@@ -433,7 +754,8 @@ impl<
         }
 
         self.last_scan_key = k;
-        if self.dual_action.key_switch(k) {
+        let hand = self.layout.hand(k.0.row(), k.0.column());
+        if self.dual_action.key_switch(k, hand) {
             self.key_switch_1(k.0);
             return;
         }
@@ -448,15 +770,23 @@ impl<
                 }
                 self.dual_action = DualActionTimer::NoDual;
                 self.last_scan_key = scan_key;
+                self.stats.dual_action(false);
                 self.run_action(hold, true);
                 self.flush_modifiers(false);
             }
             DualActionTimer::Tap { scan_key, tap } => {
                 self.dual_action = DualActionTimer::NoDual;
                 self.last_scan_key = scan_key;
-                if self.push_action(tap, false) {
-                    self.run_action(tap, true);
-                    self.flush_modifiers(false);
+                if scan_key.same_key(&k) {
+                    self.stats.dual_action(true);
+                    if self.push_action(tap, false) {
+                        self.run_action(tap, true);
+                        self.flush_modifiers(false);
+                    }
+                } else {
+                    // decided by another key while still held
+                    self.push_scan_key(&k);
+                    self.dual_action_as_tap(tap);
                 }
             }
         }
@@ -503,15 +833,14 @@ impl<
             let down_mods = self.pending_down_modifiers & clear;
 
             if up_mods != 0 {
-                self.report_channel.report(KeyEvent::modifiers(
+                self.send_report(KeyEvent::modifiers(
                     up_mods,
                     false,
                     pending || down_mods != 0,
                 ));
             }
             if down_mods != 0 {
-                self.report_channel
-                    .report(KeyEvent::modifiers(down_mods, true, pending));
+                self.send_report(KeyEvent::modifiers(down_mods, true, pending));
             }
             self.pending_up_modifiers = 0;
             self.pending_down_modifiers = 0;
@@ -521,7 +850,14 @@ impl<
     fn report(&mut self, message: KeyEvent) {
         self.report_count = self.report_count.wrapping_add(1);
         self.flush_modifiers(true);
-        self.report_channel.report(message);
+        self.send_report(message);
+    }
+
+    fn send_report(&mut self, message: KeyEvent) {
+        self.dyn_macros.record(&message, self.now);
+        if !self.report_channel.report(message) {
+            self.stats.report_overflow();
+        }
     }
 
     fn run_action(&mut self, action: u16, is_down: bool) {
@@ -530,6 +866,9 @@ impl<
                 self.modifier(action, is_down);
             }
             key_range::BASIC_MIN..=key_range::BASIC_MAX => {
+                if is_down && self.caps_word.is_running() {
+                    self.caps_word_key(action);
+                }
                 self.report(KeyEvent::basic(action as u8, is_down));
             }
             key_range::MACROS_MIN..=key_range::MACROS_MAX => {
@@ -572,6 +911,35 @@ impl<
             key_range::FW_STOP_ACTIVE => {
                 self.stop_active();
             }
+            key_range::FW_CAPS_WORD => {
+                if is_down {
+                    if self.caps_word.is_running() {
+                        self.caps_word_stop();
+                    } else {
+                        self.caps_word.start();
+                        self.caps_word.restart_timer(
+                            self.now,
+                            self.layout.global(globals::CAPS_WORD_TIMEOUT as usize),
+                        );
+                        self.set_wait_time();
+                    }
+                }
+            }
+            key_range::FW_DYN_MACRO_RECORD..=key_range::FW_DYN_MACRO_RECORD_END => {
+                if is_down {
+                    if self.dyn_macros.recording().is_some() {
+                        self.dyn_macros.stop();
+                    } else {
+                        self.dyn_macros
+                            .start((action - key_range::FW_DYN_MACRO_RECORD) as usize);
+                    }
+                }
+            }
+            key_range::FW_DYN_MACRO_PLAY..=key_range::FW_DYN_MACRO_PLAY_END => {
+                if is_down {
+                    self.play_dyn_macro((action - key_range::FW_DYN_MACRO_PLAY) as usize);
+                }
+            }
             _ => {
                 crate::info!(
                     "not yet supported: {:?} {:?}",
@@ -585,6 +953,33 @@ impl<
         }
     }
 
+    /// Shift or unshift the word key `code` before it is reported; or stop caps word if `code` is
+    /// not part of a word.
+    fn caps_word_key(&mut self, code: u16) {
+        match caps_word::word_key(code, self.layout.caps_word_keys()) {
+            Some(shift) => {
+                if shift != self.caps_word.shifted {
+                    self.caps_word.shifted = shift;
+                    self.write_modifiers(caps_word::SHIFT, if shift { 1 } else { -1 }, true);
+                }
+                self.caps_word.restart_timer(
+                    self.now,
+                    self.layout.global(globals::CAPS_WORD_TIMEOUT as usize),
+                );
+                self.set_wait_time();
+            }
+            None => self.caps_word_stop(),
+        }
+    }
+
+    fn caps_word_stop(&mut self) {
+        if self.caps_word.shifted {
+            self.write_modifiers(caps_word::SHIFT, -1, true);
+        }
+        self.caps_word.clear();
+        self.set_wait_time();
+    }
+
     pub fn load_layout(
         &mut self,
         layout_mapping: impl IntoIterator<Item = u16>,
@@ -596,6 +991,11 @@ impl<
             self.layout.global(globals::DEBOUNCE_SETTLE_TIME as usize),
             atomic::Ordering::Relaxed,
         );
+        self.debounce_mode_atomic.store(
+            self.layout.global(globals::DEBOUNCE_MODE as usize) as u8,
+            atomic::Ordering::Relaxed,
+        );
+        self.update_led_layers(self.host_leds);
 
         Ok(())
     }
@@ -613,8 +1013,12 @@ impl<
                     self.write_modifiers(key_plus_mod.1, -10, true);
                 }
             }
-            Macro::DualAction(tap, hold, t1, t2) => {
-                self.start_dual_action(is_down, *tap, *hold, *t1, *t2);
+            Macro::DualAction(tap, hold, t1, t2, mode, prior_idle) => {
+                if is_down && self.is_typing_streak(*prior_idle) {
+                    self.dual_action_as_tap(*tap);
+                } else {
+                    self.start_dual_action(is_down, *tap, *hold, *t1, *t2, *mode);
+                }
             }
             Macro::TapDance(location, len) => {
                 if *len > 2 {
@@ -629,7 +1033,9 @@ impl<
             } => {
                 if *rem > 0 {
                     let run = match mode {
-                        macros::SequenceMode::Tap | macros::SequenceMode::Hold => is_down,
+                        macros::SequenceMode::Tap
+                        | macros::SequenceMode::Hold
+                        | macros::SequenceMode::Play => is_down,
                         macros::SequenceMode::Release => !is_down,
                     };
                     if run {
@@ -646,6 +1052,59 @@ impl<
                     self.report(KeyEvent::Delay(*n));
                 }
             }
+            Macro::IfLed(led, on, off) => {
+                let action = if self.host_leds & led != 0 { *on } else { *off };
+                if is_down {
+                    // release the chosen action even if the LED changes while the key is down
+                    let k = self.last_scan_key.0;
+                    let active = &mut self.active_actions[k.row()][k.column()];
+                    if active.0 == code {
+                        active.0 = action;
+                    }
+                }
+                self.run_action(action, is_down);
+            }
+            Macro::Leader => {
+                if is_down {
+                    self.leader.start(
+                        self.now,
+                        self.layout.global(globals::LEADER_TIMEOUT as usize),
+                    );
+                    self.set_wait_time();
+                }
+            }
+        }
+    }
+
+    fn play_dyn_macro(&mut self, slot: usize) {
+        // the macro being recorded is the only one in memory
+        if self.dyn_macros.recording().is_some() || !self.dyn_macros.request_play(slot) {
+            return;
+        }
+        let (location, rem) = self.dyn_macros.location();
+        if rem > 0 {
+            let mode = macros::SequenceMode::Play;
+            self.push_macro(Macro::Sequence {
+                mode,
+                location,
+                rem,
+            });
+            self.next_macro_seq(location, rem, mode);
+        }
+    }
+
+    /// The recorded items of dynamic macro `slot`; empty unless it is the one in memory.
+    pub fn dyn_macro(&self, slot: usize) -> &[u16] {
+        self.dyn_macros.get(slot)
+    }
+
+    /// Load the items of dynamic macro `slot` and play it if it is waiting to be played.
+    pub fn load_dyn_macro(&mut self, slot: usize, items: impl IntoIterator<Item = u16>) {
+        if slot < dyn_macro_items::COUNT {
+            self.dyn_macros.set(slot, items);
+            if self.dyn_macros.take_play() == Some(slot) {
+                self.play_dyn_macro(slot);
+            }
         }
     }
 
@@ -691,6 +1150,13 @@ impl<
                             self.dual_action_expired();
                         } else if self.tapdance.wait_until <= self.now {
                             self.tapdance_timeout();
+                        } else if self.combo.wait_until <= self.now {
+                            self.combo_expired();
+                        } else if self.leader.wait_until <= self.now {
+                            self.leader_expired();
+                        } else if self.caps_word.wait_until <= self.now {
+                            self.caps_word_stop();
+                            self.flush_modifiers(false);
                         }
                     }
                     self.set_wait_time();
@@ -768,7 +1234,11 @@ impl<
         let stack = self.layout.macro_stack();
 
         while self.room_to_report() {
-            let tap = self.layout.macro_code(location as usize);
+            let tap = if location >= dyn_macro::LOCATION {
+                self.dyn_macros.code(location)
+            } else {
+                self.layout.macro_code(location as usize)
+            };
 
             if rem > 1 {
                 location += 1;
@@ -792,6 +1262,15 @@ impl<
                     self.run_action(tap, true);
                     self.run_action(tap, false);
                 }
+                macros::SequenceMode::Play => {
+                    if tap & dyn_macro_items::DELAY != 0 {
+                        self.report(KeyEvent::Delay(tap & dyn_macro_items::MAX_DELAY));
+                    } else if tap & dyn_macro_items::RELEASE != 0 {
+                        self.run_action(tap & !dyn_macro_items::RELEASE, false);
+                    } else {
+                        self.run_action(tap, true);
+                    }
+                }
             }
             self.flush_modifiers(false);
             if rem == 0 || self.layout.macro_stack() != stack {
@@ -950,6 +1429,8 @@ impl<
         } else if self.tapdance.is_running() {
             t = min(t, self.tapdance.wait_until);
         }
+        t = min(t, min(self.combo.wait_until, self.leader.wait_until));
+        t = min(t, self.caps_word.wait_until);
 
         if t != self.wait_time {
             self.wait_time = t;
@@ -976,9 +1457,59 @@ impl<
         }
     }
 
-    fn start_dual_action(&mut self, is_down: bool, tap: u16, hold: u16, time1: u16, time2: u16) {
+    /// Is the last key event within `prior_idle` ms of the event before it. `u16::MAX` means use
+    /// the global `dual_action_require_prior_idle`.
+    fn is_typing_streak(&self, prior_idle: u16) -> bool {
+        let prior_idle = if prior_idle == u16::MAX {
+            self.layout
+                .global(globals::DUAL_ACTION_REQUIRE_PRIOR_IDLE as usize)
+        } else {
+            prior_idle
+        };
+        prior_idle != 0
+            && self.prior_scan_time != 0
+            && self.last_scan_key.1.saturating_sub(self.prior_scan_time) < prior_idle as u64
+    }
+
+    /// Resolve the dual action of the last key to `tap` while the key is still down. If the key's
+    /// action is the dual action then `tap` is held until the key is released; otherwise `tap` is
+    /// tapped.
+    fn dual_action_as_tap(&mut self, tap: u16) {
+        if let DualActionTimer::Wait { hold, .. } = self.dual_action {
+            self.stats.dual_action(false);
+            self.run_action(hold, true);
+            self.dual_action = DualActionTimer::NoDual;
+        }
+        self.stats.dual_action(true);
+        let k = self.last_scan_key.0;
+        let code = self.active_actions[k.row()][k.column()].0;
+        let is_key_action = (key_range::MACROS_MIN..=key_range::MACROS_MAX).contains(&code)
+            && matches!(
+                self.layout.get_macro(code - key_range::MACROS_MIN),
+                Macro::DualAction(t, ..) if t == tap
+            );
+        if is_key_action {
+            self.active_actions[k.row()][k.column()].0 = tap;
+            self.run_action(tap, true);
+        } else if self.push_action(tap, false) {
+            self.run_action(tap, true);
+        }
+        self.flush_modifiers(false);
+        self.set_wait_time();
+    }
+
+    fn start_dual_action(
+        &mut self,
+        is_down: bool,
+        tap: u16,
+        hold: u16,
+        time1: u16,
+        time2: u16,
+        mode: u16,
+    ) {
         if is_down {
             if let DualActionTimer::Wait { hold, .. } = self.dual_action {
+                self.stats.dual_action(false);
                 self.run_action(hold, true);
                 self.dual_action = DualActionTimer::NoDual;
             }
@@ -995,9 +1526,16 @@ impl<
             } else {
                 (time1, time2)
             };
+            let mode = if mode == u16::MAX {
+                self.layout.global(globals::DUAL_ACTION_MODE as usize)
+            } else {
+                mode
+            };
 
+            let k = self.last_scan_key.0;
+            let hand = self.layout.hand(k.row(), k.column());
             self.dual_action
-                .start(self.last_scan_key, tap, hold, time1, time2);
+                .start(self.last_scan_key, tap, hold, time1, time2, mode, hand);
         } else {
             self.run_action(hold, false);
         }
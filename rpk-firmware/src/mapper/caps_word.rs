@@ -0,0 +1,57 @@
+use rpk_common::keycodes::key_range;
+
+/// The modifier bit for left shift.
+pub(super) const SHIFT: u8 = 2;
+
+/// Caps word shifts letters until a key which is not part of a word is pressed or the idle timeout
+/// expires.
+#[derive(Debug)]
+pub(super) struct CapsWord {
+    pub(super) wait_until: u64,
+    pub(super) shifted: bool,
+    running: bool,
+}
+impl Default for CapsWord {
+    fn default() -> Self {
+        Self {
+            wait_until: u64::MAX,
+            shifted: false,
+            running: false,
+        }
+    }
+}
+impl CapsWord {
+    pub(super) fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub(super) fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub(super) fn start(&mut self) {
+        self.running = true;
+        self.shifted = false;
+    }
+
+    pub(super) fn restart_timer(&mut self, now: u64, timeout: u16) {
+        self.wait_until = if timeout == 0 {
+            u64::MAX
+        } else {
+            now + timeout as u64
+        };
+    }
+}
+
+/// Returns `Some(true)` if `code` should be shifted, `Some(false)` if it continues the word
+/// unshifted and `None` if it ends the word.
+pub(super) fn word_key(code: u16, extra_keys: &[u16]) -> Option<bool> {
+    match code {
+        key_range::BASIC_A..=key_range::BASIC_Z | key_range::BASIC_MINUS => Some(true),
+        key_range::BASIC_1..=key_range::BASIC_0
+        | key_range::BASIC_BACKSPACE
+        | key_range::BASIC_DELETE => Some(false),
+        _ if extra_keys.contains(&code) => Some(false),
+        _ => None,
+    }
+}
@@ -0,0 +1,101 @@
+use rpk_common::keycodes::macro_types::COMBO_MAX_KEYS;
+
+use crate::key_scanner::ScanKey;
+
+use super::TimedScanKey;
+
+/// Tracks the keys of a combo (chord) while waiting for the chord window to close and the keys of
+/// the last combo to fire so their releases can be swallowed.
+#[derive(Debug)]
+pub(super) struct Combo {
+    pub(super) wait_until: u64,
+    pending: [TimedScanKey; COMBO_MAX_KEYS],
+    pending_len: usize,
+    active: [ScanKey; COMBO_MAX_KEYS],
+    active_len: usize,
+    action: u16,
+}
+impl Default for Combo {
+    fn default() -> Self {
+        Self {
+            wait_until: u64::MAX,
+            pending: [TimedScanKey::none(); COMBO_MAX_KEYS],
+            pending_len: 0,
+            active: [ScanKey::none(); COMBO_MAX_KEYS],
+            active_len: 0,
+            action: 0,
+        }
+    }
+}
+impl Combo {
+    pub(super) fn is_running(&self) -> bool {
+        self.pending_len != 0
+    }
+
+    pub(super) fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub(super) fn start(&mut self, scan_key: TimedScanKey, timeout: u16) {
+        self.pending[0] = scan_key;
+        self.pending_len = 1;
+        self.wait_until = scan_key.1 + timeout as u64;
+    }
+
+    pub(super) fn pending(&self) -> &[TimedScanKey] {
+        &self.pending[..self.pending_len]
+    }
+
+    pub(super) fn push(&mut self, scan_key: TimedScanKey) -> bool {
+        if self.pending_len == COMBO_MAX_KEYS {
+            false
+        } else {
+            self.pending[self.pending_len] = scan_key;
+            self.pending_len += 1;
+            true
+        }
+    }
+
+    pub(super) fn pop(&mut self) {
+        self.pending_len -= 1;
+    }
+
+    /// Stop waiting and return the keys that were held back.
+    pub(super) fn take_pending(&mut self) -> ([TimedScanKey; COMBO_MAX_KEYS], usize) {
+        let len = self.pending_len;
+        self.pending_len = 0;
+        self.wait_until = u64::MAX;
+        (self.pending, len)
+    }
+
+    /// Make the pending keys the active combo. Returns the action of the previous combo if it was
+    /// still held.
+    pub(super) fn activate(&mut self, action: u16) -> u16 {
+        let prev = self.action;
+        for (a, p) in self.active.iter_mut().zip(self.pending.iter()) {
+            *a = p.0;
+        }
+        self.active_len = self.pending_len;
+        self.action = action;
+        self.pending_len = 0;
+        self.wait_until = u64::MAX;
+        prev
+    }
+
+    /// Remove `scan_key` from the active combo. Returns `None` if it is not part of the active
+    /// combo otherwise the action to release; which is 0 if already released by another key.
+    pub(super) fn release(&mut self, scan_key: ScanKey) -> Option<u16> {
+        let i = self.active[..self.active_len]
+            .iter()
+            .position(|k| k.same_key(scan_key))?;
+        self.active_len -= 1;
+        self.active.swap(i, self.active_len);
+        let action = self.action;
+        self.action = 0;
+        Some(action)
+    }
+}
+
+pub(crate) fn position(scan_key: ScanKey) -> u16 {
+    ((scan_key.row() as u16) << 8) | scan_key.column() as u16
+}
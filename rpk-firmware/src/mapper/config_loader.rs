@@ -1,6 +1,7 @@
-use core::sync::atomic::AtomicU16;
+use core::sync::atomic::{AtomicU8, AtomicU16};
 
 use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use rpk_common::keycodes::dyn_macro;
 
 use crate::{config, key_scanner, mapper, ring_fs};
 
@@ -17,14 +18,21 @@ pub async fn run<
     mapper_channel: &'d mapper::MapperChannel<NoopRawMutex, REPORT_BUFFER_SIZE>,
     fs: &'d dyn ring_fs::RingFs<'d>,
     debounce_ms_atomic: &'d AtomicU16,
+    debounce_mode_atomic: &'d AtomicU8,
+    stats: &'d mapper::stats::Stats,
 ) {
     let mut mapper =
         mapper::Mapper::<'d, ROW_COUNT, COL_COUNT, LAYOUT_MAX, _, REPORT_BUFFER_SIZE>::new(
             mapper_channel,
             debounce_ms_atomic,
+            debounce_mode_atomic,
+            stats,
         );
     {
-        if !match fs.file_reader_by_index(0) {
+        if !match config::boot_config_location(fs)
+            .ok_or(ring_fs::RingFsError::FileNotFound)
+            .and_then(|location| fs.file_reader_by_location(location))
+        {
             Ok(fr) => {
                 if let Err(err) = mapper.load_layout(config::ConfigFileIter::new(fr)) {
                     crate::info!("error loading layout {:?}", err);
@@ -43,20 +51,45 @@ pub async fn run<
         }
     }
 
+    // the dynamic macro files are found when one is first played
+    let mut dyn_macro_files: Option<[Option<u32>; dyn_macro::COUNT]> = None;
+
     loop {
-        if let mapper::ControlMessage::LoadLayout { file_location } =
-            mapper.run(key_scan_channel).await
-        {
-            crate::debug!("load layout here {}", file_location);
-            match fs.file_reader_by_location(file_location) {
-                Ok(fr) => {
-                    if let Err(err) = mapper.load_layout(config::ConfigFileIter::new(fr)) {
-                        crate::info!("error loading layout {:?}", err);
-                        mapper.load_layout(layout_mapping.iter().copied()).unwrap();
+        match mapper.run(key_scan_channel).await {
+            mapper::ControlMessage::LoadLayout { file_location } => {
+                crate::debug!("load layout here {}", file_location);
+                match fs.file_reader_by_location(file_location) {
+                    Ok(fr) => {
+                        if let Err(err) = mapper.load_layout(config::ConfigFileIter::new(fr)) {
+                            crate::info!("error loading layout {:?}", err);
+                            mapper.load_layout(layout_mapping.iter().copied()).unwrap();
+                        }
+                    }
+                    Err(err) => crate::info!("error reading layout {:?}", err),
+                }
+            }
+            mapper::ControlMessage::SaveDynMacro(slot) => {
+                match config::save_dyn_macro(fs, slot, mapper.dyn_macro(slot as usize)) {
+                    Ok(location) => {
+                        if let Some(files) = &mut dyn_macro_files {
+                            files[slot as usize] = Some(location);
+                        }
                     }
+                    Err(err) => crate::info!("error saving dynamic macro {:?}", err),
+                }
+            }
+            mapper::ControlMessage::LoadDynMacro(slot) => {
+                let slot = slot as usize;
+                let files = dyn_macro_files.get_or_insert_with(|| config::find_dyn_macros(fs));
+                let mut items = files[slot].map(|l| config::read_dyn_macro(fs, l, slot));
+                if let Some(None) = items {
+                    // the file has moved since the locations were found
+                    *files = config::find_dyn_macros(fs);
+                    items = files[slot].map(|l| config::read_dyn_macro(fs, l, slot));
                 }
-                Err(err) => crate::info!("error reading layout {:?}", err),
+                mapper.load_dyn_macro(slot, items.flatten().into_iter().flatten());
             }
+            _ => {}
         }
     }
 }
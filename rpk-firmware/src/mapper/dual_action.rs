@@ -1,6 +1,9 @@
 use core::cmp::min;
 
 use DualActionTimer::*;
+use rpk_common::globals::dual_action_modes::{self, hands};
+
+use crate::key_scanner::ScanKey;
 
 use super::TimedScanKey;
 
@@ -16,6 +19,9 @@ pub(super) enum DualActionTimer {
         tap: u16,
         hold: u16,
         count: usize,
+        mode: u16,
+        pressed: ScanKey,
+        hand: u8,
     },
     Hold {
         scan_key: TimedScanKey,
@@ -27,6 +33,7 @@ pub(super) enum DualActionTimer {
     },
 }
 impl DualActionTimer {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn start(
         &mut self,
         scan_key: TimedScanKey,
@@ -34,6 +41,8 @@ impl DualActionTimer {
         hold: u16,
         duration: u16,
         tap_timeout: u16,
+        mode: u16,
+        hand: u8,
     ) {
         debug_assert!(matches!(self, NoDual));
         *self = Wait {
@@ -43,6 +52,9 @@ impl DualActionTimer {
             tap,
             hold,
             count: 2,
+            mode,
+            pressed: ScanKey::none(),
+            hand,
         };
     }
 
@@ -70,7 +82,9 @@ impl DualActionTimer {
         }
     }
 
-    pub(crate) fn key_switch(&mut self, next_key: TimedScanKey) -> bool {
+    /// Process `next_key` pressed with the hand `next_hand`. Returns true if there is no dual action
+    /// waiting to be decided.
+    pub(crate) fn key_switch(&mut self, next_key: TimedScanKey, next_hand: u8) -> bool {
         match self {
             NoDual => true,
             Wait {
@@ -80,12 +94,55 @@ impl DualActionTimer {
                 tap,
                 hold,
                 count,
+                mode,
+                pressed,
+                hand,
             } => {
                 if next_key.same_key(scan_key) {
                     *self = Tap {
                         scan_key: *scan_key,
                         tap: *tap,
                     };
+                } else if *mode == dual_action_modes::BILATERAL {
+                    if next_key.0.is_down() {
+                        if *hand == hands::NONE || next_hand == hands::NONE || *hand != next_hand {
+                            *self = Hold {
+                                scan_key: *scan_key,
+                                hold: *hold,
+                            };
+                        } else {
+                            *self = Tap {
+                                scan_key: *scan_key,
+                                tap: *tap,
+                            };
+                        }
+                    }
+                } else if *mode != dual_action_modes::TIMEOUT {
+                    let is_hold = match *mode {
+                        dual_action_modes::HOLD_ON_OTHER_KEY_PRESS => next_key.0.is_down(),
+                        dual_action_modes::BALANCED
+                            if next_key.0.is_down()
+                                && next_key.1 - scan_key.1 >= *tap_timeout as u64 =>
+                        {
+                            true
+                        }
+                        _ => {
+                            if next_key.0.is_down() {
+                                if pressed.is_none() {
+                                    *pressed = next_key.0;
+                                }
+                                false
+                            } else {
+                                !pressed.is_none() && pressed.is_same_key(next_key.0)
+                            }
+                        }
+                    };
+                    if is_hold {
+                        *self = Hold {
+                            scan_key: *scan_key,
+                            hold: *hold,
+                        };
+                    }
                 } else {
                     if *count == 0 {
                         *self = Hold {
@@ -106,6 +163,9 @@ impl DualActionTimer {
                         },
                         hold: *hold,
                         count: *count - 1,
+                        mode: *mode,
+                        pressed: *pressed,
+                        hand: *hand,
                     };
                 }
                 false
@@ -0,0 +1,251 @@
+use rpk_common::keycodes::{dyn_macro, key_range};
+
+use super::KeyEvent;
+
+/// The maximum number of items (key presses, releases and delays) a dynamic macro can hold.
+pub(crate) const MAX_LEN: usize = 256;
+
+/// Sequence locations at or above this are in the dynamic macro store; not the layout.
+pub(super) const LOCATION: u32 = 0x80_0000;
+
+/// Dynamic macros are recorded from the key events the mapper reports and are played back
+/// through the macro engine. Only one macro is held in memory; the one being recorded or the last
+/// one played. Any other is loaded from flash before it is played.
+pub(super) struct DynMacros {
+    items: [u16; MAX_LEN],
+    len: u16,
+    /// The slot of the macro held in `items`.
+    slot: Option<usize>,
+    recording: Option<usize>,
+    /// The recording is full; later keys are ignored.
+    full: bool,
+    last_time: u64,
+    /// The basic and modifier keys down in the recording.
+    keys_down: [u32; 8],
+    consumer: u16,
+    sys_ctl: u16,
+    save: Option<usize>,
+    load: Option<usize>,
+    play: Option<usize>,
+}
+impl Default for DynMacros {
+    fn default() -> Self {
+        Self {
+            items: [0; MAX_LEN],
+            len: 0,
+            slot: None,
+            recording: None,
+            full: false,
+            last_time: 0,
+            keys_down: [0; 8],
+            consumer: 0,
+            sys_ctl: 0,
+            save: None,
+            load: None,
+            play: None,
+        }
+    }
+}
+impl DynMacros {
+    pub(super) fn recording(&self) -> Option<usize> {
+        self.recording
+    }
+
+    pub(super) fn start(&mut self, slot: usize) {
+        self.recording = Some(slot);
+        self.slot = Some(slot);
+        self.len = 0;
+        self.full = false;
+        self.last_time = u64::MAX;
+        self.keys_down = [0; 8];
+        self.consumer = 0;
+        self.sys_ctl = 0;
+    }
+
+    /// Stop recording and mark the recorded macro as ready to save. Keys still down are released.
+    pub(super) fn stop(&mut self) {
+        if self.recording.is_some() {
+            self.release_keys_down();
+        }
+        self.save = self.recording.take();
+    }
+
+    /// Cancel recording without saving; the partially recorded macro is discarded. A macro waiting
+    /// to be loaded is not played.
+    pub(super) fn clear(&mut self) {
+        if self.recording.take().is_some() {
+            self.slot = None;
+            self.len = 0;
+        }
+        self.play = None;
+    }
+
+    pub(super) fn take_save(&mut self) -> Option<usize> {
+        self.save.take()
+    }
+
+    pub(super) fn take_load(&mut self) -> Option<usize> {
+        self.load.take()
+    }
+
+    pub(super) fn take_play(&mut self) -> Option<usize> {
+        self.play.take()
+    }
+
+    /// Is the macro in `slot` ready to play? If not it is marked to be loaded and played.
+    pub(super) fn request_play(&mut self, slot: usize) -> bool {
+        if self.slot == Some(slot) {
+            return true;
+        }
+        self.load = Some(slot);
+        self.play = Some(slot);
+        false
+    }
+
+    /// The items of the macro in `slot`; empty if it is not in memory.
+    pub(super) fn get(&self, slot: usize) -> &[u16] {
+        if self.slot == Some(slot) {
+            &self.items[..self.len as usize]
+        } else {
+            &[]
+        }
+    }
+
+    /// Hold `items` as the macro in `slot`. Ignored while recording.
+    pub(super) fn set(&mut self, slot: usize, items: impl IntoIterator<Item = u16>) {
+        if self.recording.is_some() {
+            return;
+        }
+        let mut len = 0;
+        for (d, s) in self.items.iter_mut().zip(items) {
+            *d = s;
+            len += 1;
+        }
+        self.len = len;
+        self.slot = Some(slot);
+    }
+
+    /// The start location and length of the macro in memory for the macro engine.
+    pub(super) fn location(&self) -> (u32, u16) {
+        (LOCATION, self.len)
+    }
+
+    pub(super) fn code(&self, location: u32) -> u16 {
+        self.items[(location - LOCATION) as usize]
+    }
+
+    /// Record the key `event` reported at time `now` if recording. When the macro has no room left
+    /// for the event and the releases of the keys down, the keys are released and the recording
+    /// ends; later events are ignored until recording is stopped.
+    pub(super) fn record(&mut self, event: &KeyEvent, now: u64) {
+        if self.recording.is_none() || self.full {
+            return;
+        }
+        let (keys, is_down) = match *event {
+            KeyEvent::Basic(_, is_down) => (1, is_down),
+            KeyEvent::Modifiers(bits, is_down) | KeyEvent::PendingModifiers(bits, is_down) => {
+                (bits.count_ones() as usize, is_down)
+            }
+            KeyEvent::Consumer(code) | KeyEvent::SysCtl(code) => (1, code != 0),
+            _ => (0, false),
+        };
+        // a delay, the keys and the releases of every key down afterwards
+        let needed = 1 + keys + self.keys_down_count() + if is_down { keys } else { 0 };
+        if self.len as usize + needed > MAX_LEN {
+            self.release_keys_down();
+            self.full = true;
+            return;
+        }
+
+        match *event {
+            KeyEvent::Basic(code, is_down) => self.push_key(now, code as u16, is_down),
+            KeyEvent::Modifiers(bits, is_down) | KeyEvent::PendingModifiers(bits, is_down) => {
+                for i in 0..8 {
+                    if bits & (1 << i) != 0 {
+                        self.push_key(now, key_range::MODIFIER_MIN + i, is_down);
+                    }
+                }
+            }
+            KeyEvent::Consumer(code) => {
+                if code == 0 {
+                    let code = self.consumer;
+                    if code != 0 {
+                        self.consumer = 0;
+                        self.push_key(now, code, false);
+                    }
+                } else {
+                    self.consumer = code + key_range::CONSUMER_MIN;
+                    self.push_key(now, self.consumer, true);
+                }
+            }
+            KeyEvent::SysCtl(code) => {
+                if code == 0 {
+                    let code = self.sys_ctl;
+                    if code != 0 {
+                        self.sys_ctl = 0;
+                        self.push_key(now, code, false);
+                    }
+                } else {
+                    self.sys_ctl = code - key_range::SYS_CTL_BASE + key_range::SYS_CTL_MIN;
+                    self.push_key(now, self.sys_ctl, true);
+                }
+            }
+            KeyEvent::Delay(n) => self.push(dyn_macro::DELAY | n.min(dyn_macro::MAX_DELAY)),
+            _ => {}
+        }
+    }
+
+    fn keys_down_count(&self) -> usize {
+        self.keys_down
+            .iter()
+            .map(|bits| bits.count_ones() as usize)
+            .sum::<usize>()
+            + (self.consumer != 0) as usize
+            + (self.sys_ctl != 0) as usize
+    }
+
+    /// Record the release of every key down; modifiers last.
+    fn release_keys_down(&mut self) {
+        for code in [self.consumer, self.sys_ctl] {
+            if code != 0 {
+                self.push(dyn_macro::RELEASE | code);
+            }
+        }
+        self.consumer = 0;
+        self.sys_ctl = 0;
+        for code in 0..(self.keys_down.len() << 5) as u16 {
+            if self.keys_down[(code >> 5) as usize] & (1 << (code & 31)) != 0 {
+                self.push(dyn_macro::RELEASE | code);
+            }
+        }
+        self.keys_down = [0; 8];
+    }
+
+    fn push_key(&mut self, now: u64, code: u16, is_down: bool) {
+        if let Some(bits) = self.keys_down.get_mut((code >> 5) as usize) {
+            if is_down {
+                *bits |= 1 << (code & 31);
+            } else {
+                *bits &= !(1 << (code & 31));
+            }
+        }
+        if self.last_time < now {
+            let n = (now - self.last_time).min(dyn_macro::MAX_DELAY as u64) as u16;
+            self.push(dyn_macro::DELAY | n);
+        }
+        self.last_time = now;
+        self.push(if is_down {
+            code
+        } else {
+            dyn_macro::RELEASE | code
+        });
+    }
+
+    fn push(&mut self, item: u16) {
+        let len = self.len as usize;
+        if len < MAX_LEN {
+            self.items[len] = item;
+            self.len += 1;
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use rpk_common::keycodes::macro_types::LEADER_MAX_KEYS;
+
+/// Collects the keycodes typed after a `leader()` action until they match a leader sequence or
+/// the leader timeout expires.
+#[derive(Debug)]
+pub(super) struct Leader {
+    pub(super) wait_until: u64,
+    keys: [u16; LEADER_MAX_KEYS],
+    len: usize,
+    running: bool,
+}
+impl Default for Leader {
+    fn default() -> Self {
+        Self {
+            wait_until: u64::MAX,
+            keys: [0; LEADER_MAX_KEYS],
+            len: 0,
+            running: false,
+        }
+    }
+}
+impl Leader {
+    pub(super) fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub(super) fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub(super) fn start(&mut self, now: u64, timeout: u16) {
+        self.running = true;
+        self.len = 0;
+        self.wait_until = now + timeout as u64;
+    }
+
+    /// Add `code` to the sequence restarting the timer. Returns false if the sequence is full.
+    pub(super) fn push(&mut self, code: u16, now: u64, timeout: u16) -> bool {
+        if self.len == LEADER_MAX_KEYS {
+            false
+        } else {
+            self.keys[self.len] = code;
+            self.len += 1;
+            self.wait_until = now + timeout as u64;
+            true
+        }
+    }
+
+    pub(super) fn keys(&self) -> &[u16] {
+        &self.keys[..self.len]
+    }
+}
@@ -7,6 +7,7 @@ pub enum SequenceMode {
     Hold,
     Release,
     Tap,
+    Play,
 }
 impl SequenceMode {
     fn code(&self) -> u16 {
@@ -14,6 +15,7 @@ impl SequenceMode {
             Self::Hold => macro_types::HOLD,
             Self::Release => macro_types::RELEASE,
             Self::Tap => macro_types::TAP,
+            Self::Play => macro_types::DYN_MACRO,
         }
     }
 }
@@ -63,7 +65,7 @@ impl Default for TapDance {
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum Macro {
     Modifier(KeyPlusMod),
-    DualAction(u16, u16, u16, u16),
+    DualAction(u16, u16, u16, u16, u16, u16),
     Noop,
     HoldRelease {
         hold: u16,
@@ -76,6 +78,8 @@ pub enum Macro {
     },
     Delay(u16),
     TapDance(u32, u16),
+    Leader,
+    IfLed(u8, u16, u16),
 }
 impl Macro {
     pub fn decode(location: usize, data: Option<&[u16]>) -> Self {
@@ -90,7 +94,9 @@ impl Macro {
                     } else {
                         (u16::MAX, u16::MAX)
                     };
-                    Macro::DualAction(data[1], data[2], t1, t2)
+                    let mode = if data.len() > 5 { data[5] } else { u16::MAX };
+                    let prior_idle = if data.len() > 6 { data[6] } else { u16::MAX };
+                    Macro::DualAction(data[1], data[2], t1, t2, mode, prior_idle)
                 }
                 macro_types::TAPDANCE => {
                     Macro::TapDance(location as u32 + 1, data.len() as u16 - 1)
@@ -100,6 +106,14 @@ impl Macro {
                     release: data[2],
                 },
                 macro_types::DELAY => Macro::Delay(data[1]),
+                macro_types::LEADER => Macro::Leader,
+                macro_types::IF_LED => Macro::IfLed((data[0] >> 8) as u8, data[1], data[2]),
+                // combos, leader sequences, caps word keys and LED layers are used by the mapper;
+                // not by keycode
+                macro_types::COMBO
+                | macro_types::LEADER_SEQUENCE
+                | macro_types::CAPS_WORD_KEYS
+                | macro_types::LED_LAYERS => Macro::Noop,
                 mode => {
                     if let Some(mode) = Macro::sequence_mode(mode) {
                         Macro::Sequence {
@@ -178,6 +192,7 @@ impl Macro {
             macro_types::TAP => SequenceMode::Tap,
             macro_types::HOLD => SequenceMode::Hold,
             macro_types::RELEASE => SequenceMode::Release,
+            macro_types::DYN_MACRO => SequenceMode::Play,
             _ => return None,
         })
     }
@@ -0,0 +1,125 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::{Mutex, raw::CriticalSectionRawMutex};
+
+/// The number of key positions which have their presses counted.
+pub const MAX_KEYS: usize = 256;
+
+/// The number of layers which have their active time recorded.
+pub const MAX_LAYERS: usize = 32;
+
+pub struct StatsData {
+    pub rows: u8,
+    pub cols: u8,
+    pub key_presses: [u32; MAX_KEYS],
+    pub layer_ms: [u32; MAX_LAYERS],
+    pub dual_action_taps: u32,
+    pub dual_action_holds: u32,
+    pub memo_high_water: u16,
+    pub macro_stack_high_water: u16,
+    pub report_overflows: u32,
+}
+impl StatsData {
+    const fn new() -> Self {
+        Self {
+            rows: 0,
+            cols: 0,
+            key_presses: [0; MAX_KEYS],
+            layer_ms: [0; MAX_LAYERS],
+            dual_action_taps: 0,
+            dual_action_holds: 0,
+            memo_high_water: 0,
+            macro_stack_high_water: 0,
+            report_overflows: 0,
+        }
+    }
+
+    fn key_count(&self) -> usize {
+        MAX_KEYS.min(self.rows as usize * self.cols as usize)
+    }
+
+    fn layer_count(&self) -> usize {
+        self.layer_ms
+            .iter()
+            .rposition(|t| *t != 0)
+            .map_or(0, |i| i + 1)
+    }
+
+    /// The stats encoded for the host. The format is:
+    ///
+    /// uptime u32, rows u8, cols u8, dual action taps u32, dual action holds u32, memo high-water
+    /// u16, macro stack high-water u16, report overflows u32, layer count u8, layer active ms u32
+    /// * layer count, key presses u32 * rows * cols
+    ///
+    /// All numbers are little endian.
+    pub fn bytes(&self, uptime: u32) -> impl Iterator<Item = u8> + '_ {
+        let layer_count = self.layer_count();
+        uptime
+            .to_le_bytes()
+            .into_iter()
+            .chain([self.rows, self.cols])
+            .chain(self.dual_action_taps.to_le_bytes())
+            .chain(self.dual_action_holds.to_le_bytes())
+            .chain(self.memo_high_water.to_le_bytes())
+            .chain(self.macro_stack_high_water.to_le_bytes())
+            .chain(self.report_overflows.to_le_bytes())
+            .chain([layer_count as u8])
+            .chain(
+                self.layer_ms[..layer_count]
+                    .iter()
+                    .flat_map(|t| t.to_le_bytes()),
+            )
+            .chain(
+                self.key_presses[..self.key_count()]
+                    .iter()
+                    .flat_map(|n| n.to_le_bytes()),
+            )
+    }
+}
+
+/// Usage statistics collected by the mapper for reporting to the host.
+pub struct Stats(Mutex<CriticalSectionRawMutex, RefCell<StatsData>>);
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Stats {
+    pub const fn new() -> Self {
+        Self(Mutex::new(RefCell::new(StatsData::new())))
+    }
+
+    pub fn lock<R>(&self, f: impl FnOnce(&mut StatsData) -> R) -> R {
+        self.0.lock(|s| f(&mut s.borrow_mut()))
+    }
+
+    pub(crate) fn set_matrix_size(&self, rows: usize, cols: usize) {
+        self.lock(|s| {
+            s.rows = rows as u8;
+            s.cols = cols as u8;
+        });
+    }
+
+    pub(crate) fn key_press(&self, row: usize, column: usize) {
+        self.lock(|s| {
+            if let Some(n) = s.key_presses.get_mut(row * s.cols as usize + column) {
+                *n = n.saturating_add(1);
+            }
+        });
+    }
+
+    pub(crate) fn dual_action(&self, is_tap: bool) {
+        self.lock(|s| {
+            let n = if is_tap {
+                &mut s.dual_action_taps
+            } else {
+                &mut s.dual_action_holds
+            };
+            *n = n.saturating_add(1);
+        });
+    }
+
+    pub(crate) fn report_overflow(&self) {
+        self.lock(|s| s.report_overflows = s.report_overflows.saturating_add(1));
+    }
+}
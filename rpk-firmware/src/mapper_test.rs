@@ -27,7 +27,9 @@ macro_rules! setup {
         {
             let mapper_channel = MapperChannel::default();
             let debounce_ms_atomic = atomic::AtomicU16::new(8);
-            let mut $t = Mapper::<$r, $c, 200, NoopRawMutex, 10>::new(&mapper_channel, &debounce_ms_atomic);
+            let debounce_mode_atomic = atomic::AtomicU8::new(0);
+            let stats = Stats::default();
+            let mut $t = Mapper::<$r, $c, 200, NoopRawMutex, 10>::new(&mapper_channel, &debounce_ms_atomic, &debounce_mode_atomic, &stats);
 
             let layout = rpk_config::text_to_binary($a).unwrap();
             $t.load_layout(layout).unwrap();
@@ -545,6 +547,50 @@ max_ticks_per_ms = 6
     );
 }
 
+#[test]
+fn simulate() {
+    setup!(
+        t,
+        _press,
+        assert_read,
+        r#"
+[matrix:2x3]
+
+0x00 = a b c
+0x10 = d e f
+
+[main]
+
+a = dualaction(rightshift, a)
+b = macro(x y)
+"#,
+        {
+            t.simulate_key(ScanKey::new(0, 0, true), 100);
+            while t.simulate_step(279) {}
+            assert_read!(NONE);
+            assert_eq!(t.now, 279);
+
+            while t.simulate_step(400) {}
+            assert_read!(KEY_DOWN, "rightshift");
+            assert_eq!(t.now, 400);
+
+            t.simulate_key(ScanKey::new(0, 0, false), 410);
+            assert_read!(KEY_UP, "rightshift");
+
+            t.simulate_key(ScanKey::new(0, 1, true), 420);
+            while t.simulate_step(420) {}
+            assert_read!(TAP "x");
+            assert_read!(TAP "y");
+            assert_read!(NONE);
+
+            // simulated keys are counted like scanned ones
+            t.stats.lock(|s| {
+                assert_eq!(&s.key_presses[..2], &[1, 1]);
+            });
+        }
+    );
+}
+
 #[test]
 fn mouse_move() {
     let now = 12341;
@@ -705,6 +751,85 @@ a = oneshot(shift)
     );
 }
 
+#[test]
+fn indicators() {
+    setup!(
+        t,
+        press,
+        assert_read,
+        r#"
+[matrix:2x3]
+
+0x00 = a b c
+0x10 = e f g
+
+[main]
+
+a = oneshot(nav)
+c = layer(nav)
+
+[nav]
+
+b = left
+"#,
+        {
+            use crate::indicator::IndicatorState;
+            use rpk_common::keycodes::leds;
+
+            let indicators = t.report_channel.indicators();
+            let nav = 1 << 6;
+
+            t.update_indicators();
+            assert_eq!(
+                indicators.try_take(),
+                Some(IndicatorState {
+                    layers: 1 << 5,
+                    host_leds: 0,
+                    oneshot: false,
+                })
+            );
+            t.update_indicators();
+            assert_eq!(indicators.try_take(), None);
+
+            press!(0, 2, true);
+            t.set_host_leds(leds::NUM_LOCK);
+            t.update_indicators();
+            assert_eq!(
+                indicators.try_take(),
+                Some(IndicatorState {
+                    layers: 1 << 5 | nav,
+                    host_leds: leds::NUM_LOCK,
+                    oneshot: false,
+                })
+            );
+            press!(0, 2, false);
+
+            press!(0, 0, TAP);
+            t.update_indicators();
+            assert_eq!(
+                indicators.try_take(),
+                Some(IndicatorState {
+                    layers: 1 << 5 | nav,
+                    host_leds: leds::NUM_LOCK,
+                    oneshot: true,
+                })
+            );
+
+            press!(0, 1, TAP);
+            assert_read!(TAP "left");
+            t.update_indicators();
+            assert_eq!(
+                indicators.try_take(),
+                Some(IndicatorState {
+                    layers: 1 << 5,
+                    host_leds: leds::NUM_LOCK,
+                    oneshot: false,
+                })
+            );
+        }
+    );
+}
+
 #[test]
 fn activate_layer() {
     setup!(
@@ -755,6 +880,12 @@ dual_action_timeout = 500
 dual_action_timeout2 = 50
 tapdance_tap_timeout = 90
 debounce_settle_time = 12.3
+combo_timeout = 30
+leader_timeout = 2000
+caps_word_timeout = 3000
+dual_action_mode = balanced
+dual_action_require_prior_idle = 125
+debounce_mode = defer
 
 [matrix:2x3]
 
@@ -766,10 +897,210 @@ debounce_settle_time = 12.3
             assert_eq!(t.layout.global(globals::DUAL_ACTION_TIMEOUT2 as usize), 50);
             assert_eq!(t.layout.global(globals::TAPDANCE_TAP_TIMEOUT as usize), 90);
             assert_eq!(t.layout.global(globals::DEBOUNCE_SETTLE_TIME as usize), 323);
+            assert_eq!(t.layout.global(globals::COMBO_TIMEOUT as usize), 30);
+            assert_eq!(t.layout.global(globals::LEADER_TIMEOUT as usize), 2000);
+            assert_eq!(t.layout.global(globals::CAPS_WORD_TIMEOUT as usize), 3000);
+            assert_eq!(
+                t.layout.global(globals::DUAL_ACTION_MODE as usize),
+                globals::dual_action_modes::BALANCED
+            );
+            assert_eq!(
+                t.layout
+                    .global(globals::DUAL_ACTION_REQUIRE_PRIOR_IDLE as usize),
+                125
+            );
 
             let debounce = t.debounce_ms_atomic.load(atomic::Ordering::Relaxed);
 
             assert_eq!(debounce, 323);
+            assert_eq!(
+                t.debounce_mode_atomic.load(atomic::Ordering::Relaxed) as u16,
+                globals::debounce_modes::DEFER
+            );
+        }
+    );
+}
+
+#[test]
+fn combo() {
+    setup!(
+        t,
+        press,
+        assert_read,
+        r#"
+[matrix:2x3]
+0x00 = a b c
+0x10 = e f g
+
+[combos]
+e+f = x
+a+b = z
+a+b+c = y
+"#,
+        {
+            let mut now = 100;
+
+            macro_rules! advance {
+                ($t:expr) => {
+                    now += $t;
+                    t.now = now;
+                    t.check_time();
+                };
+            }
+
+            advance!(0);
+
+            // chord
+            press!(1, 0, true);
+            assert_read!(NONE);
+            advance!(10);
+            press!(1, 1, true);
+            assert_read!(KEY_DOWN, "x");
+            press!(1, 1, false);
+            assert_read!(KEY_UP, "x");
+            press!(1, 0, false);
+            assert_read!(NONE);
+            assert_eq!(t.wait_time, u64::MAX);
+
+            // tap
+            press!(1, 0, true);
+            assert_read!(NONE);
+            press!(1, 0, false);
+            assert_read!(TAP "e");
+            assert_read!(NONE);
+
+            // chord window closes
+            press!(1, 1, true);
+            advance!(49);
+            assert_read!(NONE);
+            advance!(1);
+            assert_read!(KEY_DOWN, "f");
+            press!(1, 0, true);
+            assert_read!(NONE);
+            press!(1, 1, false);
+            assert_read!(KEY_DOWN, "e");
+            assert_read!(KEY_UP, "f");
+            press!(1, 0, false);
+            assert_read!(KEY_UP, "e");
+
+            // interrupted by another key
+            press!(1, 0, true);
+            press!(1, 2, true);
+            assert_read!(KEY_DOWN, "e");
+            assert_read!(KEY_DOWN, "g");
+            press!(1, 0, false);
+            press!(1, 2, false);
+            assert_read!(KEY_UP, "e");
+            assert_read!(KEY_UP, "g");
+            assert_read!(NONE);
+
+            // waits for a larger combo
+            press!(0, 1, true);
+            press!(0, 0, true);
+            assert_read!(NONE);
+            press!(0, 2, true);
+            assert_read!(KEY_DOWN, "y");
+            press!(0, 0, false);
+            press!(0, 1, false);
+            press!(0, 2, false);
+            assert_read!(KEY_UP, "y");
+            assert_read!(NONE);
+
+            // smaller combo fires when window closes
+            press!(0, 0, true);
+            press!(0, 1, true);
+            assert_read!(NONE);
+            advance!(50);
+            assert_read!(KEY_DOWN, "z");
+            press!(0, 1, false);
+            assert_read!(KEY_UP, "z");
+            press!(0, 0, false);
+            assert_read!(NONE);
+        }
+    );
+}
+
+#[test]
+fn leader() {
+    setup!(
+        t,
+        press,
+        assert_read,
+        r#"
+[matrix:2x3]
+0x00 = a b c
+0x10 = e f g
+
+[main]
+g = leader()
+
+[leader]
+a = x
+a b = y
+b b = macro(h i)
+e = layer(shift)
+"#,
+        {
+            let mut now = 100;
+
+            macro_rules! advance {
+                ($t:expr) => {
+                    now += $t;
+                    t.now = now;
+                    t.check_time();
+                };
+            }
+
+            advance!(0);
+
+            // exact match
+            press!(1, 2, TAP);
+            assert_read!(NONE);
+            press!(0, 1, TAP);
+            assert_read!(NONE);
+            press!(0, 1, true);
+            assert_read!(TAP "h");
+            assert_read!(TAP "i");
+            t.run_memo();
+            assert_read!(NONE);
+            press!(0, 1, false);
+            assert_read!(NONE);
+            assert_eq!(t.wait_time, u64::MAX);
+
+            // timeout runs the shorter sequence
+            press!(1, 2, TAP);
+            press!(0, 0, true);
+            advance!(999);
+            assert_read!(NONE);
+            advance!(1);
+            assert_read!(KEY_DOWN, "x");
+            t.run_memo();
+            assert_read!(KEY_UP, "x");
+            press!(0, 0, false);
+            assert_read!(NONE);
+
+            // longer sequence
+            press!(1, 2, TAP);
+            press!(0, 0, TAP);
+            advance!(500);
+            press!(0, 1, TAP);
+            assert_read!(KEY_DOWN, "y");
+            t.run_memo();
+            assert_read!(KEY_UP, "y");
+
+            // no match
+            press!(1, 2, TAP);
+            press!(0, 2, TAP);
+            assert_read!(NONE);
+            press!(0, 2, TAP);
+            assert_read!(TAP "c");
+
+            // timeout with no match
+            press!(1, 2, TAP);
+            advance!(1000);
+            press!(0, 0, TAP);
+            assert_read!(TAP "a");
+            assert_read!(NONE);
         }
     );
 }
@@ -1018,15 +1349,391 @@ g = overload(alt, g, 100)
                 advance!(59);
                 assert_read!(NONE);
 
-                advance!(1);
-                assert_read!(KEY_DOWN, "leftshift");
-                assert_read!(NONE);
+                advance!(1);
+                assert_read!(KEY_DOWN, "leftshift");
+                assert_read!(NONE);
+
+                press!(0, 2, false);
+
+                assert_read!(KEY_UP, "leftshift");
+                assert_read!(NONE);
+            }
+        }
+    );
+}
+
+#[test]
+fn stats() {
+    setup!(
+        t,
+        press,
+        assert_read,
+        r#"
+[matrix:2x3]
+
+0x00 = a b c
+0x10 = e f g
+
+[main]
+
+c = dualaction(leftshift, c, 100)
+"#,
+        {
+            t.now = 100;
+            t.update_stats();
+            t.stats.lock(|s| {
+                assert_eq!((s.rows, s.cols), (2, 3));
+                assert_eq!(s.layer_ms[layout::MAIN_BASE as usize], 100);
+            });
+
+            press!(0, 2, TAP);
+
+            press!(0, 2, true);
+            press!(0, 0, true);
+            t.now = 250;
+            t.check_time();
+            press!(0, 0, false);
+            press!(0, 2, false);
+
+            t.report_channel.clear_reports();
+            for _ in 0..11 {
+                t.send_report(KeyEvent::Pending);
+            }
+            assert_read!(E KeyEvent::Clear);
+            assert_read!(NONE);
+
+            t.update_stats();
+            t.stats.lock(|s| {
+                assert_eq!(s.dual_action_taps, 1);
+                assert_eq!(s.dual_action_holds, 1);
+                assert_eq!(s.report_overflows, 1);
+                assert_eq!(s.memo_high_water, 9);
+                assert_eq!(s.layer_ms[layout::MAIN_BASE as usize], 250);
+            });
+        }
+    );
+}
+
+#[test]
+fn dualaction_modes() {
+    setup!(
+        t,
+        press,
+        assert_read,
+        r#"
+[global]
+dual_action_mode = permissive_hold
+
+[matrix:2x3]
+
+0x00 = a b c
+0x10 = e f g
+
+[main]
+
+a = dualaction(leftshift, a, hold_on_other_key_press)
+b = dualaction(leftshift, b)
+c = dualaction(leftshift, c, 200, 50, balanced)
+"#,
+        {
+            let mut now = 100;
+
+            macro_rules! advance {
+                ($t:expr) => {
+                    now += $t;
+                    t.now = now;
+                    t.check_time();
+                };
+            }
+
+            advance!(0);
+
+            // hold on other key press
+            {
+                press!(0, 0, true);
+                advance!(10);
+                press!(1, 0, true);
+                assert_read!(KEY_DOWN, "leftshift");
+                assert!(t.run_memo());
+                assert_read!(KEY_DOWN, "e");
+                assert!(!t.run_memo());
+
+                press!(1, 0, false);
+                press!(0, 0, false);
+                assert_read!(KEY_UP, "e");
+                assert_read!(KEY_UP, "leftshift");
+                assert_read!(NONE);
+            }
+
+            // permissive hold; nested tap
+            {
+                press!(0, 1, true);
+                press!(1, 0, true);
+                advance!(10);
+                assert_read!(NONE);
+                press!(1, 0, false);
+                assert_read!(KEY_DOWN, "leftshift");
+                assert!(t.run_memo());
+                assert_read!(KEY_DOWN, "e");
+                assert!(t.run_memo());
+                assert_read!(KEY_UP, "e");
+                assert!(!t.run_memo());
+
+                press!(0, 1, false);
+                assert_read!(KEY_UP, "leftshift");
+                assert_read!(NONE);
+            }
+
+            // permissive hold; roll
+            {
+                press!(0, 1, true);
+                press!(1, 0, true);
+                advance!(100);
+                assert_read!(NONE);
+                press!(0, 1, false);
+                assert_read!(KEY_DOWN, "b");
+                while t.run_memo() {}
+                assert_read!(KEY_DOWN, "e");
+                assert_read!(KEY_UP, "b");
+
+                press!(1, 0, false);
+                assert_read!(KEY_UP, "e");
+                assert_read!(NONE);
+            }
+
+            // balanced; quick roll is a tap
+            {
+                press!(0, 2, true);
+                advance!(49);
+                press!(1, 0, true);
+                assert_read!(NONE);
+                press!(0, 2, false);
+                assert_read!(KEY_DOWN, "c");
+                while t.run_memo() {}
+                assert_read!(KEY_DOWN, "e");
+                assert_read!(KEY_UP, "c");
+
+                press!(1, 0, false);
+                assert_read!(KEY_UP, "e");
+                assert_read!(NONE);
+            }
+
+            // balanced; slow press is a hold
+            {
+                press!(0, 2, true);
+                advance!(50);
+                press!(1, 0, true);
+                assert_read!(KEY_DOWN, "leftshift");
+                assert!(t.run_memo());
+                assert_read!(KEY_DOWN, "e");
+
+                press!(1, 0, false);
+                press!(0, 2, false);
+                assert_read!(KEY_UP, "e");
+                assert_read!(KEY_UP, "leftshift");
+                assert_read!(NONE);
+            }
+
+            // balanced; hold timeout
+            {
+                press!(0, 2, true);
+                advance!(199);
+                assert_read!(NONE);
+                advance!(1);
+                assert_read!(KEY_DOWN, "leftshift");
+                press!(0, 2, false);
+                assert_read!(KEY_UP, "leftshift");
+                assert_read!(NONE);
+            }
+        }
+    );
+}
+
+#[test]
+fn dualaction_bilateral() {
+    setup!(
+        t,
+        press,
+        assert_read,
+        r#"
+[matrix:2x3]
+
+0x00 = a b c
+0x10 = e f g
+
+[hands]
+left = a b e
+right = c f
+
+[main]
+
+a = dualaction(leftshift, a, bilateral)
+"#,
+        {
+            let mut now = 100;
+
+            macro_rules! advance {
+                ($t:expr) => {
+                    now += $t;
+                    t.now = now;
+                    t.check_time();
+                };
+            }
+
+            advance!(0);
+
+            assert_eq!(t.layout.hand(0, 1), 1);
+            assert_eq!(t.layout.hand(1, 1), 2);
+            assert_eq!(t.layout.hand(1, 2), 0);
+
+            // same hand
+            {
+                press!(0, 0, true);
+                press!(0, 1, true);
+                assert_read!(KEY_DOWN, "a");
+                assert!(t.run_memo());
+                assert_read!(KEY_DOWN, "b");
+                assert!(!t.run_memo());
+
+                press!(0, 1, false);
+                assert_read!(KEY_UP, "b");
+                advance!(200);
+                assert_read!(NONE);
+                press!(0, 0, false);
+                assert_read!(KEY_UP, "a");
+                assert_read!(NONE);
+            }
+
+            // other hand
+            {
+                press!(0, 0, true);
+                press!(0, 2, true);
+                assert_read!(KEY_DOWN, "leftshift");
+                assert!(t.run_memo());
+                assert_read!(KEY_DOWN, "c");
+
+                press!(0, 2, false);
+                press!(0, 0, false);
+                assert_read!(KEY_UP, "c");
+                assert_read!(KEY_UP, "leftshift");
+                assert_read!(NONE);
+            }
+
+            // no hand
+            {
+                press!(0, 0, true);
+                press!(1, 2, true);
+                assert_read!(KEY_DOWN, "leftshift");
+                assert!(t.run_memo());
+                assert_read!(KEY_DOWN, "g");
+
+                press!(1, 2, false);
+                press!(0, 0, false);
+                assert_read!(KEY_UP, "g");
+                assert_read!(KEY_UP, "leftshift");
+                assert_read!(NONE);
+            }
+
+            // tap and timeout
+            {
+                press!(0, 0, TAP);
+                assert_read!(KEY_DOWN, "a");
+                assert!(t.run_memo());
+                assert_read!(KEY_UP, "a");
+
+                press!(0, 0, true);
+                advance!(180);
+                assert_read!(KEY_DOWN, "leftshift");
+                press!(0, 0, false);
+                assert_read!(KEY_UP, "leftshift");
+                assert_read!(NONE);
+            }
+        }
+    );
+}
+
+#[test]
+fn dual_action_require_prior_idle() {
+    setup!(
+        t,
+        press,
+        assert_read,
+        r#"
+[global]
+dual_action_require_prior_idle = 100
+
+[matrix:2x3]
+
+0x00 = a b c
+0x10 = e f g
+
+[main]
+
+a = dualaction(leftshift, a)
+b = dualaction(leftshift, b, require_prior_idle=0)
+c = dualaction(leftshift, c, 200, require_prior_idle=20)
+"#,
+        {
+            let mut now = 100;
+
+            macro_rules! advance {
+                ($t:expr) => {
+                    now += $t;
+                    t.now = now;
+                    t.check_time();
+                };
+            }
+
+            advance!(0);
+
+            // idle
+            press!(0, 0, true);
+            advance!(180);
+            assert_read!(KEY_DOWN, "leftshift");
+            press!(0, 0, false);
+            assert_read!(KEY_UP, "leftshift");
+
+            // typing streak
+            advance!(50);
+            press!(1, 0, TAP);
+            assert_read!(TAP "e");
+            advance!(99);
+            press!(0, 0, true);
+            assert_read!(KEY_DOWN, "a");
+            advance!(300);
+            assert_read!(NONE);
+            press!(0, 0, false);
+            assert_read!(KEY_UP, "a");
+            assert_read!(NONE);
+            assert!(!t.run_memo());
 
-                press!(0, 2, false);
+            // per key override off
+            press!(1, 0, TAP);
+            assert_read!(TAP "e");
+            press!(0, 1, true);
+            advance!(180);
+            assert_read!(KEY_DOWN, "leftshift");
+            press!(0, 1, false);
+            assert_read!(KEY_UP, "leftshift");
 
-                assert_read!(KEY_UP, "leftshift");
-                assert_read!(NONE);
-            }
+            // per key override
+            press!(1, 0, TAP);
+            assert_read!(TAP "e");
+            advance!(20);
+            press!(0, 2, true);
+            advance!(200);
+            assert_read!(KEY_DOWN, "leftshift");
+            press!(0, 2, false);
+            assert_read!(KEY_UP, "leftshift");
+
+            press!(1, 0, TAP);
+            assert_read!(TAP "e");
+            advance!(19);
+            press!(0, 2, true);
+            assert_read!(KEY_DOWN, "c");
+            press!(0, 2, false);
+            assert_read!(KEY_UP, "c");
+            assert_read!(NONE);
         }
     );
 }
@@ -1478,6 +2185,152 @@ c = tapdance(1,2,3,4,5,6)
     );
 }
 
+#[test]
+fn caps_word() {
+    setup!(
+        t,
+        press,
+        assert_read,
+        r#"
+[global]
+caps_word_timeout = 1000
+caps_word_keys = dot
+
+[matrix:2x3]
+0x00 = a b c
+0x10 = e f g
+
+[main]
+b = 1
+c = space
+e = dot
+g = caps_word
+"#,
+        {
+            let mut now = 100;
+
+            macro_rules! advance {
+                ($t:expr) => {
+                    now += $t;
+                    t.now = now;
+                    t.check_time();
+                };
+            }
+
+            advance!(0);
+
+            // letters are shifted; digits are not
+            press!(1, 2, TAP);
+            assert_read!(NONE);
+            press!(0, 0, TAP);
+            assert_read!(E PendingModifiers(2, true));
+            assert_read!(TAP "a");
+            press!(0, 1, TAP);
+            assert_read!(E PendingModifiers(2, false));
+            assert_read!(TAP "1");
+            press!(1, 0, TAP);
+            assert_read!(TAP "dot");
+            press!(1, 1, TAP);
+            assert_read!(E PendingModifiers(2, true));
+            assert_read!(TAP "f");
+
+            // space ends the word
+            press!(0, 2, TAP);
+            assert_read!(E PendingModifiers(2, false));
+            assert_read!(TAP "space");
+            press!(0, 0, TAP);
+            assert_read!(TAP "a");
+            assert_read!(NONE);
+            assert_eq!(t.wait_time, u64::MAX);
+
+            // idle timeout ends the word
+            press!(1, 2, TAP);
+            press!(0, 0, TAP);
+            assert_read!(E PendingModifiers(2, true));
+            assert_read!(TAP "a");
+            advance!(999);
+            assert_read!(NONE);
+            advance!(1);
+            assert_read!(E Modifiers(2, false));
+            press!(0, 0, TAP);
+            assert_read!(TAP "a");
+
+            // caps_word toggles off
+            press!(1, 2, TAP);
+            press!(1, 1, TAP);
+            assert_read!(E PendingModifiers(2, true));
+            assert_read!(TAP "f");
+            press!(1, 2, TAP);
+            assert_read!(E Modifiers(2, false));
+            press!(1, 1, TAP);
+            assert_read!(TAP "f");
+            assert_read!(NONE);
+        }
+    );
+}
+
+#[test]
+fn host_leds() {
+    setup!(
+        t,
+        press,
+        assert_read,
+        r#"
+[matrix:2x3]
+0x00 = a b c
+0x10 = e f g
+
+[main]
+a = ifled(numlock, 1, end)
+
+[nav]
+b = left
+
+[leds]
+capslock = nav
+scrolllock = shift
+"#,
+        {
+            use rpk_common::keycodes::leds;
+
+            press!(0, 1, TAP);
+            assert_read!(TAP "b");
+
+            // caps lock turns on the nav layer
+            t.set_host_leds(leds::CAPS_LOCK);
+            assert_read!(NONE);
+            press!(0, 1, TAP);
+            assert_read!(TAP "left");
+
+            // LED layers stay on after clearing all
+            t.clear_all();
+            assert_read!(E KeyEvent::Clear);
+            press!(0, 1, TAP);
+            assert_read!(TAP "left");
+
+            // modifier layers report their modifiers
+            t.set_host_leds(leds::CAPS_LOCK | leds::SCROLL_LOCK);
+            assert_read!(KEY_DOWN, "leftshift");
+            t.set_host_leds(leds::SCROLL_LOCK);
+            assert_read!(NONE);
+            press!(0, 1, TAP);
+            assert_read!(TAP "b");
+            t.set_host_leds(0);
+            assert_read!(KEY_UP, "leftshift");
+
+            // ifled releases the action it pressed
+            press!(0, 0, true);
+            assert_read!(KEY_DOWN, "end");
+            t.set_host_leds(leds::NUM_LOCK);
+            press!(0, 0, false);
+            assert_read!(KEY_UP, "end");
+            press!(0, 0, TAP);
+            assert_read!(TAP "1");
+            assert_read!(NONE);
+        }
+    );
+}
+
 #[test]
 fn modifier_macros() {
     setup!(
@@ -2112,3 +2965,199 @@ c = macro(hold(aabbccddeeff) release(fedcba))
         );
     });
 }
+
+#[test]
+fn dyn_macro_record_and_play() {
+    setup!(
+        t,
+        press,
+        assert_read,
+        r#"
+[matrix:2x3]
+0x00 = a b c
+0x10 = e f g
+
+[main]
+c = brightness_up
+f = dyn_macro_play_1
+g = dyn_macro_record_1
+"#,
+        {
+            let mut now = 100;
+
+            macro_rules! advance {
+                ($t:expr) => {
+                    now += $t;
+                    t.now = now;
+                    t.check_time();
+                };
+            }
+
+            advance!(0);
+
+            // nothing recorded yet
+            press!(1, 1, TAP);
+            assert_read!(NONE);
+
+            press!(1, 2, TAP);
+            press!(0, 0, true);
+            assert_read!(KEY_DOWN, "a");
+            advance!(30);
+            press!(0, 0, false);
+            assert_read!(KEY_UP, "a");
+            advance!(50);
+            press!(0, 2, TAP);
+            assert_read!(E KeyEvent::Consumer(0x6f));
+            assert_read!(E KeyEvent::Consumer(0));
+
+            // can't play a macro while recording
+            press!(1, 1, TAP);
+            assert_read!(NONE);
+            assert_eq!(t.dyn_macros.take_save(), None);
+
+            press!(1, 2, TAP);
+            assert_eq!(t.dyn_macros.take_save(), Some(0));
+
+            assert_eq!(
+                t.dyn_macro(0),
+                &[
+                    kc!("a"),
+                    dyn_macro_items::DELAY | 30,
+                    dyn_macro_items::RELEASE | kc!("a"),
+                    dyn_macro_items::DELAY | 50,
+                    kc!("brightness_up"),
+                    dyn_macro_items::RELEASE | kc!("brightness_up"),
+                ]
+            );
+
+            advance!(1000);
+            press!(1, 1, TAP);
+            assert_read!(KEY_DOWN, "a");
+            assert_read!(E KeyEvent::Delay(30));
+            assert_read!(KEY_UP, "a");
+            assert_read!(E KeyEvent::Delay(50));
+            assert_read!(E KeyEvent::Consumer(0x6f));
+            assert_read!(E KeyEvent::Consumer(0));
+            assert_read!(NONE);
+        }
+    );
+}
+
+#[test]
+fn dyn_macro_releases_keys_down() {
+    setup!(
+        t,
+        press,
+        assert_read,
+        r#"
+[matrix:2x3]
+0x00 = a b c
+0x10 = e f g
+
+[main]
+b = leftshift
+f = dyn_macro_play_1
+g = dyn_macro_record_1
+"#,
+        {
+            // keys down when recording stops are released
+            press!(1, 2, TAP);
+            press!(0, 1, true);
+            assert_read!(KEY_DOWN, "leftshift");
+            press!(1, 2, TAP);
+            assert_eq!(
+                t.dyn_macro(0),
+                &[
+                    kc!("leftshift"),
+                    dyn_macro_items::RELEASE | kc!("leftshift")
+                ]
+            );
+            press!(0, 1, false);
+            assert_read!(KEY_UP, "leftshift");
+
+            // a full recording ends leaving room to release the keys down
+            press!(1, 2, TAP);
+            press!(0, 1, true);
+            assert_read!(KEY_DOWN, "leftshift");
+            for _ in 0..200 {
+                press!(0, 0, TAP);
+                assert_read!(TAP "a");
+            }
+            press!(1, 2, TAP);
+            assert_eq!(t.dyn_macros.take_save(), Some(0));
+            press!(0, 1, false);
+            assert_read!(KEY_UP, "leftshift");
+
+            let items = t.dyn_macro(0).to_vec();
+            let taps = (items.len() - 2) / 2;
+            assert!(items.len() <= dyn_macro::MAX_LEN && taps > 100);
+            assert_eq!(items[0], kc!("leftshift"));
+            assert_eq!(
+                items[items.len() - 1],
+                dyn_macro_items::RELEASE | kc!("leftshift")
+            );
+
+            press!(1, 1, TAP);
+            assert_read!(KEY_DOWN, "leftshift");
+            for _ in 0..taps {
+                t.simulate_step(t.now);
+                assert_read!(TAP "a");
+            }
+            t.simulate_step(t.now);
+            assert_read!(KEY_UP, "leftshift");
+            t.simulate_step(t.now);
+            assert_read!(NONE);
+        }
+    );
+}
+
+#[test]
+fn dyn_macro_load() {
+    setup!(
+        t,
+        press,
+        assert_read,
+        r#"
+[matrix:2x3]
+0x00 = a b c
+0x10 = e f g
+
+[main]
+e = dyn_macro_play_3
+f = dyn_macro_play_1
+g = dyn_macro_play_2
+"#,
+        {
+            t.load_dyn_macro(
+                1,
+                [
+                    kc!("leftshift"),
+                    kc!("b"),
+                    dyn_macro_items::RELEASE | kc!("b"),
+                    dyn_macro_items::RELEASE | kc!("leftshift"),
+                ],
+            );
+            press!(1, 2, TAP);
+            assert_read!(KEY_DOWN, "leftshift");
+            assert_read!(TAP "b");
+            assert_read!(KEY_UP, "leftshift");
+            assert_read!(NONE);
+            assert_eq!(t.dyn_macros.take_load(), None);
+
+            // any other macro is loaded before it is played
+            press!(1, 1, TAP);
+            assert_read!(NONE);
+            assert_eq!(t.dyn_macros.take_load(), Some(0));
+            t.load_dyn_macro(0, [kc!("c"), dyn_macro_items::RELEASE | kc!("c")]);
+            assert_read!(TAP "c");
+            assert_read!(NONE);
+            assert!(t.dyn_macro(1).is_empty());
+
+            // a macro which was never recorded plays nothing
+            press!(1, 0, TAP);
+            assert_eq!(t.dyn_macros.take_load(), Some(2));
+            t.load_dyn_macro(2, []);
+            assert_read!(NONE);
+        }
+    );
+}
@@ -0,0 +1,296 @@
+use embassy_futures::{
+    join::join,
+    select::{Either, select},
+};
+use embassy_sync::{
+    blocking_mutex::raw::{NoopRawMutex, RawMutex},
+    signal::Signal,
+};
+use embedded_io_async::{Read, Write};
+
+use crate::{
+    key_scanner::{KeyScannerChannel, ScanKey},
+    warn,
+};
+
+/// The first byte of every frame sent over the link.
+const SYNC: u8 = 0xa5;
+pub const FRAME_LEN: usize = 4;
+
+/// The keys of each half are tracked for the first `MAX_ROWS` rows and 32 columns.
+const MAX_ROWS: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FrameError {
+    BadChecksum,
+}
+
+/// CRC-8 (polynomial 0x07) of a frame's row and column.
+fn checksum(row: u8, col: u8) -> u8 {
+    let mut crc = 0u8;
+    for byte in [row, col] {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Sent by the secondary after the keys it resends on a resync.
+fn resync_end() -> ScanKey {
+    ScanKey::from_memo(0xff00)
+}
+
+/// Frame a key for the link. A [`ScanKey::none`] frame is a resync: sent by the primary it asks
+/// the secondary to resend its state; sent by the secondary the keys still down follow, ended by a
+/// [`resync_end`] frame.
+pub fn encode(key: ScanKey) -> [u8; FRAME_LEN] {
+    let (row, col) = key.as_memo_bytes();
+    [SYNC, row, col, checksum(row, col)]
+}
+
+/// Decodes frames from the bytes received over the link.
+#[derive(Default)]
+pub struct FrameDecoder {
+    buf: [u8; FRAME_LEN],
+    len: usize,
+}
+impl FrameDecoder {
+    /// Add a received byte. Returns the key once its frame is complete. A frame with a bad
+    /// checksum is dropped and decoding resumes from the next [`SYNC`] byte within it.
+    pub fn push(&mut self, byte: u8) -> Option<Result<ScanKey, FrameError>> {
+        if self.len == 0 && byte != SYNC {
+            return None;
+        }
+        self.buf[self.len] = byte;
+        self.len += 1;
+        if self.len < FRAME_LEN {
+            return None;
+        }
+
+        let [_, row, col, crc] = self.buf;
+        if crc == checksum(row, col) {
+            self.len = 0;
+            return Some(Ok(ScanKey::from_memo(row as u16 | (col as u16) << 8)));
+        }
+
+        self.len = match self.buf[1..].iter().position(|b| *b == SYNC) {
+            Some(i) => {
+                self.buf.copy_within(i + 1.., 0);
+                FRAME_LEN - 1 - i
+            }
+            None => 0,
+        };
+        Some(Err(FrameError::BadChecksum))
+    }
+}
+
+/// The keys down on the secondary half.
+#[derive(Default)]
+struct KeySet([u32; MAX_ROWS]);
+impl KeySet {
+    fn set(&mut self, key: ScanKey) {
+        let (row, col) = (key.row(), key.column());
+        if row < MAX_ROWS && col < 32 {
+            if key.is_down() {
+                self.0[row] |= 1 << col;
+            } else {
+                self.0[row] &= !(1 << col);
+            }
+        }
+    }
+
+    fn contains(&self, key: ScanKey) -> bool {
+        let (row, col) = (key.row(), key.column());
+        row < MAX_ROWS && col < 32 && self.0[row] & (1 << col) != 0
+    }
+
+    /// The keys in this set which are not in `other`.
+    fn difference(&self, other: &KeySet) -> KeySet {
+        let mut set = KeySet::default();
+        for (i, bits) in set.0.iter_mut().enumerate() {
+            *bits = self.0[i] & !other.0[i];
+        }
+        set
+    }
+
+    fn iter(&self) -> impl Iterator<Item = ScanKey> {
+        self.0.iter().enumerate().flat_map(|(row, bits)| {
+            (0..32)
+                .filter(move |col| bits & (1 << col) != 0)
+                .map(move |col| ScanKey::new(row as u8, col, true))
+        })
+    }
+}
+
+async fn read_frames<R: Read>(
+    mut rx: R,
+    mut on_frame: impl AsyncFnMut(Result<ScanKey, FrameError>),
+) -> ! {
+    let mut decoder = FrameDecoder::default();
+    let mut buf = [0; 16];
+    loop {
+        match rx.read(&mut buf).await {
+            Ok(len) => {
+                for byte in &buf[..len] {
+                    if let Some(frame) = decoder.push(*byte) {
+                        on_frame(frame).await;
+                    }
+                }
+            }
+            Err(_) => warn!("Split link read failed"),
+        }
+    }
+}
+
+async fn write_frame<W: Write>(tx: &mut W, key: ScanKey) {
+    if tx.write_all(&encode(key)).await.is_err() || tx.flush().await.is_err() {
+        warn!("Split link write failed");
+    }
+}
+
+/// Runs on the half connected to the host. Keys from the secondary half are moved by the offsets
+/// and sent to the primary's [`KeyScannerChannel`]; unless they fall outside the primary's
+/// `row_count` by `col_count` matrix.
+pub struct Primary<'c, M: RawMutex, const PS: usize> {
+    channel: &'c KeyScannerChannel<M, PS>,
+    row_offset: u8,
+    col_offset: u8,
+    row_count: u8,
+    col_count: u8,
+}
+impl<'c, M: RawMutex, const PS: usize> Primary<'c, M, PS> {
+    pub fn new(
+        channel: &'c KeyScannerChannel<M, PS>,
+        row_offset: u8,
+        col_offset: u8,
+        row_count: u8,
+        col_count: u8,
+    ) -> Self {
+        Self {
+            channel,
+            row_offset,
+            col_offset,
+            row_count,
+            col_count,
+        }
+    }
+
+    fn merged(&self, key: ScanKey) -> Option<ScanKey> {
+        let row = (key.row() as u8)
+            .checked_add(self.row_offset)
+            .filter(|r| *r < self.row_count)?;
+        let col = (key.column() as u8)
+            .checked_add(self.col_offset)
+            .filter(|c| *c < self.col_count)?;
+        Some(ScanKey::new(row, col, key.is_down()))
+    }
+
+    async fn send(&self, key: ScanKey) {
+        match self.merged(key) {
+            Some(key) => self.channel.send(key).await,
+            None => warn!(
+                "Split key outside the matrix; row {} col {}",
+                key.row(),
+                key.column()
+            ),
+        }
+    }
+
+    pub async fn run<R: Read, W: Write>(&self, rx: R, mut tx: W) -> ! {
+        let resync = Signal::<NoopRawMutex, ()>::new();
+        let mut down = KeySet::default();
+        // the keys resent by the secondary during a resync
+        let mut resent: Option<KeySet> = None;
+
+        let reader = read_frames(rx, async |frame| match frame {
+            Ok(key) if key == resync_end() => {
+                // only keys released while the link was broken are sent; held keys are unchanged
+                if let Some(resent) = resent.take() {
+                    for mut key in down.difference(&resent).iter() {
+                        key.set_down(false);
+                        down.set(key);
+                        self.send(key).await;
+                    }
+                }
+            }
+            Ok(key) if key.is_none() => resent = Some(KeySet::default()),
+            Ok(key) => {
+                if let Some(resent) = resent.as_mut() {
+                    resent.set(key);
+                }
+                if down.contains(key) != key.is_down() {
+                    down.set(key);
+                    self.send(key).await;
+                }
+            }
+            Err(_) => {
+                // the resent state may be missing keys; wait for the next resync
+                resent = None;
+                resync.signal(());
+            }
+        });
+
+        let writer = async {
+            loop {
+                write_frame(&mut tx, ScanKey::none()).await;
+                resync.wait().await;
+            }
+        };
+
+        join(reader, writer).await.0
+    }
+}
+
+/// Runs on the half not connected to the host. Keys from its [`KeyScannerChannel`] are sent to the
+/// primary half.
+pub struct Secondary<'c, M: RawMutex, const PS: usize> {
+    channel: &'c KeyScannerChannel<M, PS>,
+}
+impl<'c, M: RawMutex, const PS: usize> Secondary<'c, M, PS> {
+    pub fn new(channel: &'c KeyScannerChannel<M, PS>) -> Self {
+        Self { channel }
+    }
+
+    pub async fn run<R: Read, W: Write>(&self, rx: R, mut tx: W) -> ! {
+        let resync = Signal::<NoopRawMutex, ()>::new();
+        resync.signal(());
+
+        let reader = read_frames(rx, async |frame| {
+            if matches!(frame, Ok(key) if key.is_none()) {
+                resync.signal(());
+            }
+        });
+
+        let writer = async {
+            let mut down = KeySet::default();
+            loop {
+                match select(self.channel.receive(), resync.wait()).await {
+                    Either::First(key) => {
+                        down.set(key);
+                        write_frame(&mut tx, key).await;
+                    }
+                    Either::Second(()) => {
+                        write_frame(&mut tx, ScanKey::none()).await;
+                        for key in down.iter() {
+                            write_frame(&mut tx, key).await;
+                        }
+                        write_frame(&mut tx, resync_end()).await;
+                    }
+                }
+            }
+        };
+
+        join(reader, writer).await.0
+    }
+}
+
+#[cfg(test)]
+#[path = "split_test.rs"]
+mod test;
@@ -0,0 +1,139 @@
+extern crate std;
+
+use embassy_futures::{
+    block_on,
+    select::{Either3, select3},
+};
+use embassy_sync::pipe::Pipe;
+use embassy_time::Timer;
+
+use super::*;
+
+type Link = Pipe<NoopRawMutex, 64>;
+
+#[test]
+fn encode_decode() {
+    let mut decoder = FrameDecoder::default();
+    let key = ScanKey::new(3, 7, true);
+    let frame = encode(key);
+    assert_eq!(frame[0], SYNC);
+
+    assert_eq!(decoder.push(0), None);
+    assert_eq!(decoder.push(frame[0]), None);
+    assert_eq!(decoder.push(frame[1]), None);
+    assert_eq!(decoder.push(frame[2]), None);
+    assert_eq!(decoder.push(frame[3]), Some(Ok(key)));
+
+    let frame = encode(ScanKey::none());
+    assert_eq!(
+        frame.iter().filter_map(|b| decoder.push(*b)).next(),
+        Some(Ok(ScanKey::none()))
+    );
+}
+
+#[test]
+fn resync_after_bad_checksum() {
+    let mut decoder = FrameDecoder::default();
+    let key = ScanKey::new(1, 2, false);
+
+    // a frame with a lost byte runs into the next frame
+    let mut bytes = std::vec::Vec::from(&encode(ScanKey::new(4, 5, true))[..3]);
+    bytes.extend_from_slice(&encode(key));
+
+    let frames: std::vec::Vec<_> = bytes.iter().filter_map(|b| decoder.push(*b)).collect();
+    assert_eq!(frames, [Err(FrameError::BadChecksum), Ok(key)]);
+
+    // a SYNC byte inside a bad frame starts the next frame
+    let mut decoder = FrameDecoder::default();
+    let key = ScanKey::new(0x25, 1, true);
+    let mut bytes = std::vec![SYNC];
+    bytes.extend_from_slice(&encode(key));
+    let frames: std::vec::Vec<_> = bytes.iter().filter_map(|b| decoder.push(*b)).collect();
+    assert_eq!(frames, [Err(FrameError::BadChecksum), Ok(key)]);
+}
+
+#[test]
+fn key_set() {
+    let mut set = KeySet::default();
+    set.set(ScanKey::new(2, 31, true));
+    set.set(ScanKey::new(0, 1, true));
+    set.set(ScanKey::new(40, 1, true));
+    set.set(ScanKey::new(0, 3, true));
+    set.set(ScanKey::new(0, 3, false));
+
+    let keys: std::vec::Vec<_> = set.iter().collect();
+    assert_eq!(keys, [ScanKey::new(0, 1, true), ScanKey::new(2, 31, true)]);
+
+    assert!(set.contains(ScanKey::new(0, 1, true)));
+    assert!(!set.contains(ScanKey::new(0, 3, true)));
+    assert!(!set.contains(ScanKey::new(40, 1, true)));
+
+    let mut other = KeySet::default();
+    other.set(ScanKey::new(0, 1, true));
+    let keys: std::vec::Vec<_> = set.difference(&other).iter().collect();
+    assert_eq!(keys, [ScanKey::new(2, 31, true)]);
+}
+
+#[test]
+fn primary_and_secondary() {
+    let to_primary = Link::new();
+    let to_secondary = Link::new();
+    let primary_channel = KeyScannerChannel::<NoopRawMutex, 16>::default();
+    let secondary_channel = KeyScannerChannel::<NoopRawMutex, 16>::default();
+
+    let primary = Primary::new(&primary_channel, 0, 6, 4, 12);
+    let secondary = Secondary::new(&secondary_channel);
+
+    block_on(async {
+        let test = async {
+            secondary_channel.send(ScanKey::new(1, 2, true)).await;
+            assert_eq!(primary_channel.receive().await, ScanKey::new(1, 8, true));
+            secondary_channel.send(ScanKey::new(3, 0, true)).await;
+            assert_eq!(primary_channel.receive().await, ScanKey::new(3, 6, true));
+            secondary_channel.send(ScanKey::new(1, 2, false)).await;
+            assert_eq!(primary_channel.receive().await, ScanKey::new(1, 8, false));
+
+            // keys outside the matrix are dropped
+            to_primary.write(&encode(ScanKey::new(0, 250, true))).await;
+            to_primary.write(&encode(ScanKey::new(9, 0, true))).await;
+            secondary_channel.send(ScanKey::new(1, 6, true)).await;
+            secondary_channel.send(ScanKey::new(2, 5, true)).await;
+            assert_eq!(primary_channel.receive().await, ScanKey::new(2, 11, true));
+            secondary_channel.send(ScanKey::new(2, 5, false)).await;
+            assert_eq!(primary_channel.receive().await, ScanKey::new(2, 11, false));
+
+            // corrupt the link; the primary asks for the state of the secondary and the key held
+            // across the resync is unchanged
+            to_primary.write(&[SYNC, 0x81, 4, 0]).await;
+            if let Either::First(key) =
+                select(primary_channel.receive(), Timer::after_millis(10)).await
+            {
+                panic!("Unexpected key {key:?}");
+            }
+
+            // keys missing from the resent state were released; new keys were pressed
+            to_primary.write(&encode(ScanKey::none())).await;
+            to_primary.write(&encode(ScanKey::new(0, 1, true))).await;
+            to_primary.write(&encode(resync_end())).await;
+            assert_eq!(primary_channel.receive().await, ScanKey::new(0, 7, true));
+            assert_eq!(primary_channel.receive().await, ScanKey::new(3, 6, false));
+
+            if let Either::First(key) =
+                select(primary_channel.receive(), Timer::after_millis(10)).await
+            {
+                panic!("Unexpected key {key:?}");
+            }
+        };
+
+        match select3(
+            primary.run(&to_primary, &to_secondary),
+            secondary.run(&to_secondary, &to_primary),
+            select(test, Timer::after_millis(500)),
+        )
+        .await
+        {
+            Either3::Third(Either::First(())) => {}
+            Either3::Third(Either::Second(())) => panic!("Timed out"),
+        }
+    });
+}
@@ -1,16 +1,21 @@
 use core::{
     mem::MaybeUninit,
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
 };
+use embassy_futures::select::{Either, select};
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, signal::Signal};
 use embassy_usb::{
-    Builder, Config, Handler,
+    Builder, Config, Handler, UsbDevice,
     class::hid::{ReportId, RequestHandler},
     control::{InResponse, OutResponse, Recipient, Request, RequestType},
     driver::Driver,
     types::InterfaceNumber,
 };
 
-use crate::hid::{HidReader, HidWriter};
+use crate::{
+    hid::{HidReader, HidWriter},
+    warn,
+};
 
 // HID
 const HID_DESC_DESCTYPE_HID: u8 = 0x21;
@@ -25,6 +30,9 @@ const HID_REQ_SET_REPORT: u8 = 0x09;
 const HID_REQ_GET_PROTOCOL: u8 = 0x03;
 const HID_REQ_SET_PROTOCOL: u8 = 0x0b;
 
+const HID_PROTOCOL_BOOT: u16 = 0;
+const HID_PROTOCOL_REPORT: u16 = 1;
+
 #[rustfmt::skip]
 pub const SHARED_REPORT_DESC: [u8; 59 + 73 + 25 + 25] = [
     // NKRO_DESC [u8; 59]
@@ -147,6 +155,7 @@ pub const SHARED_REPORT_DESC: [u8; 59 + 73 + 25 + 25] = [
 pub struct State<'d> {
     control: MaybeUninit<Control<'d>>,
     out_report_offset: AtomicUsize,
+    boot_protocol: AtomicBool,
 }
 impl Default for State<'_> {
     fn default() -> Self {
@@ -159,6 +168,83 @@ impl State<'_> {
         State {
             control: MaybeUninit::uninit(),
             out_report_offset: AtomicUsize::new(0),
+            boot_protocol: AtomicBool::new(false),
+        }
+    }
+}
+
+/// The suspend state of the USB bus, shared by the USB device, the key scanner and the mapper.
+#[derive(Default)]
+pub struct BusState {
+    suspended: AtomicBool,
+    remote_wakeup_enabled: AtomicBool,
+    wakeup: Signal<NoopRawMutex, ()>,
+}
+impl BusState {
+    pub const fn new() -> Self {
+        Self {
+            suspended: AtomicBool::new(false),
+            remote_wakeup_enabled: AtomicBool::new(false),
+            wakeup: Signal::new(),
+        }
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.load(Ordering::Acquire)
+    }
+
+    /// Ask the host to resume the bus; ignored unless the bus is suspended and the host has enabled
+    /// remote wakeup.
+    pub fn request_wakeup(&self) {
+        if self.is_suspended() && self.remote_wakeup_enabled.load(Ordering::Acquire) {
+            self.wakeup.signal(());
+        }
+    }
+
+    #[cfg(test)]
+    pub fn try_take_wakeup(&self) -> bool {
+        self.wakeup.try_take().is_some()
+    }
+}
+
+/// Keeps a [`BusState`] up to date with the events of the USB device.
+pub struct BusHandler<'d>(&'d BusState);
+impl<'d> BusHandler<'d> {
+    pub fn new(state: &'d BusState) -> Self {
+        Self(state)
+    }
+}
+impl Handler for BusHandler<'_> {
+    fn enabled(&mut self, enabled: bool) {
+        if !enabled {
+            self.suspended(false);
+            self.remote_wakeup_enabled(false);
+        }
+    }
+
+    fn suspended(&mut self, suspended: bool) {
+        self.0.suspended.store(suspended, Ordering::Release);
+        if !suspended {
+            self.0.wakeup.reset();
+        }
+    }
+
+    fn remote_wakeup_enabled(&mut self, enabled: bool) {
+        self.0
+            .remote_wakeup_enabled
+            .store(enabled, Ordering::Release);
+    }
+}
+
+/// Run the USB device. While the bus is suspended the host is woken when
+/// [`BusState::request_wakeup`] is called.
+pub async fn run_device<'d, D: Driver<'d>>(usb: &mut UsbDevice<'d, D>, bus: &BusState) -> ! {
+    loop {
+        usb.run_until_suspend().await;
+        if let Either::Second(()) = select(usb.wait_resume(), bus.wakeup.wait()).await
+            && let Err(e) = usb.remote_wakeup().await
+        {
+            warn!("Remote wakeup failed: {:?}", e);
         }
     }
 }
@@ -262,12 +348,15 @@ impl<'d> Configurator<'d> {
             descriptor,
             None, // TODO  &self.request_handler,
             &state.out_report_offset,
+            &state.boot_protocol,
         );
         let control = state.control.write(control);
         builder.handler(control);
         (
-            HidWriter::new(ep_in),
-            ep_out.map(|ep_out| HidReader::new(ep_out, &state.out_report_offset)),
+            HidWriter::new(ep_in, &state.boot_protocol),
+            ep_out.map(|ep_out| {
+                HidReader::new(ep_out, &state.out_report_offset, &state.boot_protocol)
+            }),
         )
     }
 }
@@ -277,6 +366,7 @@ struct Control<'d> {
     report_descriptor: &'d [u8],
     request_handler: Option<&'d mut dyn RequestHandler>,
     out_report_offset: &'d AtomicUsize,
+    boot_protocol: &'d AtomicBool,
     hid_descriptor: [u8; 9],
 }
 impl<'d> Control<'d> {
@@ -285,12 +375,14 @@ impl<'d> Control<'d> {
         report_descriptor: &'d [u8],
         request_handler: Option<&'d mut dyn RequestHandler>,
         out_report_offset: &'d AtomicUsize,
+        boot_protocol: &'d AtomicBool,
     ) -> Self {
         Control {
             if_num,
             report_descriptor,
             request_handler,
             out_report_offset,
+            boot_protocol,
             hid_descriptor: [
                 9,                                           // Length of buf inclusive of size prefix
                 HID_DESC_DESCTYPE_HID,                       // Descriptor type
@@ -308,6 +400,7 @@ impl<'d> Control<'d> {
 impl Handler for Control<'_> {
     fn reset(&mut self) {
         self.out_report_offset.store(0, Ordering::Release);
+        self.boot_protocol.store(false, Ordering::Release);
     }
 
     fn control_out(&mut self, req: Request, data: &[u8]) -> Option<OutResponse> {
@@ -339,14 +432,14 @@ impl Handler for Control<'_> {
                     _ => Some(OutResponse::Rejected),
                 }
             }
-            HID_REQ_SET_PROTOCOL => {
-                if req.value == 1 {
+            HID_REQ_SET_PROTOCOL => match req.value {
+                HID_PROTOCOL_BOOT | HID_PROTOCOL_REPORT => {
+                    self.boot_protocol
+                        .store(req.value == HID_PROTOCOL_BOOT, Ordering::Release);
                     Some(OutResponse::Accepted)
-                } else {
-                    crate::warn!("HID Boot Protocol is unsupported.");
-                    Some(OutResponse::Rejected) // UNSUPPORTED: Boot Protocol
                 }
-            }
+                _ => Some(OutResponse::Rejected),
+            },
             _ => Some(OutResponse::Rejected),
         }
     }
@@ -401,8 +494,11 @@ impl Handler for Control<'_> {
                         }
                     }
                     HID_REQ_GET_PROTOCOL => {
-                        // UNSUPPORTED: Boot Protocol
-                        buf[0] = 1;
+                        buf[0] = if self.boot_protocol.load(Ordering::Acquire) {
+                            HID_PROTOCOL_BOOT
+                        } else {
+                            HID_PROTOCOL_REPORT
+                        } as u8;
                         Some(InResponse::Accepted(&buf[0..1]))
                     }
                     _ => Some(InResponse::Rejected),
@@ -421,3 +517,7 @@ const fn report_id_try_from(value: u16) -> Result<ReportId, ()> {
         _ => Err(()),
     }
 }
+
+#[cfg(test)]
+#[path = "usb_test.rs"]
+mod test;
@@ -0,0 +1,81 @@
+use embassy_usb::driver::Direction;
+
+use super::*;
+
+fn protocol_request(direction: Direction, request: u8, value: u16) -> Request {
+    Request {
+        direction,
+        request_type: RequestType::Class,
+        recipient: Recipient::Interface,
+        request,
+        value,
+        index: 2,
+        length: 1,
+    }
+}
+
+fn get_protocol(control: &mut Control) -> u8 {
+    let mut buf = [9; 1];
+    let req = protocol_request(Direction::In, HID_REQ_GET_PROTOCOL, 0);
+    match control.control_in(req, &mut buf) {
+        Some(InResponse::Accepted(data)) => data[0],
+        _ => panic!("Get protocol rejected"),
+    }
+}
+
+#[test]
+fn set_protocol() {
+    let offset = AtomicUsize::new(0);
+    let boot_protocol = AtomicBool::new(false);
+    let mut control = Control::new(InterfaceNumber(2), &[], None, &offset, &boot_protocol);
+
+    assert_eq!(get_protocol(&mut control), 1);
+
+    let req = protocol_request(Direction::Out, HID_REQ_SET_PROTOCOL, 0);
+    assert_eq!(control.control_out(req, &[]), Some(OutResponse::Accepted));
+    assert!(boot_protocol.load(Ordering::Acquire));
+    assert_eq!(get_protocol(&mut control), 0);
+
+    let req = protocol_request(Direction::Out, HID_REQ_SET_PROTOCOL, 2);
+    assert_eq!(control.control_out(req, &[]), Some(OutResponse::Rejected));
+    assert_eq!(get_protocol(&mut control), 0);
+
+    control.reset();
+    assert_eq!(get_protocol(&mut control), 1);
+
+    let req = protocol_request(Direction::Out, HID_REQ_SET_PROTOCOL, 0);
+    control.control_out(req, &[]);
+    let req = protocol_request(Direction::Out, HID_REQ_SET_PROTOCOL, 1);
+    assert_eq!(control.control_out(req, &[]), Some(OutResponse::Accepted));
+    assert!(!boot_protocol.load(Ordering::Acquire));
+}
+
+#[test]
+fn bus_state() {
+    let bus = BusState::new();
+    let mut handler = BusHandler::new(&bus);
+
+    bus.request_wakeup();
+    assert!(!bus.try_take_wakeup());
+
+    handler.suspended(true);
+    assert!(bus.is_suspended());
+    bus.request_wakeup();
+    assert!(!bus.try_take_wakeup(), "remote wakeup not enabled");
+
+    handler.remote_wakeup_enabled(true);
+    bus.request_wakeup();
+    handler.suspended(false);
+    assert!(!bus.is_suspended());
+    assert!(!bus.try_take_wakeup(), "resumed clears wakeup");
+
+    handler.suspended(true);
+    bus.request_wakeup();
+    assert!(bus.try_take_wakeup());
+
+    handler.enabled(false);
+    assert!(!bus.is_suspended());
+    handler.suspended(true);
+    bus.request_wakeup();
+    assert!(!bus.try_take_wakeup());
+}
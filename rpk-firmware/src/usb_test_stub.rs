@@ -20,6 +20,10 @@ impl MessageChannel {
         self.0.try_receive().unwrap()
     }
 
+    pub fn try_get(&self) -> Option<Vec<u8>> {
+        self.0.try_receive().ok()
+    }
+
     pub async fn receive(&self) -> Vec<u8> {
         self.0.receive().await
     }
@@ -64,10 +68,11 @@ impl Default for MyEndpointIn {
 
 pub struct MyEndpointOut {
     pub messages: Channel<NoopRawMutex, Vec<u8>, 10>,
+    pub info: EndpointInfo,
 }
 impl Endpoint for MyEndpointOut {
     fn info(&self) -> &EndpointInfo {
-        unimplemented!()
+        &self.info
     }
 
     async fn wait_enabled(&mut self) {}
@@ -84,6 +89,12 @@ impl Default for MyEndpointOut {
     fn default() -> Self {
         Self {
             messages: Channel::new(),
+            info: EndpointInfo {
+                addr: EndpointAddress::from(0),
+                ep_type: EndpointType::Interrupt,
+                max_packet_size: MAX_BULK_LEN,
+                interval_ms: 1,
+            },
         }
     }
 }
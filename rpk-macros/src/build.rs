@@ -3,6 +3,7 @@ use quote::{ToTokens, quote};
 use rpk_config::{
     ConfigError,
     compiler::{KeyboardConfig, SourceRange, compile},
+    keycodes,
 };
 use std::{
     env,
@@ -54,6 +55,28 @@ fn compile_error(message: &str) -> TokenStream {
             };
         }
 
+        macro_rules! config_indicator_pins {
+            (peripherals: $p:ident) => {
+                []
+            };
+        }
+
+        macro_rules! config_split_secondary {
+            ($($t:tt)*) => {};
+        }
+
+        macro_rules! run_split_primary {
+            (peripherals: $p:ident, $channel:expr, $run:expr) => {
+                $run.await
+            };
+        }
+
+        macro_rules! config_encoder_pins {
+            (peripherals: $p:ident) => {
+                ([], [])
+            };
+        }
+
         const LAYOUT_MAPPING: &[u16] = &[];
 
         const INPUT_N: usize = 0;
@@ -61,7 +84,12 @@ fn compile_error(message: &str) -> TokenStream {
         const ROW_COUNT: usize = 0;
         const COL_COUNT: usize = 0;
         const ROW_IS_OUTPUT: bool = true;
+        const DIRECT_PINS: bool = false;
         const LAYOUT_MAX: usize = 0;
+        const INDICATOR_N: usize = 0;
+        static INDICATORS: [rpk_builder::indicator::Indicator; 0] = [];
+        const ENCODER_N: usize = 0;
+        static ENCODER_KEYS: [[u16; 2]; 0] = [];
 
         const FLASH_SIZE: usize = 0;
         const FS_BASE: usize = 0;
@@ -131,13 +159,19 @@ fn quote_conf(source_file: &Path) -> Result<TokenStream> {
     let config = compile(PathBuf::from(source_file), source.as_str())
         .map_err(|e| BuildError::compile_err(e, source_file, source.as_str()))?;
 
-    let (defs, input_pins, output_pins) = parse_firmware(&config)?;
+    let (defs, input_pins, output_pins, indicator_pins, split_macros) = parse_firmware(&config)?;
+    let Encoders {
+        a_pins: encoder_a_pins,
+        b_pins: encoder_b_pins,
+        ..
+    } = parse_encoders(&config)?;
 
     let macros = quote! {
         macro_rules! config_matrix_pins_rp {
-            (peripherals: $p:ident, input: [$($in_pin:ident), *], output: [$($out_pin:ident), +]) => {
+            (peripherals: $p:ident, input: [$($in_pin:ident), *], output: [$($out_pin:ident), *]) => {
                 {
-                    let mut output_pins = [$(Output::new($p.$out_pin, gpio::Level::High)), +];
+                    let mut output_pins: [Output; OUTPUT_N] =
+                        [$(Output::new($p.$out_pin, gpio::Level::High)), *];
                     let input_pins = [$(Input::new($p.$in_pin, gpio::Pull::Up)), +];
                     output_pins.iter_mut().for_each(|p| {
                         p.set_high();
@@ -153,6 +187,27 @@ fn quote_conf(source_file: &Path) -> Result<TokenStream> {
                     input: #input_pins, output: #output_pins)
             };
         }
+
+        macro_rules! config_indicator_pins_rp {
+            (peripherals: $p:ident, output: [$($pin:ident), *]) => {
+                [$(Output::new($p.$pin, gpio::Level::Low)), *]
+            };
+        }
+
+        macro_rules! config_indicator_pins {
+            (peripherals: $p:ident) => {
+                config_indicator_pins_rp!(peripherals: $p, output: #indicator_pins)
+            };
+        }
+
+        macro_rules! config_encoder_pins {
+            (peripherals: $p:ident) => {
+                (
+                    [#(Input::new($p.#encoder_a_pins, gpio::Pull::Up)),*],
+                    [#(Input::new($p.#encoder_b_pins, gpio::Pull::Up)),*],
+                )
+            };
+        }
     };
 
     let source_file = source_file.display().to_string();
@@ -160,6 +215,7 @@ fn quote_conf(source_file: &Path) -> Result<TokenStream> {
     let result = quote! {
         #defs
         #macros
+        #split_macros
 
         const fn max32(a: u32, b: u32) -> u32 {
             if a < b {
@@ -227,7 +283,278 @@ fn syn_bool(input: &TokenStream) -> Result<bool> {
     }
 }
 
-fn parse_firmware(config: &KeyboardConfig) -> Result<(TokenStream, TokenStream, TokenStream)> {
+/// The indicator for each name in the `indicators` firmware config; which is one of a host LED
+/// name, `oneshot` or a layer name.
+fn parse_indicators(config: &KeyboardConfig, pin_count: usize) -> Result<Vec<TokenStream>> {
+    let Some(vr) = config.firmware_get("indicators") else {
+        if pin_count == 0 {
+            return Ok(Vec::new());
+        }
+        return Err(BuildError::from_str(
+            "Missing required firmware config: indicators",
+        ));
+    };
+    let error = |message: String| {
+        BuildError::compile_err(
+            ConfigError::new(message, vr.start..vr.end),
+            config.path.as_path(),
+            config.source,
+        )
+    };
+
+    let text = config.trim_value(&vr);
+    let text = text
+        .strip_prefix('[')
+        .and_then(|t| t.strip_suffix(']'))
+        .ok_or_else(|| error("Expected [<indicator>, ...]".into()))?;
+
+    let indicators = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|n| !n.is_empty())
+        .map(|name| {
+            if let Some(led) = keycodes::led_bit(name) {
+                Ok(quote! { rpk_builder::indicator::Indicator::HostLed(#led) })
+            } else if name == "oneshot" {
+                Ok(quote! { rpk_builder::indicator::Indicator::Oneshot })
+            } else {
+                match config.get_layer_index(name) {
+                    Some(layer) if layer < 32 => {
+                        Ok(quote! { rpk_builder::indicator::Indicator::Layer(#layer) })
+                    }
+                    Some(_) => Err(error(format!(
+                        "Layer {name} can't be an indicator; only the first 32 layers can"
+                    ))),
+                    None => Err(error(format!("Unknown indicator {name}"))),
+                }
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if indicators.len() != pin_count {
+        return Err(error(format!(
+            "Expected {pin_count} indicators; one for each indicator pin"
+        )));
+    }
+
+    Ok(indicators)
+}
+
+/// The rotary encoders of the `encoders` firmware config.
+#[derive(Default)]
+struct Encoders {
+    a_pins: Vec<syn::Expr>,
+    b_pins: Vec<syn::Expr>,
+    /// The clockwise and counter-clockwise matrix positions of each encoder; which are named
+    /// `enc<n>_cw` and `enc<n>_ccw`.
+    keys: Vec<[u16; 2]>,
+}
+
+fn parse_encoders(config: &KeyboardConfig) -> Result<Encoders> {
+    let Some(vr) = config.firmware_get("encoders") else {
+        return Ok(Default::default());
+    };
+    let error = |message: String| {
+        BuildError::compile_err(
+            ConfigError::new(message, vr.start..vr.end),
+            config.path.as_path(),
+            config.source,
+        )
+    };
+
+    let expected = || error("Expected [(<pin a>, <pin b>), ...]".into());
+    let array: syn::ExprArray = syn::parse_str(config.trim_value(&vr)).map_err(|_| expected())?;
+
+    let mut encoders = Encoders::default();
+    for (i, elem) in array.elems.into_iter().enumerate() {
+        let syn::Expr::Tuple(tuple) = elem else {
+            return Err(expected());
+        };
+        let mut pins = tuple.elems.into_iter();
+        let (Some(a), Some(b), None) = (pins.next(), pins.next(), pins.next()) else {
+            return Err(expected());
+        };
+        encoders.a_pins.push(a);
+        encoders.b_pins.push(b);
+
+        let position = |name: String| {
+            config
+                .matrix_position(&name)
+                .ok_or_else(|| error(format!("Missing matrix position for {name}")))
+        };
+        encoders.keys.push([
+            position(format!("enc{i}_cw"))?,
+            position(format!("enc{i}_ccw"))?,
+        ]);
+    }
+
+    Ok(encoders)
+}
+
+#[derive(Debug, PartialEq)]
+enum SplitRole {
+    Primary {
+        row_offset: usize,
+        col_offset: usize,
+    },
+    Secondary,
+}
+
+/// The `split` firmware config; which is `primary` or `secondary`. The primary half places the
+/// secondary half's keys at `split_row_offset` and `split_col_offset` in its matrix; which must
+/// stay within 255 rows and columns given the `rows` by `cols` matrix of each half.
+fn parse_split_role(
+    config: &KeyboardConfig,
+    rows: usize,
+    cols: usize,
+) -> Result<Option<SplitRole>> {
+    let Some(vr) = config.firmware_get("split") else {
+        return Ok(None);
+    };
+    let error = |message: String, vr: &SourceRange| {
+        BuildError::compile_err(
+            ConfigError::new(message, vr.start..vr.end),
+            config.path.as_path(),
+            config.source,
+        )
+    };
+    let offset = |key: &str, size: usize, name: &str| {
+        config.firmware_get(key).map_or(Ok(0), |vr| {
+            let offset = config
+                .trim_value(&vr)
+                .parse::<usize>()
+                .map_err(|_| error("Expected a number".into(), &vr))?;
+            if offset.saturating_add(size) > 255 {
+                return Err(error(
+                    format!("The matrix would have more than 255 {name}"),
+                    &vr,
+                ));
+            }
+            Ok(offset)
+        })
+    };
+
+    match config.trim_value(&vr) {
+        "primary" => Ok(Some(SplitRole::Primary {
+            row_offset: offset("split_row_offset", rows, "rows")?,
+            col_offset: offset("split_col_offset", cols, "columns")?,
+        })),
+        "secondary" => Ok(Some(SplitRole::Secondary)),
+        _ => Err(error("Expected primary or secondary".into(), &vr)),
+    }
+}
+
+/// The macros that run the split link over a UART. The secondary half only scans its keys and sends
+/// them to the primary half.
+fn quote_split(
+    role: Option<SplitRole>,
+    uart: TokenStream,
+    tx_pin: TokenStream,
+    rx_pin: TokenStream,
+) -> TokenStream {
+    let Some(role) = role else {
+        return quote! {
+            macro_rules! config_split_secondary {
+                ($($t:tt)*) => {};
+            }
+
+            macro_rules! run_split_primary {
+                (peripherals: $p:ident, $channel:expr, $run:expr) => {
+                    $run.await
+                };
+            }
+        };
+    };
+
+    let uart_irq = proc_macro2::Ident::new(
+        format!("{uart}_IRQ").as_str(),
+        proc_macro2::Span::call_site(),
+    );
+
+    let common = quote! {
+        bind_interrupts!(struct SplitIrqs {
+            #uart_irq => rp::uart::BufferedInterruptHandler<rp::peripherals::#uart>;
+        });
+
+        static SPLIT_TX_BUF: StaticCell<[u8; 32]> = StaticCell::new();
+        static SPLIT_RX_BUF: StaticCell<[u8; 64]> = StaticCell::new();
+
+        macro_rules! config_split_uart {
+            (peripherals: $p:ident) => {
+                rp::uart::BufferedUart::new(
+                    $p.#uart,
+                    $p.#tx_pin,
+                    $p.#rx_pin,
+                    SplitIrqs,
+                    SPLIT_TX_BUF.init([0; 32]),
+                    SPLIT_RX_BUF.init([0; 64]),
+                    rp::uart::Config::default(),
+                )
+                .split()
+            };
+        }
+    };
+
+    match role {
+        SplitRole::Primary {
+            row_offset,
+            col_offset,
+        } => {
+            let (row_offset, col_offset) = (row_offset as u8, col_offset as u8);
+            quote! {
+                #common
+
+                macro_rules! config_split_secondary {
+                    ($($t:tt)*) => {};
+                }
+
+                macro_rules! run_split_primary {
+                    (peripherals: $p:ident, $channel:expr, $run:expr) => {{
+                        let (tx, rx) = config_split_uart!(peripherals: $p);
+                        let primary = rpk_builder::split::Primary::new(
+                            $channel, #row_offset, #col_offset, ROW_COUNT as u8, COL_COUNT as u8);
+                        match rp::select::select($run, primary.run(rx, tx)).await {
+                            rp::select::Either::First(n) => n,
+                            rp::select::Either::Second(n) => n,
+                        }
+                    }};
+                }
+            }
+        }
+        SplitRole::Secondary => quote! {
+            #common
+
+            macro_rules! config_split_secondary {
+                (peripherals: $p:ident, $input_pins:expr, $output_pins:expr, $channel:expr,
+                 $bus_state:expr) => {{
+                    let (tx, rx) = config_split_uart!(peripherals: $p);
+                    let secondary = rpk_builder::split::Secondary::new($channel);
+                    rp::select::select(
+                        run_scanner($input_pins, $output_pins, $channel, $bus_state),
+                        secondary.run(rx, tx),
+                    )
+                    .await;
+                    unreachable!()
+                }};
+            }
+
+            macro_rules! run_split_primary {
+                (peripherals: $p:ident, $channel:expr, $run:expr) => {
+                    $run.await
+                };
+            }
+        },
+    }
+}
+
+fn parse_firmware(
+    config: &KeyboardConfig,
+) -> Result<(
+    TokenStream,
+    TokenStream,
+    TokenStream,
+    TokenStream,
+    TokenStream,
+)> {
     struct SynIdent<'a>(&'a KeyboardConfig<'a>, bool);
     impl<'a> SynIdent<'a> {
         fn get_range(&mut self, key: &str) -> Result<SourceRange> {
@@ -299,7 +626,6 @@ fn parse_firmware(config: &KeyboardConfig) -> Result<(TokenStream, TokenStream,
         .map_err(|e| BuildError(e.to_string()))?;
     parse!(vendor_id);
     parse!(product_id);
-    parse!(row_is_output);
     parse!(max_layout_size);
 
     parse!(flash_size);
@@ -308,8 +634,18 @@ fn parse_firmware(config: &KeyboardConfig) -> Result<(TokenStream, TokenStream,
     parse!(fs_max_files);
 
     get!(chip);
-    parse!(PIN: input_pins);
-    parse!(PIN: output_pins);
+
+    // Direct pins are scanned as the input pins of a single row matrix with no output pins.
+    let direct_pins = config.firmware_get("direct_pins").is_some();
+    let (input_pins, output_pins, row_is_output) = if direct_pins {
+        parse!(PIN: direct_pins);
+        (direct_pins, quote! {[]}, quote! {false})
+    } else {
+        parse!(PIN: input_pins);
+        parse!(PIN: output_pins);
+        parse!(row_is_output);
+        (input_pins, output_pins, row_is_output)
+    };
 
     if chip != "rp2040" {
         let vr = config.firmware_get("chip").unwrap();
@@ -331,12 +667,52 @@ fn parse_firmware(config: &KeyboardConfig) -> Result<(TokenStream, TokenStream,
     let input_n = syn_array_len(&input_pins)?;
     let output_n = syn_array_len(&output_pins)?;
 
-    let (row_count, col_count) = if syn_bool(&row_is_output)? {
+    let indicator_pins = if config.firmware_get("indicator_pins").is_some() {
+        parse!(PIN: indicator_pins);
+        indicator_pins
+    } else {
+        quote! {[]}
+    };
+    let indicator_n = syn_array_len(&indicator_pins)?;
+    let indicators = parse_indicators(config, indicator_n)?;
+
+    let encoder_keys = parse_encoders(config)?.keys;
+    let encoder_n = encoder_keys.len();
+
+    let (mut row_count, mut col_count) = if direct_pins {
+        (1, input_n)
+    } else if syn_bool(&row_is_output)? {
         (output_n, input_n)
     } else {
         (input_n, output_n)
     };
 
+    // encoder steps are reported at their matrix positions
+    for pos in encoder_keys.iter().flatten() {
+        row_count = row_count.max((pos >> 8) as usize + 1);
+        col_count = col_count.max((pos & 0xff) as usize + 1);
+    }
+    let encoder_keys = encoder_keys.iter().map(|[cw, ccw]| quote! {[#cw, #ccw]});
+
+    let split_role = parse_split_role(config, row_count, col_count)?;
+    let split_macros = if split_role.is_some() {
+        if let Some(SplitRole::Primary {
+            row_offset,
+            col_offset,
+        }) = split_role
+        {
+            // both halves have the same matrix size
+            row_count += row_offset;
+            col_count += col_offset;
+        }
+        parse!(PIN: split_uart);
+        parse!(PIN: split_tx_pin);
+        parse!(PIN: split_rx_pin);
+        quote_split(split_role, split_uart, split_tx_pin, split_rx_pin)
+    } else {
+        quote_split(None, quote! {}, quote! {}, quote! {})
+    };
+
     Ok((
         quote! {
             const LAYOUT_MAPPING: &[u16] = #layout_mapping;
@@ -346,7 +722,12 @@ fn parse_firmware(config: &KeyboardConfig) -> Result<(TokenStream, TokenStream,
             const ROW_COUNT: usize = #row_count;
             const COL_COUNT: usize = #col_count;
             const ROW_IS_OUTPUT: bool = #row_is_output;
+            const DIRECT_PINS: bool = #direct_pins;
             const LAYOUT_MAX: usize = #max_layout_size;
+            const INDICATOR_N: usize = #indicator_n;
+            static INDICATORS: [rpk_builder::indicator::Indicator; INDICATOR_N] = [#(#indicators),*];
+            const ENCODER_N: usize = #encoder_n;
+            static ENCODER_KEYS: [[u16; 2]; ENCODER_N] = [#(#encoder_keys),*];
 
             const FLASH_SIZE: usize = #flash_size;
             const FS_BASE: usize = #fs_base;
@@ -366,6 +747,8 @@ fn parse_firmware(config: &KeyboardConfig) -> Result<(TokenStream, TokenStream,
         },
         input_pins,
         output_pins,
+        indicator_pins,
+        split_macros,
     ))
 }
 
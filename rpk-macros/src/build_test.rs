@@ -75,11 +75,18 @@ fn quote_conf_with_valid_config() {
 
     let mut vis = Visitor(HashMap::new());
     vis.visit_file(&ast);
-    assert_eq!(vis.0.len(), 18);
-    assert_eq!(vis.0.get("LAYOUT_MAPPING").unwrap(),
-        "{constM:[u16;29]=[1,771,7,0,0,8,9,10,11,12,13,23,24,1,2,4,8,64,0,36,37,38,33,34,35,30,31,32,0];&M}");
+    assert_eq!(vis.0.len(), 23);
+    assert_eq!(
+        vis.0.get("LAYOUT_MAPPING").unwrap(),
+        "{constM:[u16;29]=[2,771,7,0,0,8,9,10,11,12,13,23,24,1,2,4,8,64,0,36,37,38,33,34,35,30,31,32,0];&M}"
+    );
 
     assert_eq!(vis.0.get("INPUT_N").unwrap(), "3usize");
+    assert_eq!(vis.0.get("INDICATOR_N").unwrap(), "1usize");
+    assert_eq!(
+        vis.0.get("INDICATORS").unwrap(),
+        "[rpk_builder::indicator::Indicator::HostLed(2u8)]"
+    );
     assert_eq!(vis.0.get("FS_SIZE").unwrap(), "FLASH_SIZE-FS_BASE");
 
     let cfg = vis.0.get("CONFIG_BUILDER").unwrap();
@@ -90,6 +97,231 @@ fn quote_conf_with_valid_config() {
     assert_eq!(vis.0.get("REPORT_BUFFER_SIZE").unwrap(), "32");
 }
 
+#[test]
+fn quote_conf_with_direct_pins() {
+    const LAYOUT: &str = "test/direct-pins-layout.rpk.conf";
+    let cargo = &PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+
+    let res = quote_conf(&cargo.join(LAYOUT)).unwrap();
+    let ast: syn::File = syn::parse2(res).unwrap();
+
+    struct Visitor(HashMap<String, String>);
+    impl<'ast> Visit<'ast> for Visitor {
+        fn visit_item_const(&mut self, i: &'ast syn::ItemConst) {
+            self.0.insert(
+                i.ident.to_string(),
+                i.expr.to_token_stream().to_string().replace(" ", ""),
+            );
+        }
+    }
+
+    let mut vis = Visitor(HashMap::new());
+    vis.visit_file(&ast);
+    assert_eq!(vis.0.get("DIRECT_PINS").unwrap(), "true");
+    assert_eq!(vis.0.get("INPUT_N").unwrap(), "4usize");
+    assert_eq!(vis.0.get("OUTPUT_N").unwrap(), "0usize");
+    assert_eq!(vis.0.get("ROW_COUNT").unwrap(), "1usize");
+    assert_eq!(vis.0.get("COL_COUNT").unwrap(), "4usize");
+    assert_eq!(vis.0.get("ROW_IS_OUTPUT").unwrap(), "false");
+}
+
+#[test]
+fn test_parse_indicators() {
+    let src = r#"
+[matrix:1x2]
+0x00 = a b
+
+[nav]
+a = left
+
+[firmware]
+indicators = [numlock, oneshot nav]
+"#;
+    let config = compile(PathBuf::from("test.conf"), src).unwrap();
+    let indicators = parse_indicators(&config, 3)
+        .unwrap()
+        .iter()
+        .map(|t| t.to_string().replace(" ", ""))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        indicators,
+        [
+            "rpk_builder::indicator::Indicator::HostLed(1u8)",
+            "rpk_builder::indicator::Indicator::Oneshot",
+            "rpk_builder::indicator::Indicator::Layer(6u16)",
+        ]
+    );
+
+    let err = parse_indicators(&config, 2).err().unwrap().to_string();
+    assert!(
+        err.starts_with("error: Expected 2 indicators; one for each indicator pin"),
+        "{}",
+        err
+    );
+
+    let config = compile(
+        PathBuf::from("test.conf"),
+        "[firmware]\nindicators = [numlock, foo]\n",
+    )
+    .unwrap();
+    let err = parse_indicators(&config, 2).err().unwrap().to_string();
+    assert!(err.starts_with("error: Unknown indicator foo"), "{}", err);
+
+    let config = compile(PathBuf::from("test.conf"), "[firmware]\n").unwrap();
+    assert!(parse_indicators(&config, 0).unwrap().is_empty());
+    assert_eq!(
+        parse_indicators(&config, 1).err().unwrap().to_string(),
+        "Missing required firmware config: indicators"
+    );
+}
+
+#[test]
+fn test_parse_split() {
+    let config = compile(PathBuf::from("test.conf"), "[firmware]\n").unwrap();
+    assert_eq!(parse_split_role(&config, 4, 6).unwrap(), None);
+
+    let config = compile(
+        PathBuf::from("test.conf"),
+        "[firmware]\nsplit = primary\nsplit_col_offset = 6\n",
+    )
+    .unwrap();
+    assert_eq!(
+        parse_split_role(&config, 4, 6).unwrap(),
+        Some(SplitRole::Primary {
+            row_offset: 0,
+            col_offset: 6
+        })
+    );
+
+    let config = compile(
+        PathBuf::from("test.conf"),
+        "[firmware]\nsplit = secondary\n",
+    )
+    .unwrap();
+    assert_eq!(
+        parse_split_role(&config, 4, 6).unwrap(),
+        Some(SplitRole::Secondary)
+    );
+
+    let config = compile(PathBuf::from("test.conf"), "[firmware]\nsplit = left\n").unwrap();
+    let err = parse_split_role(&config, 4, 6).err().unwrap().to_string();
+    assert!(
+        err.starts_with("error: Expected primary or secondary"),
+        "{}",
+        err
+    );
+
+    let config = compile(
+        PathBuf::from("test.conf"),
+        "[firmware]\nsplit = primary\nsplit_row_offset = x\n",
+    )
+    .unwrap();
+    let err = parse_split_role(&config, 4, 6).err().unwrap().to_string();
+    assert!(err.starts_with("error: Expected a number"), "{}", err);
+
+    // offsets are limited to matrices of 255 rows and columns
+    let config = compile(
+        PathBuf::from("test.conf"),
+        "[firmware]\nsplit = primary\nsplit_row_offset = 251\nsplit_col_offset = 249\n",
+    )
+    .unwrap();
+    assert_eq!(
+        parse_split_role(&config, 4, 6).unwrap(),
+        Some(SplitRole::Primary {
+            row_offset: 251,
+            col_offset: 249
+        })
+    );
+
+    let config = compile(
+        PathBuf::from("test.conf"),
+        "[firmware]\nsplit = primary\nsplit_col_offset = 300\n",
+    )
+    .unwrap();
+    let err = parse_split_role(&config, 4, 6).err().unwrap().to_string();
+    assert!(
+        err.starts_with("error: The matrix would have more than 255 columns"),
+        "{}",
+        err
+    );
+    assert!(err.contains("300"), "{}", err);
+
+    let config = compile(
+        PathBuf::from("test.conf"),
+        "[firmware]\nsplit = primary\nsplit_row_offset = 252\n",
+    )
+    .unwrap();
+    let err = parse_split_role(&config, 4, 6).err().unwrap().to_string();
+    assert!(
+        err.starts_with("error: The matrix would have more than 255 rows"),
+        "{}",
+        err
+    );
+
+    let split = quote_split(
+        Some(SplitRole::Primary {
+            row_offset: 0,
+            col_offset: 6,
+        }),
+        quote! {UART0},
+        quote! {PIN_0},
+        quote! {PIN_1},
+    )
+    .to_string()
+    .replace(" ", "");
+    assert!(
+        split.contains("UART0_IRQ=>rp::uart::BufferedInterruptHandler<rp::peripherals::UART0>")
+    );
+    assert!(split.contains(
+        "rpk_builder::split::Primary::new($channel,0u8,6u8,ROW_COUNTasu8,COL_COUNTasu8)"
+    ));
+}
+
+#[test]
+fn test_parse_encoders() {
+    let config = compile(PathBuf::from("test.conf"), "[firmware]\n").unwrap();
+    let encoders = parse_encoders(&config).unwrap();
+    assert!(encoders.a_pins.is_empty() && encoders.keys.is_empty());
+
+    let src = r#"
+[matrix:2x2]
+0x00 = a b
+0x10 = enc0_cw enc0_ccw
+
+[firmware]
+encoders = [(PIN_2, PIN_3)]
+"#;
+    let config = compile(PathBuf::from("test.conf"), src).unwrap();
+    let encoders = parse_encoders(&config).unwrap();
+    assert_eq!(encoders.a_pins[0].to_token_stream().to_string(), "PIN_2");
+    assert_eq!(encoders.b_pins[0].to_token_stream().to_string(), "PIN_3");
+    assert_eq!(encoders.keys, [[0x0100, 0x0101]]);
+
+    let config = compile(
+        PathBuf::from("test.conf"),
+        "[firmware]\nencoders = [(PIN_2, PIN_3)]\n",
+    )
+    .unwrap();
+    let err = parse_encoders(&config).err().unwrap().to_string();
+    assert!(
+        err.starts_with("error: Missing matrix position for enc0_cw"),
+        "{}",
+        err
+    );
+
+    let config = compile(
+        PathBuf::from("test.conf"),
+        "[firmware]\nencoders = [PIN_2, PIN_3]\n",
+    )
+    .unwrap();
+    let err = parse_encoders(&config).err().unwrap().to_string();
+    assert!(
+        err.starts_with("error: Expected [(<pin a>, <pin b>), ...]"),
+        "{}",
+        err
+    );
+}
+
 #[test]
 fn test_syn_array_len() {
     let pins = quote! {[PIN_1, PIN_2,P3,]};